@@ -0,0 +1,140 @@
+// Files de la pipeline face à crossbeam::queue::ArrayQueue (cargo bench --bench queues).
+// Le module est inclus tel quel : le crate n'expose pas de bibliothèque.
+// Sans harnais de test, ses modules de tests se réduisent à leurs imports.
+#[allow(dead_code, unused_imports)]
+#[path = "../src/sync/mod.rs"]
+mod sync;
+
+use std::hint::black_box;
+use std::sync::Arc;
+use std::thread;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crossbeam::queue::ArrayQueue;
+use crate::sync::{mpmc, spsc};
+
+// Profondeurs réellement utilisées entre les étages (QueueSizes par défaut)
+const CAPACITIES: [usize; 2] = [4, 64];
+const ITEMS: u64 = 100_000;
+
+// Aller-retour sans contention : coût propre d'un push + pop
+fn uncontended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("uncontended");
+    group.throughput(Throughput::Elements(1));
+    for capacity in CAPACITIES {
+        let (mut tx, mut rx) = spsc::channel::<u64>(capacity);
+        group.bench_with_input(BenchmarkId::new("spsc", capacity), &capacity, |b, _| {
+            b.iter(|| {
+                tx.try_push(black_box(1)).unwrap();
+                rx.try_pop().unwrap()
+            })
+        });
+
+        let (tx, rx) = mpmc::bounded::<u64>(capacity);
+        group.bench_with_input(BenchmarkId::new("mpmc", capacity), &capacity, |b, _| {
+            b.iter(|| {
+                tx.try_push(black_box(1)).unwrap();
+                rx.try_pop().unwrap()
+            })
+        });
+
+        let queue = ArrayQueue::<u64>::new(capacity);
+        group.bench_with_input(BenchmarkId::new("array_queue", capacity), &capacity, |b, _| {
+            b.iter(|| {
+                queue.push(black_box(1)).unwrap();
+                queue.pop().unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+// Réessaie jusqu'à réussite ; yield plutôt que spin pour rester mesurable sur peu de cœurs
+fn retry<T>(mut attempt: impl FnMut() -> Option<T>) -> T {
+    loop {
+        if let Some(value) = attempt() {
+            return value;
+        }
+        thread::yield_now();
+    }
+}
+
+// Un producteur, un consommateur sur deux threads (cas decode → convert)
+fn one_to_one(c: &mut Criterion) {
+    let mut group = c.benchmark_group("one_to_one");
+    group.throughput(Throughput::Elements(ITEMS));
+    for capacity in CAPACITIES {
+        group.bench_with_input(BenchmarkId::new("spsc", capacity), &capacity, |b, &capacity| {
+            b.iter(|| {
+                let (mut tx, mut rx) = spsc::channel::<u64>(capacity);
+                let producer = thread::spawn(move || (0..ITEMS).for_each(|i| retry(|| tx.try_push(i).ok())));
+                let sum: u64 = (0..ITEMS).map(|_| retry(|| rx.try_pop())).sum();
+                producer.join().unwrap();
+                sum
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("mpmc", capacity), &capacity, |b, &capacity| {
+            b.iter(|| {
+                let (tx, rx) = mpmc::bounded::<u64>(capacity);
+                let producer = thread::spawn(move || (0..ITEMS).for_each(|i| retry(|| tx.try_push(i).ok())));
+                let sum: u64 = (0..ITEMS).map(|_| retry(|| rx.try_pop())).sum();
+                producer.join().unwrap();
+                sum
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("array_queue", capacity), &capacity, |b, &capacity| {
+            b.iter(|| {
+                let queue = Arc::new(ArrayQueue::<u64>::new(capacity));
+                let tx = queue.clone();
+                let producer = thread::spawn(move || (0..ITEMS).for_each(|i| retry(|| tx.push(i).ok())));
+                let sum: u64 = (0..ITEMS).map(|_| retry(|| queue.pop())).sum();
+                producer.join().unwrap();
+                sum
+            })
+        });
+    }
+    group.finish();
+}
+
+// Plusieurs sessions WebSocket poussent dans la file d'ingest, un seul étage la vide
+fn many_to_one(c: &mut Criterion) {
+    const PRODUCERS: u64 = 4;
+    let capacity = 64;
+    let mut group = c.benchmark_group("many_to_one");
+    group.throughput(Throughput::Elements(ITEMS));
+
+    group.bench_function(BenchmarkId::new("mpmc", capacity), |b| {
+        b.iter(|| {
+            let (tx, rx) = mpmc::bounded::<u64>(capacity);
+            let producers: Vec<_> = (0..PRODUCERS)
+                .map(|_| {
+                    let tx = tx.clone();
+                    thread::spawn(move || (0..ITEMS / PRODUCERS).for_each(|i| retry(|| tx.try_push(i).ok())))
+                })
+                .collect();
+            let sum: u64 = (0..ITEMS).map(|_| retry(|| rx.try_pop())).sum();
+            producers.into_iter().for_each(|p| p.join().unwrap());
+            sum
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("array_queue", capacity), |b| {
+        b.iter(|| {
+            let queue = Arc::new(ArrayQueue::<u64>::new(capacity));
+            let producers: Vec<_> = (0..PRODUCERS)
+                .map(|_| {
+                    let tx = queue.clone();
+                    thread::spawn(move || (0..ITEMS / PRODUCERS).for_each(|i| retry(|| tx.push(i).ok())))
+                })
+                .collect();
+            let sum: u64 = (0..ITEMS).map(|_| retry(|| queue.pop())).sum();
+            producers.into_iter().for_each(|p| p.join().unwrap());
+            sum
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, uncontended, one_to_one, many_to_one);
+criterion_main!(benches);
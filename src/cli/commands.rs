@@ -0,0 +1,289 @@
+use clap::Parser;
+use std::path::PathBuf;
+use crate::codec::convert::PixelFormat;
+use crate::codec::rotate::Rotation;
+use crate::codec::scale::{FitMode, ScaleFilter};
+use crate::config::Config;
+use crate::logging::LogFormat;
+use crate::metrics::Stage;
+use crate::pipeline::affinity::StagePlacement;
+use crate::record::Container;
+use crate::sink::SinkConfig;
+use crate::v4l2::device::IoMode;
+
+#[derive(Parser, Debug, Clone)]
+#[command(name = "phonecam-ultimate", version, about = "Smartphone → webcam virtuelle V4L2")]
+pub struct Args {
+    /// Fichier de configuration TOML (défaut : ~/.config/phonecam/config.toml) ; les options ci-dessous le surchargent
+    #[arg(long, value_name = "FICHIER")]
+    pub config: Option<PathBuf>,
+
+    /// Port du serveur web, 0 : choisi par le système [défaut : 8080]
+    #[arg(long)]
+    pub http_port: Option<u16>,
+
+    /// Port du relais UDP, 0 : choisi par le système [défaut : 9999]
+    #[arg(long)]
+    pub udp_port: Option<u16>,
+
+    /// Numéro du périphérique v4l2loopback (/dev/videoN) [défaut : 10]
+    #[arg(long)]
+    pub video_nr: Option<u16>,
+
+    /// Nom de la caméra virtuelle créée via /dev/v4l2loopback [défaut : PhoneCam Ultimate]
+    #[arg(long)]
+    pub card_label: Option<String>,
+
+    /// Ne pas créer /dev/videoN s'il n'existe pas (loopback préparée par modprobe)
+    #[arg(long)]
+    pub no_create_loopback: bool,
+
+    /// Lister les périphériques vidéo (loopback ou non) puis quitter
+    #[arg(long)]
+    pub list_devices: bool,
+
+    /// Supprimer la loopback /dev/videoN puis quitter
+    #[arg(long, value_name = "N")]
+    pub remove_loopback: Option<u16>,
+
+    /// Format de pixels écrit sur la caméra virtuelle (i420, nv12, yuyv, uyvy, rgb24, bgra, grey) [défaut : yuyv]
+    #[arg(long, value_parser = parse_pixel_format)]
+    pub pixel_format: Option<PixelFormat>,
+
+    /// Mode d'écriture vers /dev/videoN : write, mmap ou dmabuf (streaming, repli sur mmap si indisponible) [défaut : write]
+    #[arg(long, value_parser = parse_io_mode)]
+    pub io: Option<IoMode>,
+
+    /// Destination des images : v4l2, file:CHEMIN (.yuv brut ou .y4m) ou stdout [défaut : v4l2]
+    #[arg(long, value_parser = parse_sink)]
+    pub sink: Option<SinkConfig>,
+
+    /// Résolution fixe de la caméra virtuelle (LARGEURxHAUTEUR, dimensions paires) [défaut : 1280x720]
+    #[arg(long, value_parser = parse_resolution)]
+    pub resolution: Option<(usize, usize)>,
+
+    /// Filtre de mise à l'échelle (bilinear, area, lanczos) [défaut : bilinear]
+    #[arg(long, value_parser = parse_scale_filter)]
+    pub scale_filter: Option<ScaleFilter>,
+
+    /// Adaptation au cadre : fit (bandes noires), fill (rognage) ou stretch [défaut : fit]
+    #[arg(long, value_parser = parse_fit)]
+    pub fit: Option<FitMode>,
+
+    /// Rotation horaire forcée (0, 90, 180, 270) ; par défaut, suit l'orientation du téléphone
+    #[arg(long, value_parser = parse_rotation)]
+    pub rotate: Option<Rotation>,
+
+    /// Miroir horizontal forcé
+    #[arg(long)]
+    pub flip_h: bool,
+
+    /// Miroir vertical forcé
+    #[arg(long)]
+    pub flip_v: bool,
+
+    /// Cadence fixe de la caméra virtuelle (images/s) [défaut : 30]
+    #[arg(long, value_parser = parse_fps)]
+    pub output_fps: Option<u32>,
+
+    /// Ne rien écrire quand le flux du téléphone s'interrompt (ni répétition ni image noire)
+    #[arg(long)]
+    pub no_stall_repeat: bool,
+
+    /// Durée de répétition de la dernière image avant de passer au noir (ms) [défaut : 2000]
+    #[arg(long)]
+    pub max_repeat_ms: Option<u64>,
+
+    /// Décoder toutes les images même quand aucune application ne lit /dev/videoN
+    #[arg(long)]
+    pub always_decode: bool,
+
+    /// Plafond du débit d'encodage des téléphones (kbit/s)
+    #[arg(long, value_name = "KBPS")]
+    pub max_bitrate_kbps: Option<u32>,
+
+    /// Épingle un étage sur des CPUs, avec nœud NUMA optionnel : ETAGE=CPUS[@NOEUD], ex. decode=2-3@0 (répétable).
+    /// Étages depacketize, decode, convert, output ; ingest tourne sur les workers tokio et n'est pas épinglable
+    #[arg(long = "pin", value_parser = parse_pin)]
+    pub pins: Vec<(Stage, StagePlacement)>,
+
+    /// Priorité temps réel SCHED_FIFO des threads de la pipeline (1-99, nécessite CAP_SYS_NICE)
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..=99))]
+    pub realtime: Option<i32>,
+
+    /// Enregistrer dès le lancement (sinon : démarrage depuis le dashboard)
+    #[arg(long)]
+    pub record: bool,
+
+    /// Dossier des enregistrements [défaut : recordings]
+    #[arg(long)]
+    pub record_dir: Option<PathBuf>,
+
+    /// Conteneur du flux enregistré, sans réencodage : mp4 ou mkv [défaut : mp4]
+    #[arg(long, value_parser = parse_container)]
+    pub record_container: Option<Container>,
+
+    /// Enregistrer aussi les images de sortie en Y4M (volumineux)
+    #[arg(long)]
+    pub record_y4m: bool,
+
+    /// Nouveau fichier au-delà de cette taille (Mo)
+    #[arg(long)]
+    pub record_max_mb: Option<u64>,
+
+    /// Nouveau fichier au-delà de cette durée (secondes)
+    #[arg(long)]
+    pub record_max_secs: Option<u64>,
+
+    /// Niveau des logs, syntaxe RUST_LOG (ex. debug, info,phonecam_ultimate::pipeline=trace) ; prioritaire sur RUST_LOG
+    #[arg(long, value_name = "FILTRE", value_parser = parse_log_filter)]
+    pub log_level: Option<String>,
+
+    /// Format des logs sur stderr : text ou json [défaut : text]
+    #[arg(long, value_parser = parse_log_format)]
+    pub log_format: Option<LogFormat>,
+}
+
+pub(crate) fn parse_fps(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(fps @ (15 | 24 | 30 | 60)) => Ok(fps),
+        _ => Err(format!("'{}' : valeurs acceptées 15, 24, 30 ou 60", value)),
+    }
+}
+
+pub(crate) fn parse_pixel_format(value: &str) -> Result<PixelFormat, String> {
+    PixelFormat::from_name(value).ok_or_else(|| {
+        let names: Vec<&str> = PixelFormat::ALL.iter().map(|f| f.name()).collect();
+        format!("'{}' : formats acceptés {}", value, names.join(", "))
+    })
+}
+
+pub(crate) fn parse_resolution(value: &str) -> Result<(usize, usize), String> {
+    let parsed = value
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.trim().parse::<usize>().ok()?, h.trim().parse::<usize>().ok()?)));
+    match parsed {
+        Some((w, h)) if w >= 2 && h >= 2 && w % 2 == 0 && h % 2 == 0 && w <= 7680 && h <= 4320 => Ok((w, h)),
+        _ => Err(format!("'{}' : attendu LARGEURxHAUTEUR pairs, ex. 1280x720", value)),
+    }
+}
+
+pub(crate) fn parse_io_mode(value: &str) -> Result<IoMode, String> {
+    IoMode::from_name(value).ok_or_else(|| format!("'{}' : modes acceptés write, mmap, dmabuf", value))
+}
+
+pub(crate) fn parse_sink(value: &str) -> Result<SinkConfig, String> {
+    SinkConfig::from_name(value).ok_or_else(|| format!("'{}' : sorties acceptées v4l2, file:CHEMIN, stdout", value))
+}
+
+pub(crate) fn parse_container(value: &str) -> Result<Container, String> {
+    Container::from_name(value).ok_or_else(|| format!("'{}' : conteneurs acceptés mp4, mkv", value))
+}
+
+pub(crate) fn parse_scale_filter(value: &str) -> Result<ScaleFilter, String> {
+    ScaleFilter::from_name(value).ok_or_else(|| format!("'{}' : filtres acceptés bilinear, area, lanczos", value))
+}
+
+pub(crate) fn parse_fit(value: &str) -> Result<FitMode, String> {
+    FitMode::from_name(value).ok_or_else(|| format!("'{}' : modes acceptés fit, fill, stretch", value))
+}
+
+// Ingest tourne sur les workers tokio, pas sur un thread d'étage : rien à épingler
+pub(crate) fn parse_pin(value: &str) -> Result<(Stage, StagePlacement), String> {
+    let error = || {
+        let names: Vec<&str> = Stage::ALL.iter().filter(|&&s| s != Stage::Ingest).map(|s| s.name()).collect();
+        format!("'{}' : attendu ETAGE=CPUS[@NOEUD], ex. decode=2-3@0 (étages {})", value, names.join(", "))
+    };
+    let (stage, placement) = value.split_once('=').ok_or_else(error)?;
+    let stage = Stage::from_name(stage.trim()).ok_or_else(error)?;
+    if stage == Stage::Ingest {
+        return Err(format!("'{}' : ingest tourne sur les workers tokio et ne peut pas être épinglé, épingler depacketize", value));
+    }
+    Ok((stage, StagePlacement::parse(placement).ok_or_else(error)?))
+}
+
+pub(crate) fn parse_log_filter(value: &str) -> Result<String, String> {
+    tracing_subscriber::EnvFilter::try_new(value)
+        .map(|_| value.to_string())
+        .map_err(|e| format!("'{}' : filtre invalide ({}), ex. info ou warn,phonecam_ultimate::pipeline=debug", value, e))
+}
+
+pub(crate) fn parse_log_format(value: &str) -> Result<LogFormat, String> {
+    LogFormat::from_name(value).ok_or_else(|| format!("'{}' : formats acceptés text, json", value))
+}
+
+fn parse_rotation(value: &str) -> Result<Rotation, String> {
+    value
+        .parse::<u32>()
+        .ok()
+        .and_then(Rotation::from_degrees)
+        .ok_or_else(|| format!("'{}' : rotations acceptées 0, 90, 180, 270", value))
+}
+
+impl Args {
+    // Options données sur la ligne de commande, prioritaires sur le fichier de configuration
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(target: &mut T, value: &Option<T>) {
+            if let Some(value) = value {
+                *target = value.clone();
+            }
+        }
+
+        set(&mut config.server.http_port, &self.http_port);
+        set(&mut config.server.udp_port, &self.udp_port);
+        set(&mut config.loopback.video_nr, &self.video_nr);
+        set(&mut config.loopback.card_label, &self.card_label);
+        if self.no_create_loopback {
+            config.loopback.create = false;
+        }
+
+        let output = &mut config.output;
+        set(&mut output.pixel_format, &self.pixel_format);
+        set(&mut output.io, &self.io);
+        set(&mut output.sink, &self.sink);
+        set(&mut output.resolution, &self.resolution);
+        set(&mut output.scale_filter, &self.scale_filter);
+        set(&mut output.fit, &self.fit);
+        set(&mut output.fps, &self.output_fps);
+        set(&mut output.max_repeat_ms, &self.max_repeat_ms);
+        // Une option d'orientation remplace toute l'orientation du fichier
+        if self.rotate.is_some() || self.flip_h || self.flip_v {
+            output.rotate = self.rotate;
+            output.flip_h = self.flip_h;
+            output.flip_v = self.flip_v;
+        }
+        if self.no_stall_repeat {
+            output.repeat_on_stall = false;
+        }
+        if self.always_decode {
+            output.always_decode = true;
+        }
+
+        if self.max_bitrate_kbps.is_some() {
+            config.phone.max_bitrate_kbps = self.max_bitrate_kbps;
+        }
+        if !self.pins.is_empty() {
+            config.affinity.pins = self.pins.clone();
+        }
+        if self.realtime.is_some() {
+            config.affinity.realtime = self.realtime;
+        }
+
+        let record = &mut config.record;
+        record.start |= self.record;
+        record.y4m |= self.record_y4m;
+        set(&mut record.dir, &self.record_dir);
+        set(&mut record.container, &self.record_container);
+        if self.record_max_mb.is_some() {
+            record.max_mb = self.record_max_mb;
+        }
+        if self.record_max_secs.is_some() {
+            record.max_secs = self.record_max_secs;
+        }
+
+        if self.log_level.is_some() {
+            config.log.level = self.log_level.clone();
+        }
+        set(&mut config.log.format, &self.log_format);
+    }
+}
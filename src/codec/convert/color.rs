@@ -0,0 +1,167 @@
+// Matrices YUV ↔ RGB en virgule fixe (16 bits de fraction), calculées depuis Kr/Kb
+
+const SHIFT: u32 = 16;
+const ONE: f32 = (1 << SHIFT) as f32;
+const HALF: i32 = 1 << (SHIFT - 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMatrix {
+    // SD, et la plupart des flux sans métadonnées < 720p
+    Bt601,
+    #[default]
+    Bt709,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorRange {
+    // 16-235 (Y) / 16-240 (chroma) : la norme pour la vidéo
+    #[default]
+    Limited,
+    // 0-255 (JPEG, certaines caméras de téléphone)
+    Full,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Colorimetry {
+    pub matrix: ColorMatrix,
+    pub range: ColorRange,
+}
+
+impl Colorimetry {
+    pub const fn new(matrix: ColorMatrix, range: ColorRange) -> Self {
+        Self { matrix, range }
+    }
+
+    // Choix habituel quand le flux n'annonce rien : 709 à partir de la HD
+    pub fn guess(height: usize) -> Self {
+        let matrix = if height >= 720 { ColorMatrix::Bt709 } else { ColorMatrix::Bt601 };
+        Self::new(matrix, ColorRange::Limited)
+    }
+
+    fn coefficients(self) -> (f32, f32) {
+        match self.matrix {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+        }
+    }
+
+    // (offset Y, excursion Y, excursion chroma)
+    fn scale(self) -> (i32, f32, f32) {
+        match self.range {
+            ColorRange::Limited => (16, 219.0, 224.0),
+            ColorRange::Full => (0, 255.0, 255.0),
+        }
+    }
+
+    pub fn yuv_to_rgb(self) -> YuvToRgb {
+        let (kr, kb) = self.coefficients();
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_range, c_range) = self.scale();
+        let c = 255.0 / c_range;
+
+        YuvToRgb {
+            y_offset,
+            y_gain: (255.0 / y_range * ONE).round() as i32,
+            r_v: (2.0 * (1.0 - kr) * c * ONE).round() as i32,
+            g_u: (2.0 * kb * (1.0 - kb) / kg * c * ONE).round() as i32,
+            g_v: (2.0 * kr * (1.0 - kr) / kg * c * ONE).round() as i32,
+            b_u: (2.0 * (1.0 - kb) * c * ONE).round() as i32,
+        }
+    }
+
+    pub fn rgb_to_yuv(self) -> RgbToYuv {
+        let (kr, kb) = self.coefficients();
+        let kg = 1.0 - kr - kb;
+        let (y_offset, y_range, c_range) = self.scale();
+        let y = y_range / 255.0 * ONE;
+        let c = c_range / 255.0 * ONE;
+
+        RgbToYuv {
+            y_offset,
+            y: [(kr * y).round() as i32, (kg * y).round() as i32, (kb * y).round() as i32],
+            u: [
+                (-kr / (2.0 * (1.0 - kb)) * c).round() as i32,
+                (-kg / (2.0 * (1.0 - kb)) * c).round() as i32,
+                (0.5 * c).round() as i32,
+            ],
+            v: [
+                (0.5 * c).round() as i32,
+                (-kg / (2.0 * (1.0 - kr)) * c).round() as i32,
+                (-kb / (2.0 * (1.0 - kr)) * c).round() as i32,
+            ],
+        }
+    }
+
+    // Luma seule ramenée en pleine échelle (GREY est toujours 0-255)
+    pub fn luma_to_full(self, y: u8) -> u8 {
+        match self.range {
+            ColorRange::Full => y,
+            ColorRange::Limited => clamp(((y as i32 - 16) * 255 * 2 + 219) / (219 * 2)),
+        }
+    }
+
+    pub fn luma_from_full(self, y: u8) -> u8 {
+        match self.range {
+            ColorRange::Full => y,
+            ColorRange::Limited => (16 + (y as i32 * 219 * 2 + 255) / (255 * 2)) as u8,
+        }
+    }
+}
+
+#[inline(always)]
+fn clamp(v: i32) -> u8 {
+    v.clamp(0, 255) as u8
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct YuvToRgb {
+    y_offset: i32,
+    y_gain: i32,
+    r_v: i32,
+    g_u: i32,
+    g_v: i32,
+    b_u: i32,
+}
+
+impl YuvToRgb {
+    #[inline(always)]
+    pub fn convert(&self, y: u8, u: u8, v: u8) -> [u8; 3] {
+        let y = (y as i32 - self.y_offset) * self.y_gain + HALF;
+        let u = u as i32 - 128;
+        let v = v as i32 - 128;
+
+        [
+            clamp((y + self.r_v * v) >> SHIFT),
+            clamp((y - self.g_u * u - self.g_v * v) >> SHIFT),
+            clamp((y + self.b_u * u) >> SHIFT),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RgbToYuv {
+    y_offset: i32,
+    y: [i32; 3],
+    u: [i32; 3],
+    v: [i32; 3],
+}
+
+impl RgbToYuv {
+    #[inline(always)]
+    pub fn luma(&self, [r, g, b]: [u8; 3]) -> u8 {
+        let (r, g, b) = (r as i32, g as i32, b as i32);
+        clamp(self.y_offset + ((self.y[0] * r + self.y[1] * g + self.y[2] * b + HALF) >> SHIFT))
+    }
+
+    // Chroma d'une couleur moyenne (somme de `n` pixels, pour le sous-échantillonnage)
+    #[inline(always)]
+    pub fn chroma(&self, [r, g, b]: [u32; 3], n: u32) -> (u8, u8) {
+        let (r, g, b) = (r as i32, g as i32, b as i32);
+        let n = n as i32;
+        // Décalage de 128 ajouté avant la division : le numérateur reste positif et l'arrondi juste
+        let bias = ((128 << SHIFT) + HALF) * n;
+        let u = (self.u[0] * r + self.u[1] * g + self.u[2] * b + bias) / n;
+        let v = (self.v[0] * r + self.v[1] * g + self.v[2] * b + bias) / n;
+        (clamp(u >> SHIFT), clamp(v >> SHIFT))
+    }
+}
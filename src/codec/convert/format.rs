@@ -0,0 +1,141 @@
+// Formats de pixels échangés avec FFmpeg et V4L2
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    // YUV 4:2:0 planaire (Y, U, V)
+    I420,
+    // YUV 4:2:0 semi-planaire (Y, UV entrelacés) : sortie VAAPI
+    Nv12,
+    // YUV 4:2:2 entrelacé Y0 U Y1 V
+    Yuyv,
+    // YUV 4:2:2 entrelacé U Y0 V Y1
+    Uyvy,
+    Rgb24,
+    // B G R A en mémoire
+    Bgra,
+    Grey,
+}
+
+// Position d'un plan dans un tampon contigu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneLayout {
+    pub offset: usize,
+    pub stride: usize,
+    pub rows: usize,
+    // Octets utiles par ligne (≤ stride)
+    pub row_bytes: usize,
+}
+
+impl PlaneLayout {
+    pub fn size(&self) -> usize {
+        self.stride * self.rows
+    }
+}
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+impl PixelFormat {
+    pub const ALL: [PixelFormat; 7] = [
+        PixelFormat::I420,
+        PixelFormat::Nv12,
+        PixelFormat::Yuyv,
+        PixelFormat::Uyvy,
+        PixelFormat::Rgb24,
+        PixelFormat::Bgra,
+        PixelFormat::Grey,
+    ];
+
+    // Code V4L2 (V4L2_PIX_FMT_*)
+    pub const fn fourcc(self) -> u32 {
+        match self {
+            PixelFormat::I420 => fourcc(b"YU12"),
+            PixelFormat::Nv12 => fourcc(b"NV12"),
+            PixelFormat::Yuyv => fourcc(b"YUYV"),
+            PixelFormat::Uyvy => fourcc(b"UYVY"),
+            PixelFormat::Rgb24 => fourcc(b"RGB3"),
+            // V4L2_PIX_FMT_ABGR32 : B G R A en mémoire
+            PixelFormat::Bgra => fourcc(b"AR24"),
+            PixelFormat::Grey => fourcc(b"GREY"),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PixelFormat::I420 => "i420",
+            PixelFormat::Nv12 => "nv12",
+            PixelFormat::Yuyv => "yuyv",
+            PixelFormat::Uyvy => "uyvy",
+            PixelFormat::Rgb24 => "rgb24",
+            PixelFormat::Bgra => "bgra",
+            PixelFormat::Grey => "grey",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    pub fn is_yuv(self) -> bool {
+        !matches!(self, PixelFormat::Rgb24 | PixelFormat::Bgra)
+    }
+
+    pub fn plane_count(self) -> usize {
+        match self {
+            PixelFormat::I420 => 3,
+            PixelFormat::Nv12 => 2,
+            _ => 1,
+        }
+    }
+
+    // Octets par pixel des formats entrelacés (premier plan)
+    fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::I420 | PixelFormat::Nv12 | PixelFormat::Grey => 1,
+            PixelFormat::Yuyv | PixelFormat::Uyvy => 2,
+            PixelFormat::Rgb24 => 3,
+            PixelFormat::Bgra => 4,
+        }
+    }
+
+    // Disposition compacte (bytesperline = largeur utile), celle d'un tampon V4L2 "sizeimage"
+    pub fn layout(self, width: usize, height: usize) -> [PlaneLayout; 3] {
+        let (cw, ch) = (width.div_ceil(2), height.div_ceil(2));
+        let main = PlaneLayout {
+            offset: 0,
+            stride: width * self.bytes_per_pixel(),
+            rows: height,
+            row_bytes: width * self.bytes_per_pixel(),
+        };
+        let empty = PlaneLayout {
+            offset: main.size(),
+            stride: 0,
+            rows: 0,
+            row_bytes: 0,
+        };
+
+        match self {
+            PixelFormat::I420 => {
+                let u = PlaneLayout { offset: main.size(), stride: cw, rows: ch, row_bytes: cw };
+                let v = PlaneLayout { offset: u.offset + u.size(), ..u };
+                [main, u, v]
+            }
+            PixelFormat::Nv12 => {
+                let uv = PlaneLayout { offset: main.size(), stride: cw * 2, rows: ch, row_bytes: cw * 2 };
+                [main, uv, PlaneLayout { offset: uv.offset + uv.size(), ..empty }]
+            }
+            _ => [main, empty, empty],
+        }
+    }
+
+    pub fn frame_size(self, width: usize, height: usize) -> usize {
+        self.layout(width, height).iter().map(PlaneLayout::size).sum()
+    }
+}
+
+impl std::fmt::Display for PixelFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
@@ -0,0 +1,518 @@
+pub mod format;
+pub mod color;
+
+pub use color::{ColorMatrix, ColorRange, Colorimetry};
+pub use format::{PixelFormat, PlaneLayout};
+
+use crate::codec::simd::{self, Plane, Yuv420};
+use color::{RgbToYuv, YuvToRgb};
+
+// Image source : jusqu'à trois plans avec leur pas (les plans inutilisés sont vides)
+#[derive(Clone, Copy)]
+pub struct Image<'a> {
+    pub format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    pub planes: [Plane<'a>; 3],
+}
+
+#[derive(Debug)]
+pub enum ConvertError {
+    // Un plan source ne couvre pas width × height (pas trop court ou données tronquées)
+    SourceTooSmall { format: PixelFormat, plane: usize },
+    DestinationTooSmall { format: PixelFormat, needed: usize, got: usize },
+}
+
+impl std::fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConvertError::SourceTooSmall { format, plane } => {
+                write!(f, "plan {} de l'image {} trop petit", plane, format)
+            }
+            ConvertError::DestinationTooSmall { format, needed, got } => {
+                write!(f, "tampon {} trop petit : {} octets requis, {} fournis", format, needed, got)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl<'a> Image<'a> {
+    pub fn new(format: PixelFormat, width: usize, height: usize, planes: [Plane<'a>; 3]) -> Result<Self, ConvertError> {
+        let image = Self { format, width, height, planes };
+        let layout = format.layout(width, height);
+        for (plane, l) in layout.iter().enumerate().take(format.plane_count()) {
+            if !image.planes[plane].covers(l.rows, l.row_bytes) {
+                return Err(ConvertError::SourceTooSmall { format, plane });
+            }
+        }
+        Ok(image)
+    }
+
+    // Image dans un tampon contigu sans remplissage (frame V4L2, fichier .yuv)
+    pub fn packed(format: PixelFormat, data: &'a [u8], width: usize, height: usize) -> Result<Self, ConvertError> {
+        let layout = format.layout(width, height);
+        let needed = format.frame_size(width, height);
+        if data.len() < needed {
+            return Err(ConvertError::SourceTooSmall { format, plane: 0 });
+        }
+        let plane = |p: &PlaneLayout| Plane::new(&data[p.offset..p.offset + p.size()], p.stride);
+        Self::new(format, width, height, [plane(&layout[0]), plane(&layout[1]), plane(&layout[2])])
+    }
+
+    // Vue planaire d'une image I420 (entrée du scaler et des convertisseurs SIMD)
+    pub fn as_yuv420(&self) -> Yuv420<'a> {
+        debug_assert_eq!(self.format, PixelFormat::I420);
+        Yuv420 {
+            y: self.planes[0],
+            u: self.planes[1],
+            v: self.planes[2],
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+// Convertit `src` vers `dst_format`, écrit dans `dst` en disposition compacte (cf. PixelFormat::layout).
+// La colorimétrie sert aux passages YUV ↔ RGB et à la luma GREY (toujours pleine échelle).
+// Pour les conversions ponctuelles : un passage par I420 alloue son tampon intermédiaire.
+pub fn convert(src: &Image, dst: &mut [u8], dst_format: PixelFormat, colorimetry: Colorimetry) -> Result<(), ConvertError> {
+    convert_with(src, dst, dst_format, colorimetry, &mut Vec::new())
+}
+
+// Comme `convert`, avec un tampon intermédiaire gardé d'une frame à l'autre par l'appelant
+pub fn convert_with(
+    src: &Image,
+    dst: &mut [u8],
+    dst_format: PixelFormat,
+    colorimetry: Colorimetry,
+    scratch: &mut Vec<u8>,
+) -> Result<(), ConvertError> {
+    let (width, height) = (src.width, src.height);
+    let needed = dst_format.frame_size(width, height);
+    if dst.len() < needed {
+        return Err(ConvertError::DestinationTooSmall { format: dst_format, needed, got: dst.len() });
+    }
+    let layout = dst_format.layout(width, height);
+    let mut out = split_planes(&mut dst[..needed], &layout);
+
+    match (src.format, dst_format) {
+        (from, to) if from == to => copy_planes(src, &mut out, &layout),
+        (PixelFormat::I420, _) => from_i420(&src.as_yuv420(), &mut out, &layout, dst_format, colorimetry),
+        (_, PixelFormat::I420) => to_i420(src, &mut out, &layout, colorimetry),
+        // Sortie VAAPI vers le format par défaut de la loopback : sans passer par I420
+        (PixelFormat::Nv12, PixelFormat::Yuyv) => nv12_to_yuyv(src, out[0], layout[0].stride),
+        (PixelFormat::Rgb24 | PixelFormat::Bgra, PixelFormat::Rgb24 | PixelFormat::Bgra) => {
+            rgb_to_rgb(src, out[0], dst_format, layout[0].stride)
+        }
+        _ => {
+            let i420_layout = PixelFormat::I420.layout(width, height);
+            scratch.resize(PixelFormat::I420.frame_size(width, height), 0);
+            to_i420(src, &mut split_planes(scratch, &i420_layout), &i420_layout, colorimetry);
+            let tmp = Image::packed(PixelFormat::I420, scratch, width, height)?;
+            from_i420(&tmp.as_yuv420(), &mut out, &layout, dst_format, colorimetry);
+        }
+    }
+    Ok(())
+}
+
+// Image noire dans `dst_format` (sortie sans source) : noir RGB ramené dans la plage de la colorimétrie
+pub fn black_frame(dst_format: PixelFormat, width: usize, height: usize, colorimetry: Colorimetry) -> Vec<u8> {
+    let black = vec![0u8; PixelFormat::Rgb24.frame_size(width, height)];
+    let mut out = vec![0u8; dst_format.frame_size(width, height)];
+    let src = Image::packed(PixelFormat::Rgb24, &black, width, height).expect("tampon RGB aux dimensions");
+    convert(&src, &mut out, dst_format, colorimetry).expect("tampon de sortie aux dimensions");
+    out
+}
+
+fn split_planes<'a>(buf: &'a mut [u8], layout: &[PlaneLayout; 3]) -> [&'a mut [u8]; 3] {
+    let (p0, rest) = buf.split_at_mut(layout[1].offset);
+    let (p1, p2) = rest.split_at_mut(layout[2].offset - layout[1].offset);
+    [p0, p1, p2]
+}
+
+fn copy_planes(src: &Image, out: &mut [&mut [u8]; 3], layout: &[PlaneLayout; 3]) {
+    for plane in 0..src.format.plane_count() {
+        let l = &layout[plane];
+        for row in 0..l.rows {
+            out[plane][row * l.stride..][..l.row_bytes].copy_from_slice(src.planes[plane].row(row, l.row_bytes));
+        }
+    }
+}
+
+fn from_i420(src: &Yuv420, out: &mut [&mut [u8]; 3], layout: &[PlaneLayout; 3], format: PixelFormat, colorimetry: Colorimetry) {
+    let (width, height) = (src.width, src.height);
+    let cw = width.div_ceil(2);
+
+    match format {
+        PixelFormat::I420 => {
+            for (plane, src_plane) in [src.y, src.u, src.v].into_iter().enumerate() {
+                let l = &layout[plane];
+                for row in 0..l.rows {
+                    out[plane][row * l.stride..][..l.row_bytes].copy_from_slice(src_plane.row(row, l.row_bytes));
+                }
+            }
+        }
+        PixelFormat::Nv12 => {
+            for row in 0..height {
+                out[0][row * layout[0].stride..][..width].copy_from_slice(src.y.row(row, width));
+            }
+            for row in 0..layout[1].rows {
+                let (u, v) = (src.u.row(row, cw), src.v.row(row, cw));
+                let uv = &mut out[1][row * layout[1].stride..][..cw * 2];
+                for x in 0..cw {
+                    uv[x * 2] = u[x];
+                    uv[x * 2 + 1] = v[x];
+                }
+            }
+        }
+        PixelFormat::Yuyv => simd::yuv420_to_yuyv(src, out[0], layout[0].stride),
+        PixelFormat::Uyvy => {
+            for row in 0..height {
+                let (y, u, v) = (src.y.row(row, width), src.u.row(row / 2, cw), src.v.row(row / 2, cw));
+                let line = &mut out[0][row * layout[0].stride..][..width * 2];
+                for x in 0..width {
+                    line[x * 2] = if x % 2 == 0 { u[x / 2] } else { v[x / 2] };
+                    line[x * 2 + 1] = y[x];
+                }
+            }
+        }
+        PixelFormat::Rgb24 | PixelFormat::Bgra => {
+            let matrix: YuvToRgb = colorimetry.yuv_to_rgb();
+            let bpp = if format == PixelFormat::Rgb24 { 3 } else { 4 };
+            for row in 0..height {
+                let (y, u, v) = (src.y.row(row, width), src.u.row(row / 2, cw), src.v.row(row / 2, cw));
+                let line = &mut out[0][row * layout[0].stride..][..width * bpp];
+                for (x, px) in line.chunks_exact_mut(bpp).enumerate() {
+                    let [r, g, b] = matrix.convert(y[x], u[x / 2], v[x / 2]);
+                    if bpp == 3 {
+                        px.copy_from_slice(&[r, g, b]);
+                    } else {
+                        px.copy_from_slice(&[b, g, r, 255]);
+                    }
+                }
+            }
+        }
+        PixelFormat::Grey => {
+            for row in 0..height {
+                let line = &mut out[0][row * layout[0].stride..][..width];
+                for (dst, &y) in line.iter_mut().zip(src.y.row(row, width)) {
+                    *dst = colorimetry.luma_to_full(y);
+                }
+            }
+        }
+    }
+}
+
+fn to_i420(src: &Image, out: &mut [&mut [u8]; 3], layout: &[PlaneLayout; 3], colorimetry: Colorimetry) {
+    let (width, height) = (src.width, src.height);
+    let (cw, ch) = (width.div_ceil(2), height.div_ceil(2));
+    let [y_out, u_out, v_out] = out;
+    let (y_stride, c_stride) = (layout[0].stride, layout[1].stride);
+
+    match src.format {
+        PixelFormat::I420 => unreachable!("I420 est converti directement"),
+        PixelFormat::Nv12 => {
+            for row in 0..height {
+                y_out[row * y_stride..][..width].copy_from_slice(src.planes[0].row(row, width));
+            }
+            for row in 0..ch {
+                let uv = src.planes[1].row(row, cw * 2);
+                for x in 0..cw {
+                    u_out[row * c_stride + x] = uv[x * 2];
+                    v_out[row * c_stride + x] = uv[x * 2 + 1];
+                }
+            }
+        }
+        PixelFormat::Yuyv | PixelFormat::Uyvy => {
+            // Positions dans un macropixel de 4 octets
+            let (y_at, u_at, v_at) = if src.format == PixelFormat::Yuyv { (0, 1, 3) } else { (1, 0, 2) };
+            let packed = src.planes[0];
+            for row in 0..height {
+                let line = packed.row(row, width * 2);
+                for x in 0..width {
+                    y_out[row * y_stride + x] = line[x * 2 + y_at];
+                }
+            }
+            // 4:2:2 → 4:2:0 : moyenne des deux lignes de chaque paire
+            for row in 0..ch {
+                let top = packed.row(row * 2, width * 2);
+                let bottom = packed.row((row * 2 + 1).min(height - 1), width * 2);
+                for x in 0..cw {
+                    let avg = |at: usize| (top[x * 4 + at] as u16 + bottom[x * 4 + at] as u16).div_ceil(2) as u8;
+                    u_out[row * c_stride + x] = avg(u_at);
+                    // Largeur impaire : le dernier macropixel n'a pas de V, on reprend U
+                    v_out[row * c_stride + x] = if x * 4 + v_at < width * 2 { avg(v_at) } else { avg(u_at) };
+                }
+            }
+        }
+        PixelFormat::Rgb24 | PixelFormat::Bgra => {
+            let matrix: RgbToYuv = colorimetry.rgb_to_yuv();
+            let bpp = if src.format == PixelFormat::Rgb24 { 3 } else { 4 };
+            let rgb = |line: &[u8], x: usize| {
+                let px = &line[x * bpp..];
+                if bpp == 3 { [px[0], px[1], px[2]] } else { [px[2], px[1], px[0]] }
+            };
+            for row in 0..height {
+                let line = src.planes[0].row(row, width * bpp);
+                for x in 0..width {
+                    y_out[row * y_stride + x] = matrix.luma(rgb(line, x));
+                }
+            }
+            // Chroma de la couleur moyenne de chaque bloc 2×2 (tronqué aux bords impairs)
+            for row in 0..ch {
+                let rows = (row * 2..(row * 2 + 2).min(height)).map(|r| src.planes[0].row(r, width * bpp));
+                let lines: Vec<&[u8]> = rows.collect();
+                for x in 0..cw {
+                    let mut sum = [0u32; 3];
+                    let mut n = 0;
+                    for line in &lines {
+                        for px in x * 2..(x * 2 + 2).min(width) {
+                            let [r, g, b] = rgb(line, px);
+                            sum[0] += r as u32;
+                            sum[1] += g as u32;
+                            sum[2] += b as u32;
+                            n += 1;
+                        }
+                    }
+                    let (u, v) = matrix.chroma(sum, n);
+                    u_out[row * c_stride + x] = u;
+                    v_out[row * c_stride + x] = v;
+                }
+            }
+        }
+        PixelFormat::Grey => {
+            for row in 0..height {
+                let line = src.planes[0].row(row, width);
+                for x in 0..width {
+                    y_out[row * y_stride + x] = colorimetry.luma_from_full(line[x]);
+                }
+            }
+            u_out.fill(128);
+            v_out.fill(128);
+        }
+    }
+}
+
+fn nv12_to_yuyv(src: &Image, out: &mut [u8], stride: usize) {
+    let (width, cw) = (src.width, src.width.div_ceil(2));
+    for row in 0..src.height {
+        let y = src.planes[0].row(row, width);
+        let uv = src.planes[1].row(row / 2, cw * 2);
+        let line = &mut out[row * stride..][..width * 2];
+        for x in 0..width {
+            line[x * 2] = y[x];
+            // Pixel pair → U, impair → V : même octet que dans la paire UV de NV12
+            line[x * 2 + 1] = uv[(x / 2) * 2 + x % 2];
+        }
+    }
+}
+
+fn rgb_to_rgb(src: &Image, out: &mut [u8], format: PixelFormat, stride: usize) {
+    let (src_bpp, dst_bpp) = if format == PixelFormat::Bgra { (3, 4) } else { (4, 3) };
+    for row in 0..src.height {
+        let line = src.planes[0].row(row, src.width * src_bpp);
+        let dst = &mut out[row * stride..][..src.width * dst_bpp];
+        for (s, d) in line.chunks_exact(src_bpp).zip(dst.chunks_exact_mut(dst_bpp)) {
+            if dst_bpp == 4 {
+                d.copy_from_slice(&[s[2], s[1], s[0], 255]);
+            } else {
+                d.copy_from_slice(&[s[2], s[1], s[0]]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_COLORIMETRIES: [Colorimetry; 4] = [
+        Colorimetry::new(ColorMatrix::Bt601, ColorRange::Limited),
+        Colorimetry::new(ColorMatrix::Bt601, ColorRange::Full),
+        Colorimetry::new(ColorMatrix::Bt709, ColorRange::Limited),
+        Colorimetry::new(ColorMatrix::Bt709, ColorRange::Full),
+    ];
+
+    fn convert_packed(format: PixelFormat, data: &[u8], width: usize, height: usize, to: PixelFormat, colorimetry: Colorimetry) -> Vec<u8> {
+        let src = Image::packed(format, data, width, height).unwrap();
+        let mut out = vec![0u8; to.frame_size(width, height)];
+        convert(&src, &mut out, to, colorimetry).unwrap();
+        out
+    }
+
+    // I420 dont la chroma est constante sur chaque paire de lignes : aucune perte en 4:2:2
+    fn i420_pattern(width: usize, height: usize) -> Vec<u8> {
+        let layout = PixelFormat::I420.layout(width, height);
+        let mut data = vec![0u8; PixelFormat::I420.frame_size(width, height)];
+        for (i, b) in data[..layout[1].offset].iter_mut().enumerate() {
+            *b = (16 + i * 7 % 219) as u8;
+        }
+        for (i, b) in data[layout[1].offset..].iter_mut().enumerate() {
+            *b = (16 + i * 13 % 224) as u8;
+        }
+        data
+    }
+
+    #[test]
+    fn yuv_formats_round_trip_through_i420() {
+        let colorimetry = Colorimetry::default();
+        for (width, height) in [(2, 2), (6, 4), (8, 6)] {
+            let i420 = i420_pattern(width, height);
+            for format in [PixelFormat::Nv12, PixelFormat::Yuyv, PixelFormat::Uyvy, PixelFormat::I420] {
+                let packed = convert_packed(PixelFormat::I420, &i420, width, height, format, colorimetry);
+                let back = convert_packed(format, &packed, width, height, PixelFormat::I420, colorimetry);
+                assert_eq!(back, i420, "{} {}x{}", format, width, height);
+            }
+        }
+    }
+
+    #[test]
+    fn odd_sizes_keep_luma_and_fit_the_layout() {
+        let colorimetry = Colorimetry::default();
+        let (width, height) = (5, 3);
+        let i420 = i420_pattern(width, height);
+        let luma = &i420[..width * height];
+        for format in [PixelFormat::Nv12, PixelFormat::Yuyv, PixelFormat::Uyvy] {
+            let packed = convert_packed(PixelFormat::I420, &i420, width, height, format, colorimetry);
+            let back = convert_packed(format, &packed, width, height, PixelFormat::I420, colorimetry);
+            assert_eq!(&back[..width * height], luma, "{}", format);
+        }
+    }
+
+    #[test]
+    fn packed_layouts_place_samples() {
+        let colorimetry = Colorimetry::default();
+        // 2×2 : Y = 10 20 / 30 40, U = 100, V = 200
+        let i420 = [10, 20, 30, 40, 100, 200];
+        assert_eq!(convert_packed(PixelFormat::I420, &i420, 2, 2, PixelFormat::Nv12, colorimetry), [10, 20, 30, 40, 100, 200]);
+        assert_eq!(
+            convert_packed(PixelFormat::I420, &i420, 2, 2, PixelFormat::Yuyv, colorimetry),
+            [10, 100, 20, 200, 30, 100, 40, 200]
+        );
+        assert_eq!(
+            convert_packed(PixelFormat::I420, &i420, 2, 2, PixelFormat::Uyvy, colorimetry),
+            [100, 10, 200, 20, 100, 30, 200, 40]
+        );
+        // Route directe NV12 → YUYV, identique au passage par I420
+        let nv12 = [10, 20, 30, 40, 100, 200];
+        assert_eq!(
+            convert_packed(PixelFormat::Nv12, &nv12, 2, 2, PixelFormat::Yuyv, colorimetry),
+            [10, 100, 20, 200, 30, 100, 40, 200]
+        );
+    }
+
+    #[test]
+    fn rgb_orders_swap_exactly() {
+        let colorimetry = Colorimetry::default();
+        let rgb = [1, 2, 3, 4, 5, 6];
+        let bgra = convert_packed(PixelFormat::Rgb24, &rgb, 2, 1, PixelFormat::Bgra, colorimetry);
+        assert_eq!(bgra, [3, 2, 1, 255, 6, 5, 4, 255]);
+        assert_eq!(convert_packed(PixelFormat::Bgra, &bgra, 2, 1, PixelFormat::Rgb24, colorimetry), rgb);
+    }
+
+    // Valeurs de référence (arrondies) des normes BT.601 / BT.709
+    #[test]
+    fn known_rgb_to_yuv_values() {
+        let cases = [
+            // (colorimétrie, RGB, YUV)
+            (ALL_COLORIMETRIES[0], [255, 255, 255], [235, 128, 128]),
+            (ALL_COLORIMETRIES[0], [0, 0, 0], [16, 128, 128]),
+            (ALL_COLORIMETRIES[0], [255, 0, 0], [81, 90, 240]),
+            (ALL_COLORIMETRIES[1], [255, 0, 0], [76, 85, 255]),
+            (ALL_COLORIMETRIES[2], [255, 0, 0], [63, 102, 240]),
+            (ALL_COLORIMETRIES[3], [255, 0, 0], [54, 99, 255]),
+            (ALL_COLORIMETRIES[3], [255, 255, 255], [255, 128, 128]),
+            (ALL_COLORIMETRIES[3], [0, 0, 0], [0, 128, 128]),
+        ];
+        for (colorimetry, rgb, yuv) in cases {
+            let flat = rgb.repeat(4);
+            let i420 = convert_packed(PixelFormat::Rgb24, &flat, 2, 2, PixelFormat::I420, colorimetry);
+            assert_eq!(i420, [yuv[0], yuv[0], yuv[0], yuv[0], yuv[1], yuv[2]], "{:?} {:?}", colorimetry, rgb);
+        }
+    }
+
+    #[test]
+    fn known_yuv_to_rgb_values() {
+        let limited = ALL_COLORIMETRIES[0].yuv_to_rgb();
+        assert_eq!(limited.convert(235, 128, 128), [255, 255, 255]);
+        assert_eq!(limited.convert(16, 128, 128), [0, 0, 0]);
+        // Hors plage limitée : écrêté, pas de débordement
+        assert_eq!(limited.convert(255, 128, 128), [255, 255, 255]);
+        assert_eq!(limited.convert(0, 128, 128), [0, 0, 0]);
+        assert_eq!(ALL_COLORIMETRIES[3].yuv_to_rgb().convert(128, 128, 128), [128, 128, 128]);
+    }
+
+    #[test]
+    fn flat_colors_round_trip_within_rounding() {
+        let colors = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [200, 150, 50], [30, 60, 90], [128, 128, 128]];
+        for colorimetry in ALL_COLORIMETRIES {
+            for rgb in colors {
+                let flat = rgb.repeat(4);
+                let yuyv = convert_packed(PixelFormat::Rgb24, &flat, 2, 2, PixelFormat::Yuyv, colorimetry);
+                let back = convert_packed(PixelFormat::Yuyv, &yuyv, 2, 2, PixelFormat::Rgb24, colorimetry);
+                for (got, want) in back.iter().zip(&flat) {
+                    assert!(got.abs_diff(*want) <= 2, "{:?} {:?} -> {:?}", colorimetry, rgb, &back[..3]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grey_is_full_range_luma() {
+        let i420 = [16, 235, 126, 16, 90, 240];
+        let limited = convert_packed(PixelFormat::I420, &i420, 2, 2, PixelFormat::Grey, ALL_COLORIMETRIES[0]);
+        assert_eq!(limited, [0, 255, 128, 0]);
+        let back = convert_packed(PixelFormat::Grey, &limited, 2, 2, PixelFormat::I420, ALL_COLORIMETRIES[0]);
+        assert_eq!(back, [16, 235, 126, 16, 128, 128]);
+
+        let full = convert_packed(PixelFormat::I420, &i420, 2, 2, PixelFormat::Grey, ALL_COLORIMETRIES[1]);
+        assert_eq!(full, [16, 235, 126, 16]);
+    }
+
+    #[test]
+    fn black_frame_follows_the_range() {
+        let limited = black_frame(PixelFormat::Yuyv, 2, 1, ALL_COLORIMETRIES[2]);
+        assert_eq!(limited, [16, 128, 16, 128]);
+        let full = black_frame(PixelFormat::Nv12, 2, 2, ALL_COLORIMETRIES[3]);
+        assert_eq!(full, [0, 0, 0, 0, 128, 128]);
+    }
+
+    #[test]
+    fn scratch_is_reused_across_frames() {
+        let colorimetry = Colorimetry::default();
+        let rgb = [200u8, 150, 50].repeat(16);
+        let src = Image::packed(PixelFormat::Rgb24, &rgb, 4, 4).unwrap();
+        let mut scratch = Vec::new();
+        let mut out = vec![0u8; PixelFormat::Uyvy.frame_size(4, 4)];
+        convert_with(&src, &mut out, PixelFormat::Uyvy, colorimetry, &mut scratch).unwrap();
+        assert_eq!(scratch.len(), PixelFormat::I420.frame_size(4, 4));
+        let buffer = scratch.as_ptr();
+        convert_with(&src, &mut out, PixelFormat::Uyvy, colorimetry, &mut scratch).unwrap();
+        assert_eq!(scratch.as_ptr(), buffer);
+        assert_eq!(out, convert_packed(PixelFormat::Rgb24, &rgb, 4, 4, PixelFormat::Uyvy, colorimetry));
+    }
+
+    #[test]
+    fn undersized_buffers_are_rejected() {
+        let colorimetry = Colorimetry::default();
+        let i420 = i420_pattern(4, 4);
+        let src = Image::packed(PixelFormat::I420, &i420, 4, 4).unwrap();
+        let mut out = vec![0u8; PixelFormat::Yuyv.frame_size(4, 4) - 1];
+        assert!(matches!(
+            convert(&src, &mut out, PixelFormat::Yuyv, colorimetry),
+            Err(ConvertError::DestinationTooSmall { needed: 32, got: 31, .. })
+        ));
+        assert!(matches!(
+            Image::packed(PixelFormat::Nv12, &i420[..20], 4, 4),
+            Err(ConvertError::SourceTooSmall { format: PixelFormat::Nv12, .. })
+        ));
+        // Pas plus court qu'une ligne
+        let plane = Plane::new(&i420, 3);
+        assert!(matches!(
+            Image::new(PixelFormat::Grey, 4, 4, [plane, plane, plane]),
+            Err(ConvertError::SourceTooSmall { plane: 0, .. })
+        ));
+    }
+}
@@ -1 +1,4 @@
 pub mod simd;
+pub mod convert;
+pub mod scale;
+pub mod rotate;
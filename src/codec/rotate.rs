@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use crate::codec::simd::{Plane, Yuv420};
+
+// Rotation horaire, par quarts de tour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(into = "u32", try_from = "u32")]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees % 360 {
+            0 => Some(Rotation::R0),
+            90 => Some(Rotation::R90),
+            180 => Some(Rotation::R180),
+            270 => Some(Rotation::R270),
+            _ => None,
+        }
+    }
+
+    pub fn degrees(self) -> u32 {
+        match self {
+            Rotation::R0 => 0,
+            Rotation::R90 => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        }
+    }
+
+    // Quart de tour : largeur et hauteur échangées
+    pub fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::R90 | Rotation::R270)
+    }
+}
+
+impl From<Rotation> for u32 {
+    fn from(rotation: Rotation) -> u32 {
+        rotation.degrees()
+    }
+}
+
+impl TryFrom<u32> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u32) -> Result<Self, String> {
+        Rotation::from_degrees(degrees).ok_or_else(|| format!("rotation {}° : 0, 90, 180 ou 270 attendu", degrees))
+    }
+}
+
+// Rotation puis miroirs, appliqués à l'image déjà tournée
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Orientation {
+    pub rotation: Rotation,
+    #[serde(default)]
+    pub flip_h: bool,
+    #[serde(default)]
+    pub flip_v: bool,
+}
+
+impl Orientation {
+    pub const IDENTITY: Orientation = Orientation {
+        rotation: Rotation::R0,
+        flip_h: false,
+        flip_v: false,
+    };
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    pub fn output_size(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.rotation.swaps_axes() { (height, width) } else { (width, height) }
+    }
+
+    // Encodage sur un octet, pour un partage par atomique entre threads
+    pub fn to_bits(self) -> u8 {
+        (self.rotation.degrees() / 90) as u8 | (self.flip_h as u8) << 2 | (self.flip_v as u8) << 3
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        Self {
+            rotation: Rotation::from_degrees((bits & 0b11) as u32 * 90).unwrap_or_default(),
+            flip_h: bits & 0b100 != 0,
+            flip_v: bits & 0b1000 != 0,
+        }
+    }
+
+    // Pixel source du pixel de sortie (0, y) et pas (en x, y source) quand x de sortie avance de 1
+    fn source_walk(&self, width: usize, height: usize, oy: usize) -> ((isize, isize), (isize, isize)) {
+        let (ow, oh) = self.output_size(width, height);
+        let (w, h) = (width as isize, height as isize);
+        // Miroirs défaits d'abord : coordonnées dans l'image simplement tournée
+        let (x0, dx) = if self.flip_h { (ow as isize - 1, -1) } else { (0, 1) };
+        let y = if self.flip_v { (oh - 1 - oy) as isize } else { oy as isize };
+
+        match self.rotation {
+            Rotation::R0 => ((x0, y), (dx, 0)),
+            Rotation::R90 => ((y, h - 1 - x0), (0, -dx)),
+            Rotation::R180 => ((w - 1 - x0, h - 1 - y), (-dx, 0)),
+            Rotation::R270 => ((w - 1 - y, x0), (0, dx)),
+        }
+    }
+}
+
+// Oriente un plan de `width` × `height` vers `out` (dimensions de sortie, pas `out_stride`)
+pub fn orient_plane(src: Plane, width: usize, height: usize, out: &mut [u8], out_stride: usize, orientation: Orientation) {
+    let (ow, oh) = orientation.output_size(width, height);
+    assert!(src.covers(height, width), "plan source trop petit");
+    assert!(oh == 0 || out.len() >= (oh - 1) * out_stride + ow, "plan destination trop petit");
+
+    let stride = src.stride as isize;
+    for oy in 0..oh {
+        let ((sx, sy), (dx, dy)) = orientation.source_walk(width, height, oy);
+        let step = dx + dy * stride;
+        let mut index = sy * stride + sx;
+        for px in &mut out[oy * out_stride..oy * out_stride + ow] {
+            *px = src.data[index as usize];
+            index += step;
+        }
+    }
+}
+
+// I420 orienté vers `dst` (I420 compact aux dimensions de sortie), renvoie ces dimensions
+pub fn orient_i420(src: &Yuv420, dst: &mut [u8], orientation: Orientation) -> (usize, usize) {
+    let (width, height) = (src.width, src.height);
+    let (ow, oh) = orientation.output_size(width, height);
+    let (cw, ch) = (width.div_ceil(2), height.div_ceil(2));
+    let (ocw, och) = orientation.output_size(cw, ch);
+
+    let (y_out, rest) = dst.split_at_mut(ow * oh);
+    let (u_out, v_out) = rest.split_at_mut(ocw * och);
+    orient_plane(src.y, width, height, y_out, ow, orientation);
+    orient_plane(src.u, cw, ch, u_out, ocw, orientation);
+    orient_plane(src.v, cw, ch, v_out, ocw, orientation);
+
+    (ow, oh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 2 3
+    // 4 5 6
+    const SRC: [u8; 6] = [1, 2, 3, 4, 5, 6];
+
+    fn orient(rotation: Rotation, flip_h: bool, flip_v: bool) -> Vec<u8> {
+        let orientation = Orientation { rotation, flip_h, flip_v };
+        let (ow, oh) = orientation.output_size(3, 2);
+        let mut out = vec![0u8; ow * oh];
+        orient_plane(Plane::new(&SRC, 3), 3, 2, &mut out, ow, orientation);
+        out
+    }
+
+    #[test]
+    fn quarter_turns_are_clockwise() {
+        assert_eq!(orient(Rotation::R0, false, false), SRC);
+        assert_eq!(orient(Rotation::R90, false, false), [4, 1, 5, 2, 6, 3]);
+        assert_eq!(orient(Rotation::R180, false, false), [6, 5, 4, 3, 2, 1]);
+        assert_eq!(orient(Rotation::R270, false, false), [3, 6, 2, 5, 1, 4]);
+    }
+
+    #[test]
+    fn mirrors_apply_after_rotation() {
+        assert_eq!(orient(Rotation::R0, true, false), [3, 2, 1, 6, 5, 4]);
+        assert_eq!(orient(Rotation::R0, false, true), [4, 5, 6, 1, 2, 3]);
+        assert_eq!(orient(Rotation::R0, true, true), orient(Rotation::R180, false, false));
+        // Quart de tour puis miroir horizontal : transposition
+        assert_eq!(orient(Rotation::R90, true, false), [1, 4, 2, 5, 3, 6]);
+        assert_eq!(orient(Rotation::R90, false, true), [6, 3, 5, 2, 4, 1]);
+        assert_eq!(orient(Rotation::R270, true, false), [6, 3, 5, 2, 4, 1]);
+    }
+
+    #[test]
+    fn strides_are_honoured_on_both_sides() {
+        // Source avec 2 octets de remplissage par ligne, destination avec 1
+        let padded = [1, 2, 3, 0xAA, 0xAA, 4, 5, 6, 0xAA, 0xAA];
+        let orientation = Orientation { rotation: Rotation::R90, ..Orientation::IDENTITY };
+        let mut out = [0xEEu8; 3 * 3];
+        orient_plane(Plane::new(&padded, 5), 3, 2, &mut out, 3, orientation);
+        assert_eq!(out, [4, 1, 0xEE, 5, 2, 0xEE, 6, 3, 0xEE]);
+    }
+
+    #[test]
+    fn odd_dimensions_round_chroma_up() {
+        // 5×3 : chroma 3×2, sortie 3×5 avec chroma 2×3
+        let (width, height) = (5, 3);
+        let y = (0..15).collect::<Vec<u8>>();
+        let u = (100..106).collect::<Vec<u8>>();
+        let v = (200..206).collect::<Vec<u8>>();
+        let src = Yuv420 {
+            y: Plane::new(&y, width),
+            u: Plane::new(&u, 3),
+            v: Plane::new(&v, 3),
+            width,
+            height,
+        };
+        let orientation = Orientation { rotation: Rotation::R90, ..Orientation::IDENTITY };
+        let mut dst = vec![0u8; 15 + 2 * 6];
+        assert_eq!(orient_i420(&src, &mut dst, orientation), (3, 5));
+
+        assert_eq!(&dst[..3], [10, 5, 0]);
+        // U 3×2 tourné : 2 colonnes, 3 lignes
+        assert_eq!(&dst[15..21], [103, 100, 104, 101, 105, 102]);
+        assert_eq!(&dst[21..], [203, 200, 204, 201, 205, 202]);
+    }
+
+    #[test]
+    fn four_quarter_turns_restore_the_image() {
+        let quarter = Orientation { rotation: Rotation::R90, ..Orientation::IDENTITY };
+        let (mut data, mut size) = (SRC.to_vec(), (3, 2));
+        for _ in 0..4 {
+            let (ow, oh) = quarter.output_size(size.0, size.1);
+            let mut out = vec![0u8; ow * oh];
+            orient_plane(Plane::new(&data, size.0), size.0, size.1, &mut out, ow, quarter);
+            (data, size) = (out, (ow, oh));
+        }
+        assert_eq!((data.as_slice(), size), (&SRC[..], (3, 2)));
+    }
+
+    #[test]
+    fn bits_and_degrees_round_trip() {
+        for bits in 0..16u8 {
+            assert_eq!(Orientation::from_bits(bits).to_bits(), bits);
+        }
+        assert_eq!(Rotation::from_degrees(450), Some(Rotation::R90));
+        assert_eq!(Rotation::from_degrees(45), None);
+        assert_eq!(serde_json::from_str::<Rotation>("270").unwrap(), Rotation::R270);
+        assert!(serde_json::from_str::<Rotation>("100").is_err());
+        assert!(Orientation::IDENTITY.is_identity());
+        assert_eq!(Orientation { rotation: Rotation::R270, ..Orientation::IDENTITY }.output_size(640, 480), (480, 640));
+    }
+}
@@ -0,0 +1,526 @@
+use crate::codec::convert::ColorRange;
+use crate::codec::simd::{self, Plane, SimdLevel, Yuv420};
+
+// Poids des filtres en virgule fixe (14 bits : les sommes tiennent en i32 même avec Lanczos)
+const WEIGHT_BITS: u32 = 14;
+const WEIGHT_ONE: i32 = 1 << WEIGHT_BITS;
+const WEIGHT_HALF: i32 = 1 << (WEIGHT_BITS - 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleFilter {
+    #[default]
+    Bilinear,
+    // Moyenne de la surface couverte : le meilleur rapport qualité/coût en réduction
+    Area,
+    // Lanczos à 3 lobes : le plus net, le plus coûteux
+    Lanczos,
+}
+
+impl ScaleFilter {
+    pub const ALL: [ScaleFilter; 3] = [ScaleFilter::Bilinear, ScaleFilter::Area, ScaleFilter::Lanczos];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleFilter::Bilinear => "bilinear",
+            ScaleFilter::Area => "area",
+            ScaleFilter::Lanczos => "lanczos",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    // Demi-largeur du noyau, en pixels source (à l'échelle 1)
+    fn support(self) -> f64 {
+        match self {
+            ScaleFilter::Bilinear => 1.0,
+            ScaleFilter::Area => 0.5,
+            ScaleFilter::Lanczos => 3.0,
+        }
+    }
+
+    fn weight(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            ScaleFilter::Bilinear => (1.0 - x).max(0.0),
+            ScaleFilter::Area => if x < 0.5 { 1.0 } else { 0.0 },
+            ScaleFilter::Lanczos => {
+                if x >= 3.0 {
+                    0.0
+                } else if x < 1e-8 {
+                    1.0
+                } else {
+                    let px = std::f64::consts::PI * x;
+                    3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+                }
+            }
+        }
+    }
+}
+
+// Adaptation du cadre du téléphone (portrait ou paysage) à la résolution fixe de la caméra
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    // Image entière, bandes noires en haut/bas (letterbox) ou sur les côtés (pillarbox)
+    #[default]
+    Fit,
+    // Remplit le cadre, l'excédent est rogné au centre
+    Fill,
+    // Déforme l'image pour couvrir exactement le cadre
+    Stretch,
+}
+
+impl FitMode {
+    pub const ALL: [FitMode; 3] = [FitMode::Fit, FitMode::Fill, FitMode::Stretch];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FitMode::Fit => "fit",
+            FitMode::Fill => "fill",
+            FitMode::Stretch => "stretch",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn full(width: usize, height: usize) -> Self {
+        Self { x: 0, y: 0, width, height }
+    }
+
+    // Rectangle correspondant dans un plan de chroma 4:2:0 de `plane_w` × `plane_h`
+    fn chroma(&self, plane_w: usize, plane_h: usize) -> Self {
+        let (x, y) = (self.x / 2, self.y / 2);
+        Self {
+            x,
+            y,
+            width: self.width.div_ceil(2).min(plane_w - x),
+            height: self.height.div_ceil(2).min(plane_h - y),
+        }
+    }
+}
+
+fn even(v: u64) -> usize {
+    (v as usize & !1).max(2)
+}
+
+// Zone source utilisée et zone destination couverte, alignées sur 2 pour la chroma 4:2:0
+pub fn placement(src_w: usize, src_h: usize, dst_w: usize, dst_h: usize, fit: FitMode) -> (Rect, Rect) {
+    let (sw, sh, dw, dh) = (src_w as u64, src_h as u64, dst_w as u64, dst_h as u64);
+    let src = Rect::full(src_w, src_h);
+    let dst = Rect::full(dst_w, dst_h);
+    // Source plus large que la destination (à proportions égales)
+    let wider = sw * dh > dw * sh;
+
+    match fit {
+        FitMode::Stretch => (src, dst),
+        FitMode::Fit => {
+            let (width, height) = if wider {
+                (dst_w, even((sh * dw + sw / 2) / sw).min(dst_h))
+            } else {
+                (even((sw * dh + sh / 2) / sh).min(dst_w), dst_h)
+            };
+            let inner = Rect {
+                x: ((dst_w - width) / 2) & !1,
+                y: ((dst_h - height) / 2) & !1,
+                width,
+                height,
+            };
+            (src, inner)
+        }
+        FitMode::Fill => {
+            let (width, height) = if wider {
+                (even((dw * sh + dh / 2) / dh).min(src_w), src_h)
+            } else {
+                (src_w, even((dh * sw + dw / 2) / dw).min(src_h))
+            };
+            let crop = Rect {
+                x: ((src_w - width) / 2) & !1,
+                y: ((src_h - height) / 2) & !1,
+                width,
+                height,
+            };
+            (crop, dst)
+        }
+    }
+}
+
+// Coefficients d'un axe : pour chaque pixel de sortie, `taps` poids à partir de `starts[i]`
+struct FilterTable {
+    taps: usize,
+    starts: Vec<usize>,
+    weights: Vec<i16>,
+}
+
+impl FilterTable {
+    fn new(src_len: usize, dst_len: usize, filter: ScaleFilter) -> Self {
+        let ratio = src_len as f64 / dst_len as f64;
+        // En réduction, le noyau s'élargit pour couvrir tous les pixels source (anti-aliasing)
+        let stretch = ratio.max(1.0);
+        let support = filter.support() * stretch;
+        let taps = ((support * 2.0).ceil() as usize + 1).min(src_len);
+
+        let mut starts = Vec::with_capacity(dst_len);
+        let mut weights = vec![0i16; dst_len * taps];
+
+        for i in 0..dst_len {
+            let center = (i as f64 + 0.5) * ratio;
+            let left = (center - support).floor() as isize;
+            let right = (center + support).ceil() as isize;
+            let start = left.clamp(0, (src_len - taps) as isize) as usize;
+
+            // Poids flottants, pixels hors image ramenés sur le bord
+            let mut row = vec![0f64; taps];
+            for j in left..=right {
+                let w = filter.weight((j as f64 + 0.5 - center) / stretch);
+                if w == 0.0 {
+                    continue;
+                }
+                let k = j.clamp(0, src_len as isize - 1) as usize;
+                if let Some(slot) = k.checked_sub(start).and_then(|k| row.get_mut(k)) {
+                    *slot += w;
+                }
+            }
+            let total: f64 = row.iter().sum();
+            if total == 0.0 {
+                // Noyau vide (boîte étroite entre deux pixels) : plus proche voisin
+                let nearest = (center as usize).min(src_len - 1).clamp(start, start + taps - 1);
+                row[nearest - start] = 1.0;
+            }
+            let total: f64 = row.iter().sum();
+
+            // Quantification, l'erreur d'arrondi reportée sur le poids principal
+            let out = &mut weights[i * taps..(i + 1) * taps];
+            for (q, w) in out.iter_mut().zip(&row) {
+                *q = (w / total * WEIGHT_ONE as f64).round() as i16;
+            }
+            let sum: i32 = out.iter().map(|&w| w as i32).sum();
+            if let Some(main) = (0..taps).max_by_key(|&t| out[t]) {
+                out[main] += (WEIGHT_ONE - sum) as i16;
+            }
+            starts.push(start);
+        }
+
+        Self { taps, starts, weights }
+    }
+
+    fn weights(&self, i: usize) -> &[i16] {
+        &self.weights[i * self.taps..(i + 1) * self.taps]
+    }
+}
+
+struct PlaneScaler {
+    src: Rect,
+    dst: Rect,
+    horizontal: FilterTable,
+    vertical: FilterTable,
+}
+
+impl PlaneScaler {
+    fn new(src: Rect, dst: Rect, filter: ScaleFilter) -> Self {
+        Self {
+            src,
+            dst,
+            horizontal: FilterTable::new(src.width, dst.width, filter),
+            vertical: FilterTable::new(src.height, dst.height, filter),
+        }
+    }
+
+    // Passe horizontale vers `tmp` (dst.width × src.height), puis verticale vers `out`
+    fn run(&self, src: Plane, out: &mut [u8], out_stride: usize, tmp: &mut Vec<u8>, acc: &mut Vec<i32>, level: SimdLevel) {
+        let width = self.dst.width;
+        tmp.resize(width * self.src.height, 0);
+        acc.resize(width, 0);
+
+        for row in 0..self.src.height {
+            let line = &src.row(self.src.y + row, self.src.x + self.src.width)[self.src.x..];
+            horizontal_row(level, line, &self.horizontal, &mut tmp[row * width..(row + 1) * width]);
+        }
+
+        for row in 0..self.dst.height {
+            let start = self.vertical.starts[row];
+            let offset = (self.dst.y + row) * out_stride + self.dst.x;
+            vertical_row(level, tmp, width, start, self.vertical.weights(row), acc, &mut out[offset..offset + width]);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScaleConfig {
+    pub width: usize,
+    pub height: usize,
+    pub filter: ScaleFilter,
+    pub fit: FitMode,
+}
+
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            filter: ScaleFilter::default(),
+            fit: FitMode::default(),
+        }
+    }
+}
+
+// Redimensionnement I420 -> I420 à la résolution fixe de la caméra virtuelle.
+// Les tables de coefficients dépendent de la taille source : recréer le scaler quand elle change.
+pub struct Scaler {
+    config: ScaleConfig,
+    source: (usize, usize),
+    planes: [PlaneScaler; 3],
+    tmp: Vec<u8>,
+    acc: Vec<i32>,
+}
+
+impl Scaler {
+    pub fn new(src_width: usize, src_height: usize, config: ScaleConfig) -> Self {
+        let (src, dst) = placement(src_width, src_height, config.width, config.height, config.fit);
+        let (scw, sch) = (src_width.div_ceil(2), src_height.div_ceil(2));
+        let (dcw, dch) = (config.width / 2, config.height / 2);
+        let luma = PlaneScaler::new(src, dst, config.filter);
+        let chroma = || PlaneScaler::new(src.chroma(scw, sch), dst.chroma(dcw, dch), config.filter);
+
+        Self {
+            config,
+            source: (src_width, src_height),
+            planes: [luma, chroma(), chroma()],
+            tmp: Vec::new(),
+            acc: Vec::new(),
+        }
+    }
+
+    pub fn source_size(&self) -> (usize, usize) {
+        self.source
+    }
+
+    // `dst` : I420 compact de config.width × config.height ; les bandes hors image sont noires
+    pub fn scale(&mut self, src: &Yuv420, dst: &mut [u8], range: ColorRange) {
+        assert_eq!((src.width, src.height), self.source, "taille source différente de celle du scaler");
+        let (w, h) = (self.config.width, self.config.height);
+        let (cw, ch) = (w / 2, h / 2);
+        assert!(dst.len() >= w * h + 2 * cw * ch, "tampon I420 trop petit");
+
+        let (y_out, rest) = dst.split_at_mut(w * h);
+        let (u_out, v_out) = rest.split_at_mut(cw * ch);
+        let black = if range == ColorRange::Full { 0 } else { 16 };
+        if self.planes[0].dst != Rect::full(w, h) {
+            y_out.fill(black);
+            u_out[..cw * ch].fill(128);
+            v_out[..cw * ch].fill(128);
+        }
+
+        let level = simd::level();
+        let outputs: [(Plane, &mut [u8], usize); 3] = [(src.y, y_out, w), (src.u, u_out, cw), (src.v, v_out, cw)];
+        for (plane, (input, out, stride)) in self.planes.iter().zip(outputs) {
+            plane.run(input, out, stride, &mut self.tmp, &mut self.acc, level);
+        }
+    }
+}
+
+// Variantes AVX2 choisies à l'exécution comme pour simd:: ; même arithmétique entière que
+// les boucles génériques, donc résultat identique à l'octet près.
+fn horizontal_row(level: SimdLevel, line: &[u8], table: &FilterTable, out: &mut [u8]) {
+    #[cfg(target_arch = "x86_64")]
+    if level >= SimdLevel::Avx2 {
+        // SAFETY : AVX2 détecté par simd::level()
+        return unsafe { horizontal_row_avx2(line, table, out) };
+    }
+    horizontal_row_generic(line, table, out)
+}
+
+fn vertical_row(level: SimdLevel, tmp: &[u8], stride: usize, start: usize, weights: &[i16], acc: &mut [i32], out: &mut [u8]) {
+    #[cfg(target_arch = "x86_64")]
+    if level >= SimdLevel::Avx2 {
+        // SAFETY : AVX2 détecté par simd::level()
+        return unsafe { vertical_row_avx2(tmp, stride, start, weights, acc, out) };
+    }
+    vertical_row_generic(tmp, stride, start, weights, acc, out)
+}
+
+// Un pixel de sortie à la fois : produit scalaire des taps par blocs de 16 puis 8 (vpmaddwd).
+// Les noyaux courts (bilinéaire en agrandissement) finissent entièrement dans la boucle scalaire.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn horizontal_row_avx2(line: &[u8], table: &FilterTable, out: &mut [u8]) {
+    use std::arch::x86_64::*;
+
+    let taps = table.taps;
+    for (x, px) in out.iter_mut().enumerate() {
+        let src = &line[table.starts[x]..table.starts[x] + taps];
+        let weights = table.weights(x);
+        let mut t = 0;
+
+        let mut wide = _mm256_setzero_si256();
+        while t + 16 <= taps {
+            let p = _mm256_cvtepu8_epi16(_mm_loadu_si128(src.as_ptr().add(t) as *const __m128i));
+            let w = _mm256_loadu_si256(weights.as_ptr().add(t) as *const __m256i);
+            wide = _mm256_add_epi32(wide, _mm256_madd_epi16(p, w));
+            t += 16;
+        }
+        let mut sum = _mm_add_epi32(_mm256_castsi256_si128(wide), _mm256_extracti128_si256::<1>(wide));
+        if t + 8 <= taps {
+            let p = _mm_cvtepu8_epi16(_mm_loadl_epi64(src.as_ptr().add(t) as *const __m128i));
+            let w = _mm_loadu_si128(weights.as_ptr().add(t) as *const __m128i);
+            sum = _mm_add_epi32(sum, _mm_madd_epi16(p, w));
+            t += 8;
+        }
+        sum = _mm_add_epi32(sum, _mm_shuffle_epi32::<0b01_00_11_10>(sum));
+        sum = _mm_add_epi32(sum, _mm_shuffle_epi32::<0b10_11_00_01>(sum));
+
+        let total = src[t..]
+            .iter()
+            .zip(&weights[t..])
+            .fold(WEIGHT_HALF + _mm_cvtsi128_si32(sum), |acc, (&p, &w)| acc + p as i32 * w as i32);
+        *px = (total >> WEIGHT_BITS).clamp(0, 255) as u8;
+    }
+}
+
+// 16 pixels de sortie par tour ; les lignes sources sont entrelacées deux à deux pour que
+// vpmaddwd applique deux taps à la fois. Les accumulateurs restent en registres (`acc` inutilisé).
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn vertical_row_avx2(tmp: &[u8], stride: usize, start: usize, weights: &[i16], _acc: &mut [i32], out: &mut [u8]) {
+    use std::arch::x86_64::*;
+
+    let width = out.len();
+    let load = |t: usize, x: usize| {
+        let row = &tmp[(start + t) * stride + x..][..16];
+        _mm256_cvtepu8_epi16(_mm_loadu_si128(row.as_ptr() as *const __m128i))
+    };
+
+    let mut x = 0;
+    while x + 16 <= width {
+        // Pixels 0-3 et 8-11 dans `lo`, 4-7 et 12-15 dans `hi` (unpack par voie de 128 bits)
+        let mut lo = _mm256_set1_epi32(WEIGHT_HALF);
+        let mut hi = lo;
+        for (pair, w) in weights.chunks(2).enumerate() {
+            let t = pair * 2;
+            let (a, b, w0, w1) = match *w {
+                [w0, w1] => (load(t, x), load(t + 1, x), w0, w1),
+                [w0] => (load(t, x), _mm256_setzero_si256(), w0, 0),
+                _ => unreachable!(),
+            };
+            let w = _mm256_set1_epi32(((w1 as u16 as i32) << 16) | w0 as u16 as i32);
+            lo = _mm256_add_epi32(lo, _mm256_madd_epi16(_mm256_unpacklo_epi16(a, b), w));
+            hi = _mm256_add_epi32(hi, _mm256_madd_epi16(_mm256_unpackhi_epi16(a, b), w));
+        }
+        // Les saturations de packs/packus valent le clamp(0, 255) de la version générique
+        let lo = _mm256_srai_epi32::<{ WEIGHT_BITS as i32 }>(lo);
+        let hi = _mm256_srai_epi32::<{ WEIGHT_BITS as i32 }>(hi);
+        let words = _mm256_packs_epi32(lo, hi);
+        let bytes = _mm256_permute4x64_epi64::<0b10_00>(_mm256_packus_epi16(words, words));
+        _mm_storeu_si128(out[x..x + 16].as_mut_ptr() as *mut __m128i, _mm256_castsi256_si128(bytes));
+        x += 16;
+    }
+
+    for (x, o) in out.iter_mut().enumerate().skip(x) {
+        let sum = weights
+            .iter()
+            .enumerate()
+            .fold(WEIGHT_HALF, |acc, (t, &w)| acc + tmp[(start + t) * stride + x] as i32 * w as i32);
+        *o = (sum >> WEIGHT_BITS).clamp(0, 255) as u8;
+    }
+}
+
+#[inline(always)]
+fn horizontal_row_generic(line: &[u8], table: &FilterTable, out: &mut [u8]) {
+    for (x, px) in out.iter_mut().enumerate() {
+        let src = &line[table.starts[x]..table.starts[x] + table.taps];
+        let sum = src
+            .iter()
+            .zip(table.weights(x))
+            .fold(WEIGHT_HALF, |acc, (&p, &w)| acc + p as i32 * w as i32);
+        *px = (sum >> WEIGHT_BITS).clamp(0, 255) as u8;
+    }
+}
+
+#[inline(always)]
+fn vertical_row_generic(tmp: &[u8], stride: usize, start: usize, weights: &[i16], acc: &mut [i32], out: &mut [u8]) {
+    let width = out.len();
+    let acc = &mut acc[..width];
+    acc.fill(WEIGHT_HALF);
+
+    for (t, &w) in weights.iter().enumerate() {
+        let row = &tmp[(start + t) * stride..][..width];
+        for (a, &p) in acc.iter_mut().zip(row) {
+            *a += p as i32 * w as i32;
+        }
+    }
+    for (o, &a) in out.iter_mut().zip(acc.iter()) {
+        *o = (a >> WEIGHT_BITS).clamp(0, 255) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Octets pseudo-aléatoires, avec des plages saturées à 0/255 pour les dépassements de Lanczos
+    fn samples(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9) | 1;
+        (0..len)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                match (i / 7) % 4 {
+                    0 => 0,
+                    1 => 255,
+                    _ => state as u8,
+                }
+            })
+            .collect()
+    }
+
+    // Agrandissements, réductions légères et fortes (noyaux de plus de 16 taps)
+    const SIZES: [(usize, usize); 10] = [(1, 5), (3, 17), (7, 7), (20, 33), (33, 20), (64, 47), (100, 31), (150, 16), (211, 29), (640, 37)];
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx2_rows_match_generic() {
+        if !is_x86_feature_detected!("avx2") {
+            return;
+        }
+        for filter in ScaleFilter::ALL {
+            for (seed, &(src_len, dst_len)) in SIZES.iter().enumerate() {
+                let table = FilterTable::new(src_len, dst_len, filter);
+                let line = samples(src_len, seed as u32);
+                let (mut expected, mut out) = (vec![0u8; dst_len], vec![0u8; dst_len]);
+                horizontal_row_generic(&line, &table, &mut expected);
+                // SAFETY : AVX2 détecté ci-dessus
+                unsafe { horizontal_row_avx2(&line, &table, &mut out) };
+                assert!(out == expected, "{} horizontal : écart pour {} -> {}", filter.name(), src_len, dst_len);
+
+                // Passe verticale sur des lignes de largeurs quelconques (restes de 16 compris)
+                let width = 1 + (src_len * 7 + dst_len) % 70;
+                let stride = width + seed;
+                let tmp = samples(stride * src_len, seed as u32 + 100);
+                let mut acc = vec![0i32; width];
+                for row in 0..dst_len {
+                    let (start, weights) = (table.starts[row], table.weights(row));
+                    let (mut expected, mut out) = (vec![0u8; width], vec![0u8; width]);
+                    vertical_row_generic(&tmp, stride, start, weights, &mut acc, &mut expected);
+                    // SAFETY : AVX2 détecté ci-dessus
+                    unsafe { vertical_row_avx2(&tmp, stride, start, weights, &mut acc, &mut out) };
+                    assert!(out == expected, "{} vertical : écart pour {} -> {}, ligne {}", filter.name(), src_len, dst_len, row);
+                }
+            }
+        }
+    }
+}
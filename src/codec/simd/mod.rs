@@ -1 +1,305 @@
+#[cfg(target_arch = "x86_64")]
 pub mod yuv_convert_avx512;
+#[cfg(target_arch = "x86_64")]
+pub mod yuv_convert_avx2;
+#[cfg(target_arch = "x86_64")]
+pub mod yuv_convert_sse41;
+#[cfg(target_arch = "aarch64")]
+pub mod yuv_convert_neon;
+pub mod yuv_convert_scalar;
+
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+// Forcer un niveau (tests, machines aux drivers capricieux) : PHONECAM_SIMD=avx2
+const SIMD_ENV: &str = "PHONECAM_SIMD";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimdLevel {
+    Scalar,
+    Neon,
+    Sse41,
+    Avx2,
+    Avx512,
+}
+
+impl SimdLevel {
+    pub const ALL: [SimdLevel; 5] = [
+        SimdLevel::Scalar,
+        SimdLevel::Neon,
+        SimdLevel::Sse41,
+        SimdLevel::Avx2,
+        SimdLevel::Avx512,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SimdLevel::Scalar => "scalar",
+            SimdLevel::Neon => "neon",
+            SimdLevel::Sse41 => "sse4.1",
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Avx512 => "avx512",
+        }
+    }
+
+    // "sse41" accepté en plus du nom affiché
+    fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("sse41") {
+            return Some(SimdLevel::Sse41);
+        }
+        Self::ALL.into_iter().find(|level| level.name().eq_ignore_ascii_case(name))
+    }
+
+    // Meilleur niveau supporté par le CPU courant
+    fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+                return SimdLevel::Avx512;
+            }
+            if is_x86_feature_detected!("avx2") {
+                return SimdLevel::Avx2;
+            }
+            if is_x86_feature_detected!("sse4.1") {
+                return SimdLevel::Sse41;
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                return SimdLevel::Neon;
+            }
+        }
+        SimdLevel::Scalar
+    }
+
+    fn supported(self) -> bool {
+        self <= Self::detect() && (self != SimdLevel::Neon || cfg!(target_arch = "aarch64"))
+    }
+}
+
+// Niveau retenu, détecté une seule fois au premier appel
+pub fn level() -> SimdLevel {
+    static LEVEL: OnceLock<SimdLevel> = OnceLock::new();
+    *LEVEL.get_or_init(|| {
+        let detected = SimdLevel::detect();
+        let level = match std::env::var(SIMD_ENV).ok().as_deref().map(SimdLevel::from_name) {
+            None => detected,
+            Some(Some(forced)) if forced.supported() => forced,
+            Some(_) => {
+                warn!(variable = SIMD_ENV, fallback = detected.name(), "niveau SIMD forcé non supporté par ce CPU, ignoré");
+                detected
+            }
+        };
+        info!(simd = level.name(), "conversion YUV");
+        level
+    })
+}
+
+type RowFn = unsafe fn(&[u8], &[u8], &[u8], &mut [u8], usize);
+
+fn row_kernel(level: SimdLevel) -> RowFn {
+    match level {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512 => yuv_convert_avx512::yuv420_to_yuyv_row,
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => yuv_convert_avx2::yuv420_to_yuyv_row,
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Sse41 => yuv_convert_sse41::yuv420_to_yuyv_row,
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => yuv_convert_neon::yuv420_to_yuyv_row,
+        _ => yuv_convert_scalar::yuv420_to_yuyv_row,
+    }
+}
+
+// Plan d'image avec son pas (linesize FFmpeg, bytesperline V4L2), qui peut dépasser la largeur utile
+#[derive(Clone, Copy)]
+pub struct Plane<'a> {
+    pub data: &'a [u8],
+    pub stride: usize,
+}
+
+impl<'a> Plane<'a> {
+    pub fn new(data: &'a [u8], stride: usize) -> Self {
+        Self { data, stride }
+    }
+
+    // Plan sans remplissage en fin de ligne
+    #[cfg(test)]
+    pub fn packed(data: &'a [u8], width: usize) -> Self {
+        Self { data, stride: width }
+    }
+
+    pub(crate) fn row(&self, row: usize, len: usize) -> &'a [u8] {
+        &self.data[row * self.stride..row * self.stride + len]
+    }
+
+    // Taille minimale couvrant `rows` lignes de `len` octets (la dernière n'a pas de remplissage)
+    pub(crate) fn covers(&self, rows: usize, len: usize) -> bool {
+        self.stride >= len && (rows == 0 || self.data.len() >= (rows - 1) * self.stride + len)
+    }
+}
+
+pub struct Yuv420<'a> {
+    pub y: Plane<'a>,
+    pub u: Plane<'a>,
+    pub v: Plane<'a>,
+    pub width: usize,
+    pub height: usize,
+}
+
+// YUV420 planaire -> YUYV (format attendu par V4L2 loopback), `dst_stride` octets par ligne.
+// Choisit à l'exécution la meilleure implémentation disponible sur ce CPU.
+pub fn yuv420_to_yuyv(src: &Yuv420, dst: &mut [u8], dst_stride: usize) {
+    yuv420_to_yuyv_with(level(), src, dst, dst_stride)
+}
+
+pub fn yuv420_to_yuyv_with(level: SimdLevel, src: &Yuv420, dst: &mut [u8], dst_stride: usize) {
+    let (width, height) = (src.width, src.height);
+    // Chroma arrondie au supérieur : largeurs et hauteurs impaires gardent leur dernière colonne/ligne
+    let chroma_width = width.div_ceil(2);
+    let chroma_height = height.div_ceil(2);
+    assert!(src.y.covers(height, width), "plan Y trop petit");
+    assert!(src.u.covers(chroma_height, chroma_width), "plan U trop petit");
+    assert!(src.v.covers(chroma_height, chroma_width), "plan V trop petit");
+    assert!(Plane::new(dst, dst_stride).covers(height, width * 2), "tampon YUYV trop petit");
+
+    // Un niveau non supporté exécuterait des instructions illégales
+    let kernel = row_kernel(if level.supported() { level } else { SimdLevel::Scalar });
+
+    for row in 0..height {
+        let y_row = src.y.row(row, width);
+        let u_row = src.u.row(row / 2, chroma_width);
+        let v_row = src.v.row(row / 2, chroma_width);
+        let out_row = &mut dst[row * dst_stride..row * dst_stride + width * 2];
+
+        // SAFETY : le niveau a été vérifié contre le CPU, les tranches couvrent une ligne entière
+        unsafe { kernel(y_row, u_row, v_row, out_row, width) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Plus large vecteur : 64 pixels (AVX-512) ; on couvre deux tours complets et tous les restes
+    const MAX_WIDTH: usize = 2 * 64 + 33;
+
+    // Référence pixel par pixel, indépendante des fonctions de ligne
+    fn reference(src: &Yuv420, dst: &mut [u8], dst_stride: usize) {
+        for row in 0..src.height {
+            for x in 0..src.width {
+                let out = row * dst_stride + x * 2;
+                dst[out] = src.y.data[row * src.y.stride + x];
+                let chroma = if x % 2 == 0 { &src.u } else { &src.v };
+                dst[out + 1] = chroma.data[(row / 2) * chroma.stride + x / 2];
+            }
+        }
+    }
+
+    fn levels() -> impl Iterator<Item = SimdLevel> {
+        SimdLevel::ALL.into_iter().filter(|level| level.supported())
+    }
+
+    // Valeur différente pour chaque octet : une lecture décalée d'un octet se voit
+    fn pattern(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + seed * 31) as u8).collect()
+    }
+
+    #[test]
+    fn every_level_matches_reference_across_vector_widths() {
+        for width in 1..=MAX_WIDTH {
+            for height in 1..=3usize {
+                let (cw, ch) = (width.div_ceil(2), height.div_ceil(2));
+                let (y, u, v) = (pattern(width * height, 1), pattern(cw * ch, 2), pattern(cw * ch, 3));
+                let src = Yuv420 {
+                    y: Plane::packed(&y, width),
+                    u: Plane::packed(&u, cw),
+                    v: Plane::packed(&v, cw),
+                    width,
+                    height,
+                };
+                let mut expected = vec![0u8; width * height * 2];
+                reference(&src, &mut expected, width * 2);
+
+                for level in levels() {
+                    let mut out = vec![0u8; width * height * 2];
+                    yuv420_to_yuyv_with(level, &src, &mut out, width * 2);
+                    assert!(out == expected, "{} : écart pour {}x{}", level.name(), width, height);
+                }
+            }
+        }
+    }
+
+    // Générateur déterministe (xorshift64*) : un échec se reproduit à l'identique
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+
+        fn bytes(&mut self, len: usize) -> Vec<u8> {
+            (0..len).map(|_| self.next() as u8).collect()
+        }
+    }
+
+    // Pas aléatoire, remplissage aléatoire ; la dernière ligne s'arrête parfois à la largeur utile
+    fn random_plane(rng: &mut Rng, rows: usize, len: usize) -> (Vec<u8>, usize) {
+        let stride = len + rng.below(80);
+        let last = if rng.below(2) == 0 { stride } else { len };
+        (rng.bytes((rows - 1) * stride + last), stride)
+    }
+
+    #[test]
+    fn random_sizes_and_strides_match_scalar() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..3000 {
+            let width = 1 + rng.below(300);
+            let height = 1 + rng.below(9);
+            let (cw, ch) = (width.div_ceil(2), height.div_ceil(2));
+            let (y, y_stride) = random_plane(&mut rng, height, width);
+            let (u, u_stride) = random_plane(&mut rng, ch, cw);
+            let (v, v_stride) = random_plane(&mut rng, ch, cw);
+            let src = Yuv420 {
+                y: Plane::new(&y, y_stride),
+                u: Plane::new(&u, u_stride),
+                v: Plane::new(&v, v_stride),
+                width,
+                height,
+            };
+            // Les octets hors image de la destination doivent rester intacts
+            let dst_stride = width * 2 + rng.below(80);
+            let background = rng.bytes(height * dst_stride);
+
+            let mut expected = background.clone();
+            yuv420_to_yuyv_with(SimdLevel::Scalar, &src, &mut expected, dst_stride);
+            let mut reference_out = background.clone();
+            reference(&src, &mut reference_out, dst_stride);
+            assert!(expected == reference_out, "scalar : écart pour {}x{}", width, height);
+
+            for level in levels() {
+                let mut out = background.clone();
+                yuv420_to_yuyv_with(level, &src, &mut out, dst_stride);
+                assert!(
+                    out == expected,
+                    "{} : écart pour {}x{} (pas y={} u={} v={} sortie={})",
+                    level.name(),
+                    width,
+                    height,
+                    y_stride,
+                    u_stride,
+                    v_stride,
+                    dst_stride
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use std::arch::x86_64::*;
+use crate::codec::simd::yuv_convert_scalar;
+
+// 32 pixels par itération. Les unpack AVX2 travaillent par moitié de 128 bits :
+// on remet les deux moitiés dans l'ordre avec un permute final.
+#[target_feature(enable = "avx2")]
+pub unsafe fn yuv420_to_yuyv_row(y: &[u8], u: &[u8], v: &[u8], yuyv: &mut [u8], width: usize) {
+    let width_simd = width & !31;
+
+    for x in (0..width_simd).step_by(32) {
+        let y_vec = _mm256_loadu_si256(y.as_ptr().add(x) as *const __m256i);
+        let u_vec = _mm_loadu_si128(u.as_ptr().add(x / 2) as *const __m128i);
+        let v_vec = _mm_loadu_si128(v.as_ptr().add(x / 2) as *const __m128i);
+
+        // Moitié basse : paires UV 0-7, moitié haute : paires 8-15
+        let uv = _mm256_set_m128i(_mm_unpackhi_epi8(u_vec, v_vec), _mm_unpacklo_epi8(u_vec, v_vec));
+
+        // lo = [pixels 0-7 | 16-23], hi = [pixels 8-15 | 24-31]
+        let lo = _mm256_unpacklo_epi8(y_vec, uv);
+        let hi = _mm256_unpackhi_epi8(y_vec, uv);
+
+        let out = yuyv.as_mut_ptr().add(x * 2) as *mut __m256i;
+        _mm256_storeu_si256(out, _mm256_permute2x128_si256(lo, hi, 0x20));
+        _mm256_storeu_si256(out.add(1), _mm256_permute2x128_si256(lo, hi, 0x31));
+    }
+
+    yuv_convert_scalar::yuv420_to_yuyv_row(
+        &y[width_simd..],
+        &u[width_simd / 2..],
+        &v[width_simd / 2..],
+        &mut yuyv[width_simd * 2..],
+        width - width_simd,
+    );
+}
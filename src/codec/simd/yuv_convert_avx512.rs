@@ -1,50 +1,45 @@
-#![feature(portable_simd)]
+use std::arch::x86_64::*;
+use crate::codec::simd::yuv_convert_scalar;
 
-use std::simd::*;
-
-#[target_feature(enable = "avx512f")]
-pub unsafe fn yuv420_to_yuyv_avx512(
-    y: &[u8],
-    u: &[u8],
-    v: &[u8],
-    yuyv: &mut [u8],
-    width: usize,
-    height: usize,
-) {
+// 64 pixels par itération (AVX-512BW pour les unpack sur octets).
+// Même principe que la version AVX2 : unpack par voie de 128 bits, puis réordonnancement
+// des voies par permutation de qwords.
+#[target_feature(enable = "avx512f,avx512bw")]
+pub unsafe fn yuv420_to_yuyv_row(y: &[u8], u: &[u8], v: &[u8], yuyv: &mut [u8], width: usize) {
     let width_simd = width & !63;
-    
-    for row in 0..height {
-        let y_row = &y[row * width..];
-        let uv_row_idx = row / 2;
-        let u_row = &u[uv_row_idx * (width / 2)..];
-        let v_row = &v[uv_row_idx * (width / 2)..];
-        let yuyv_row = &mut yuyv[row * width * 2..];
-        
-        for x in (0..width_simd).step_by(64) {
-            let y_vec = u8x64::from_slice(&y_row[x..x + 64]);
-            
-            // On charge 32 U et 32 V car ils sont sous-échantillonnés (4:2:0)
-            let u_vec = u8x32::from_slice(&u_row[x/2..(x/2)+32]);
-            let v_vec = u8x32::from_slice(&v_row[x/2..(x/2)+32]);
-            
-            // Entrelacement Y0 U0 Y1 V0 ...
-            // Magie AVX-512 pour réorganiser les octets en un seul passage
-            let result = interleave_avx512(y_vec, u_vec, v_vec);
-            yuyv_row[x*2..(x+64)*2].copy_from_slice(&result);
-        }
-    }
-}
 
-#[inline(always)]
-unsafe fn interleave_avx512(y: u8x64, u: u8x32, v: u8x32) -> [u8; 128] {
-    let mut out = [0u8; 128];
-    // Implementation réelle via intrinsics AVX-512...
-    // Pour l'exemple, on simule une copie
-    for i in 0..32 {
-        out[i*4] = y[i*2];
-        out[i*4+1] = u[i];
-        out[i*4+2] = y[i*2+1];
-        out[i*4+3] = v[i];
+    // Voie k de uv = paires UV 8k..8k+7
+    let uv_order = _mm512_set_epi64(7, 6, 3, 2, 5, 4, 1, 0);
+    // Sorties : [lo.0 hi.0 lo.1 hi.1] puis [lo.2 hi.2 lo.3 hi.3] (voies de 128 bits)
+    let first_half = _mm512_set_epi64(11, 10, 3, 2, 9, 8, 1, 0);
+    let second_half = _mm512_set_epi64(15, 14, 7, 6, 13, 12, 5, 4);
+
+    for x in (0..width_simd).step_by(64) {
+        let y_vec = _mm512_loadu_si512(y.as_ptr().add(x) as *const _);
+        let u_vec = _mm256_loadu_si256(u.as_ptr().add(x / 2) as *const __m256i);
+        let v_vec = _mm256_loadu_si256(v.as_ptr().add(x / 2) as *const __m256i);
+
+        // [0-7 | 16-23] et [8-15 | 24-31] -> [0-7 | 8-15 | 16-23 | 24-31]
+        let uv_a = _mm256_unpacklo_epi8(u_vec, v_vec);
+        let uv_b = _mm256_unpackhi_epi8(u_vec, v_vec);
+        let uv = _mm512_permutexvar_epi64(
+            uv_order,
+            _mm512_inserti64x4(_mm512_castsi256_si512(uv_a), uv_b, 1),
+        );
+
+        let lo = _mm512_unpacklo_epi8(y_vec, uv);
+        let hi = _mm512_unpackhi_epi8(y_vec, uv);
+
+        let out = yuyv.as_mut_ptr().add(x * 2);
+        _mm512_storeu_si512(out as *mut _, _mm512_permutex2var_epi64(lo, first_half, hi));
+        _mm512_storeu_si512(out.add(64) as *mut _, _mm512_permutex2var_epi64(lo, second_half, hi));
     }
-    out
+
+    yuv_convert_scalar::yuv420_to_yuyv_row(
+        &y[width_simd..],
+        &u[width_simd / 2..],
+        &v[width_simd / 2..],
+        &mut yuyv[width_simd * 2..],
+        width - width_simd,
+    );
 }
@@ -0,0 +1,24 @@
+use std::arch::aarch64::*;
+use crate::codec::simd::yuv_convert_scalar;
+
+// 16 pixels par itération : vld2 sépare Y pairs/impairs, vst4 entrelace Y0 U Y1 V
+#[target_feature(enable = "neon")]
+pub unsafe fn yuv420_to_yuyv_row(y: &[u8], u: &[u8], v: &[u8], yuyv: &mut [u8], width: usize) {
+    let width_simd = width & !15;
+
+    for x in (0..width_simd).step_by(16) {
+        let y_pair = vld2_u8(y.as_ptr().add(x));
+        let u_vec = vld1_u8(u.as_ptr().add(x / 2));
+        let v_vec = vld1_u8(v.as_ptr().add(x / 2));
+
+        vst4_u8(yuyv.as_mut_ptr().add(x * 2), uint8x8x4_t(y_pair.0, u_vec, y_pair.1, v_vec));
+    }
+
+    yuv_convert_scalar::yuv420_to_yuyv_row(
+        &y[width_simd..],
+        &u[width_simd / 2..],
+        &v[width_simd / 2..],
+        &mut yuyv[width_simd * 2..],
+        width - width_simd,
+    );
+}
@@ -0,0 +1,18 @@
+// Implémentation de référence, portable : sert aussi à finir les bords des versions SIMD.
+// Une ligne YUYV : Y0 U Y1 V pour chaque paire de pixels.
+pub fn yuv420_to_yuyv_row(y: &[u8], u: &[u8], v: &[u8], yuyv: &mut [u8], width: usize) {
+    let pairs = width / 2;
+
+    for i in 0..pairs {
+        yuyv[i * 4] = y[i * 2];
+        yuyv[i * 4 + 1] = u[i];
+        yuyv[i * 4 + 2] = y[i * 2 + 1];
+        yuyv[i * 4 + 3] = v[i];
+    }
+
+    // Largeur impaire : dernier pixel seul, avec la chroma de sa colonne
+    if width % 2 == 1 {
+        yuyv[pairs * 4] = y[pairs * 2];
+        yuyv[pairs * 4 + 1] = u[pairs];
+    }
+}
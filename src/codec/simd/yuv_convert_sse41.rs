@@ -0,0 +1,28 @@
+use std::arch::x86_64::*;
+use crate::codec::simd::yuv_convert_scalar;
+
+// 16 pixels par itération : deux unpack suffisent pour entrelacer Y et UV
+#[target_feature(enable = "sse4.1")]
+pub unsafe fn yuv420_to_yuyv_row(y: &[u8], u: &[u8], v: &[u8], yuyv: &mut [u8], width: usize) {
+    let width_simd = width & !15;
+
+    for x in (0..width_simd).step_by(16) {
+        let y_vec = _mm_loadu_si128(y.as_ptr().add(x) as *const __m128i);
+        let u_vec = _mm_loadl_epi64(u.as_ptr().add(x / 2) as *const __m128i);
+        let v_vec = _mm_loadl_epi64(v.as_ptr().add(x / 2) as *const __m128i);
+
+        // U0 V0 U1 V1 ... puis Y0 U0 Y1 V0 ...
+        let uv = _mm_unpacklo_epi8(u_vec, v_vec);
+        let out = yuyv.as_mut_ptr().add(x * 2) as *mut __m128i;
+        _mm_storeu_si128(out, _mm_unpacklo_epi8(y_vec, uv));
+        _mm_storeu_si128(out.add(1), _mm_unpackhi_epi8(y_vec, uv));
+    }
+
+    yuv_convert_scalar::yuv420_to_yuyv_row(
+        &y[width_simd..],
+        &u[width_simd / 2..],
+        &v[width_simd / 2..],
+        &mut yuyv[width_simd * 2..],
+        width - width_simd,
+    );
+}
@@ -0,0 +1,719 @@
+// Configuration typée : ~/.config/phonecam/config.toml (ou --config), puis les options de la ligne
+// de commande par-dessus. Le fichier est surveillé : les réglages sans interruption (débit max des
+// téléphones, niveau de log, cadence et orientation de sortie, période des stats) s'appliquent à
+// chaud, les autres sont signalés et attendent un redémarrage.
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use notify::{EventKind, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use serde::{Deserialize, Deserializer};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+use crate::cli::commands::{self, Args};
+use crate::codec::convert::PixelFormat;
+use crate::codec::rotate::{Orientation, Rotation};
+use crate::codec::scale::{FitMode, ScaleConfig, ScaleFilter};
+use crate::logging::{LogConfig, LogFormat};
+use crate::metrics::Stage;
+use crate::pipeline::affinity::{AffinityConfig, StagePlacement};
+use crate::pipeline::pacer::PacerConfig;
+use crate::pipeline::{PipelineConfig, QueueSizes};
+use crate::record::{Container, RecordConfig};
+use crate::sink::SinkConfig;
+use crate::v4l2::device::IoMode;
+use crate::v4l2::loopback::LoopbackOptions;
+
+// Un enregistrement d'éditeur produit plusieurs événements (écriture, renommage) : on attend le calme
+const RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerSection,
+    pub loopback: LoopbackSection,
+    pub output: OutputSection,
+    pub phone: PhoneSection,
+    pub queues: QueueSection,
+    pub affinity: AffinitySection,
+    pub record: RecordSection,
+    pub log: LogSection,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSection {
+    pub http_port: u16,
+    // Relais UDP historique des paquets /raw
+    pub udp_port: u16,
+    // Paquets en attente par client de l'aperçu (/video) avant qu'il ne décroche
+    pub broadcast_capacity: usize,
+    // Période d'envoi des statistiques au dashboard (à chaud)
+    pub stats_interval_ms: u64,
+    // Tampon de réception du relais UDP (octets)
+    pub packet_buffer: usize,
+}
+
+impl Default for ServerSection {
+    fn default() -> Self {
+        Self {
+            http_port: 8080,
+            udp_port: 9999,
+            broadcast_capacity: 16,
+            stats_interval_ms: 500,
+            packet_buffer: 65536,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoopbackSection {
+    // /dev/videoN
+    pub video_nr: u16,
+    pub card_label: String,
+    // Créer /dev/videoN via /dev/v4l2loopback s'il n'existe pas
+    pub create: bool,
+}
+
+impl Default for LoopbackSection {
+    fn default() -> Self {
+        Self {
+            video_nr: 10,
+            card_label: "PhoneCam Ultimate".into(),
+            create: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSection {
+    #[serde(deserialize_with = "de::pixel_format")]
+    pub pixel_format: PixelFormat,
+    #[serde(deserialize_with = "de::io_mode")]
+    pub io: IoMode,
+    #[serde(deserialize_with = "de::sink")]
+    pub sink: SinkConfig,
+    #[serde(deserialize_with = "de::resolution")]
+    pub resolution: (usize, usize),
+    #[serde(deserialize_with = "de::scale_filter")]
+    pub scale_filter: ScaleFilter,
+    #[serde(deserialize_with = "de::fit")]
+    pub fit: FitMode,
+    // Cadence du pacer (à chaud)
+    pub fps: u32,
+    pub repeat_on_stall: bool,
+    pub max_repeat_ms: u64,
+    // Décoder même sans lecteur sur /dev/videoN
+    pub always_decode: bool,
+    // Orientation forcée (à chaud) ; sans rotation ni miroir, suit le téléphone
+    pub rotate: Option<Rotation>,
+    pub flip_h: bool,
+    pub flip_v: bool,
+}
+
+impl Default for OutputSection {
+    fn default() -> Self {
+        Self {
+            pixel_format: PixelFormat::Yuyv,
+            io: IoMode::default(),
+            sink: SinkConfig::default(),
+            resolution: (1280, 720),
+            scale_filter: ScaleFilter::default(),
+            fit: FitMode::default(),
+            fps: 30,
+            repeat_on_stall: true,
+            max_repeat_ms: 2000,
+            always_decode: false,
+            rotate: None,
+            flip_h: false,
+            flip_v: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PhoneSection {
+    // Plafond du débit d'encodage des téléphones (kbit/s, à chaud) ; sans valeur, celui de leur profil
+    pub max_bitrate_kbps: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueSection {
+    pub ingest: usize,
+    pub encoded: usize,
+    pub frame: usize,
+}
+
+impl Default for QueueSection {
+    fn default() -> Self {
+        let sizes = QueueSizes::default();
+        Self {
+            ingest: sizes.ingest,
+            encoded: sizes.encoded,
+            frame: sizes.frame,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AffinitySection {
+    // { decode = "2-3@0", output = "4" }
+    #[serde(deserialize_with = "de::pins")]
+    pub pins: Vec<(Stage, StagePlacement)>,
+    pub realtime: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordSection {
+    // Démarrer dès le lancement
+    pub start: bool,
+    pub dir: PathBuf,
+    #[serde(deserialize_with = "de::container")]
+    pub container: Container,
+    pub y4m: bool,
+    pub max_mb: Option<u64>,
+    pub max_secs: Option<u64>,
+}
+
+impl Default for RecordSection {
+    fn default() -> Self {
+        Self {
+            start: false,
+            dir: PathBuf::from("recordings"),
+            container: Container::default(),
+            y4m: false,
+            max_mb: None,
+            max_secs: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogSection {
+    // Directives EnvFilter (à chaud) ; sans valeur, RUST_LOG puis "info"
+    #[serde(deserialize_with = "de::log_filter")]
+    pub level: Option<String>,
+    #[serde(deserialize_with = "de::log_format")]
+    pub format: LogFormat,
+}
+
+// Valeurs texte validées par les mêmes fonctions que la ligne de commande
+mod de {
+    use super::*;
+    use serde::de::Error;
+
+    fn parsed<'de, D: Deserializer<'de>, T>(d: D, parse: fn(&str) -> Result<T, String>) -> Result<T, D::Error> {
+        parse(&String::deserialize(d)?).map_err(D::Error::custom)
+    }
+
+    pub fn pixel_format<'de, D: Deserializer<'de>>(d: D) -> Result<PixelFormat, D::Error> {
+        parsed(d, commands::parse_pixel_format)
+    }
+
+    pub fn io_mode<'de, D: Deserializer<'de>>(d: D) -> Result<IoMode, D::Error> {
+        parsed(d, commands::parse_io_mode)
+    }
+
+    pub fn sink<'de, D: Deserializer<'de>>(d: D) -> Result<SinkConfig, D::Error> {
+        parsed(d, commands::parse_sink)
+    }
+
+    pub fn resolution<'de, D: Deserializer<'de>>(d: D) -> Result<(usize, usize), D::Error> {
+        parsed(d, commands::parse_resolution)
+    }
+
+    pub fn scale_filter<'de, D: Deserializer<'de>>(d: D) -> Result<ScaleFilter, D::Error> {
+        parsed(d, commands::parse_scale_filter)
+    }
+
+    pub fn fit<'de, D: Deserializer<'de>>(d: D) -> Result<FitMode, D::Error> {
+        parsed(d, commands::parse_fit)
+    }
+
+    pub fn container<'de, D: Deserializer<'de>>(d: D) -> Result<Container, D::Error> {
+        parsed(d, commands::parse_container)
+    }
+
+    pub fn log_format<'de, D: Deserializer<'de>>(d: D) -> Result<LogFormat, D::Error> {
+        parsed(d, commands::parse_log_format)
+    }
+
+    pub fn log_filter<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+        parsed(d, commands::parse_log_filter).map(Some)
+    }
+
+    pub fn pins<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<(Stage, StagePlacement)>, D::Error> {
+        BTreeMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(stage, placement)| commands::parse_pin(&format!("{}={}", stage, placement)).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read { path: PathBuf, source: std::io::Error },
+    // Syntaxe TOML, clé inconnue ou valeur refusée ; key vide pour une erreur de syntaxe
+    Parse { path: PathBuf, key: String, message: String },
+    // Valeur lisible mais hors des bornes acceptées (après application de la ligne de commande)
+    Invalid { key: &'static str, message: String },
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Read { path, source } => write!(f, "lecture de {} impossible : {}", path.display(), source),
+            ConfigError::Parse { path, key, message } if key.is_empty() => write!(f, "{} : {}", path.display(), message),
+            ConfigError::Parse { path, key, message } => write!(f, "{} : clé {} : {}", path.display(), key, message),
+            ConfigError::Invalid { key, message } => write!(f, "clé {} : {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: &'static str, message: impl Into<String>) -> Result<(), ConfigError> {
+    Err(ConfigError::Invalid { key, message: message.into() })
+}
+
+impl Config {
+    pub fn parse(path: &Path, text: &str) -> Result<Self, ConfigError> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(text)).map_err(|e| {
+            let key = e.path().to_string();
+            ConfigError::Parse {
+                path: path.to_path_buf(),
+                key: if key == "." { String::new() } else { key },
+                message: e.into_inner().to_string().trim_end().to_string(),
+            }
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let server = &self.server;
+        // Port 0 : choisi par le système, l'adresse effective est dans les logs
+        if server.udp_port != 0 && server.udp_port == server.http_port {
+            return invalid("server.udp_port", "port distinct de server.http_port attendu");
+        }
+        if server.broadcast_capacity == 0 {
+            return invalid("server.broadcast_capacity", "au moins 1");
+        }
+        if server.stats_interval_ms < 50 {
+            return invalid("server.stats_interval_ms", "au moins 50 ms");
+        }
+        if !(1500..=65536).contains(&server.packet_buffer) {
+            return invalid("server.packet_buffer", "entre 1500 et 65536 octets");
+        }
+        if let Err(e) = commands::parse_fps(&self.output.fps.to_string()) {
+            return invalid("output.fps", e);
+        }
+        if self.phone.max_bitrate_kbps.is_some_and(|kbps| kbps < 100) {
+            return invalid("phone.max_bitrate_kbps", "au moins 100 kbit/s");
+        }
+        for (key, size) in [("queues.ingest", self.queues.ingest), ("queues.encoded", self.queues.encoded), ("queues.frame", self.queues.frame)] {
+            if !(1..=4096).contains(&size) {
+                return invalid(key, "entre 1 et 4096 éléments");
+            }
+        }
+        if self.affinity.realtime.is_some_and(|p| !(1..=99).contains(&p)) {
+            return invalid("affinity.realtime", "priorité SCHED_FIFO entre 1 et 99");
+        }
+        if self.record.max_mb == Some(0) {
+            return invalid("record.max_mb", "au moins 1 Mo");
+        }
+        if self.record.max_secs == Some(0) {
+            return invalid("record.max_secs", "au moins 1 seconde");
+        }
+        Ok(())
+    }
+
+    // Copie de self avec les réglages à chaud de new ; le reste attend un redémarrage
+    fn hot_update(&self, new: &Config) -> Config {
+        let mut next = self.clone();
+        next.server.stats_interval_ms = new.server.stats_interval_ms;
+        next.output.fps = new.output.fps;
+        next.output.rotate = new.output.rotate;
+        next.output.flip_h = new.output.flip_h;
+        next.output.flip_v = new.output.flip_v;
+        next.phone = new.phone.clone();
+        next.log.level = new.log.level.clone();
+        next
+    }
+
+    // Sections modifiées dans new qui ne peuvent pas s'appliquer sans redémarrage
+    fn restart_required(&self, new: &Config) -> Vec<&'static str> {
+        let next = self.hot_update(new);
+        [
+            ("server", next.server != new.server),
+            ("loopback", next.loopback != new.loopback),
+            ("output", next.output != new.output),
+            ("queues", next.queues != new.queues),
+            ("affinity", next.affinity != new.affinity),
+            ("record", next.record != new.record),
+            ("log", next.log != new.log),
+        ]
+        .into_iter()
+        .filter_map(|(section, changed)| changed.then_some(section))
+        .collect()
+    }
+
+    pub fn loopback_options(&self) -> LoopbackOptions<'_> {
+        LoopbackOptions {
+            label: &self.loopback.card_label,
+            max_width: self.output.resolution.0,
+            max_height: self.output.resolution.1,
+            create: self.loopback.create,
+        }
+    }
+
+    // Orientation manuelle dès qu'une rotation ou un miroir est demandé
+    pub fn orientation(&self) -> Option<Orientation> {
+        let output = &self.output;
+        (output.rotate.is_some() || output.flip_h || output.flip_v).then(|| Orientation {
+            rotation: output.rotate.unwrap_or_default(),
+            flip_h: output.flip_h,
+            flip_v: output.flip_v,
+        })
+    }
+
+    fn affinity(&self) -> AffinityConfig {
+        let mut affinity = AffinityConfig {
+            realtime: self.affinity.realtime,
+            ..Default::default()
+        };
+        for (stage, placement) in &self.affinity.pins {
+            affinity.stages[*stage as usize] = placement.clone();
+        }
+        affinity
+    }
+
+    pub fn log_config(&self) -> LogConfig {
+        LogConfig {
+            filter: self.log.level.clone(),
+            format: self.log.format,
+        }
+    }
+
+    // Plafond envoyé aux téléphones, en bit/s
+    pub fn max_bitrate_bps(&self) -> Option<u64> {
+        self.phone.max_bitrate_kbps.map(|kbps| kbps as u64 * 1000)
+    }
+
+    pub fn stats_interval(&self) -> Duration {
+        Duration::from_millis(self.server.stats_interval_ms)
+    }
+
+    pub fn pipeline_config(&self) -> PipelineConfig {
+        let output = &self.output;
+        let record = &self.record;
+        PipelineConfig {
+            video_nr: self.loopback.video_nr,
+            output_format: output.pixel_format,
+            io: output.io,
+            sink: output.sink.clone(),
+            scale: ScaleConfig {
+                width: output.resolution.0,
+                height: output.resolution.1,
+                filter: output.scale_filter,
+                fit: output.fit,
+            },
+            orientation: self.orientation(),
+            pacer: PacerConfig {
+                fps: output.fps,
+                repeat_on_stall: output.repeat_on_stall,
+                max_repeat: Duration::from_millis(output.max_repeat_ms),
+            },
+            record: RecordConfig {
+                dir: record.dir.clone(),
+                container: record.container,
+                y4m: record.y4m,
+                max_bytes: record.max_mb.map(|mb| mb * 1_000_000),
+                max_duration: record.max_secs.map(Duration::from_secs),
+                start: record.start,
+            },
+            idle_when_unwatched: !output.always_decode,
+            affinity: self.affinity(),
+            queues: QueueSizes {
+                ingest: self.queues.ingest,
+                encoded: self.queues.encoded,
+                frame: self.queues.frame,
+            },
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME/phonecam/config.toml, sinon ~/.config/phonecam/config.toml
+pub fn default_path() -> PathBuf {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    base.join("phonecam").join("config.toml")
+}
+
+// Fichier + surcharges de la ligne de commande, relus à chaque rechargement :
+// une option passée en CLI reste prioritaire sur le fichier modifié
+pub struct ConfigSource {
+    path: PathBuf,
+    // --config explicite : le fichier doit exister
+    required: bool,
+    args: Args,
+    // Dernière config lue (pas forcément appliquée) : chaque modification à froid n'est signalée qu'une fois
+    seen: Mutex<Option<Config>>,
+    // Port du relais UDP obtenu pour udp_port = 0, repris à chaque relecture (0 : pas encore lié)
+    bound_udp_port: AtomicU16,
+}
+
+impl ConfigSource {
+    pub fn new(args: &Args) -> Self {
+        Self {
+            path: args.config.clone().unwrap_or_else(default_path),
+            required: args.config.is_some(),
+            args: args.clone(),
+            seen: Mutex::new(None),
+            bound_udp_port: AtomicU16::new(0),
+        }
+    }
+
+    // Relais UDP lié sur un port choisi par le système : les sessions doivent viser celui-là
+    pub fn set_bound_udp_port(&self, port: u16) {
+        self.bound_udp_port.store(port, Ordering::Relaxed);
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = match std::fs::read_to_string(&self.path) {
+            Ok(text) => Config::parse(&self.path, &text)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !self.required => Config::default(),
+            Err(source) => return Err(ConfigError::Read { path: self.path.clone(), source }),
+        };
+        self.args.apply(&mut config);
+        if config.server.udp_port == 0 {
+            config.server.udp_port = self.bound_udp_port.load(Ordering::Relaxed);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    // Sections en attente de redémarrage qui ont changé depuis la lecture précédente ;
+    // `current` (la config en vigueur) sert de référence à la première relecture
+    fn new_restart_sections(&self, current: &Config, new: &Config) -> Vec<&'static str> {
+        let previous = self.seen.lock().replace(new.clone());
+        let changed = previous.as_ref().unwrap_or(current).restart_required(new);
+        current.restart_required(new).into_iter().filter(|section| changed.contains(section)).collect()
+    }
+}
+
+// Relit la configuration et publie les réglages à chaud ; un fichier invalide laisse la config en place
+pub fn reload(source: &ConfigSource, tx: &watch::Sender<Arc<Config>>) {
+    let new = match source.load() {
+        Ok(config) => config,
+        Err(e) => {
+            warn!(error = %e, "configuration invalide ignorée, réglages actuels conservés");
+            return;
+        }
+    };
+    let current = tx.borrow().clone();
+    let restart = source.new_restart_sections(&current, &new);
+    if !restart.is_empty() {
+        warn!(sections = %restart.join(", "), "modifications prises en compte au prochain redémarrage");
+    }
+    let next = current.hot_update(&new);
+    if next == *current {
+        debug!(path = %source.path().display(), "configuration relue, aucun réglage à chaud modifié");
+        return;
+    }
+    info!(path = %source.path().display(), "configuration rechargée");
+    tx.send_replace(Arc::new(next));
+}
+
+// Dossier existant le plus proche du fichier, et le nom à y guetter : le fichier lui-même, ou le
+// premier dossier manquant de son chemin. Rien n'est créé
+fn watch_target(path: &Path) -> Option<(PathBuf, OsString)> {
+    let mut child = path;
+    for dir in path.ancestors().skip(1) {
+        let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        if dir.is_dir() {
+            return Some((dir.to_path_buf(), child.file_name()?.to_os_string()));
+        }
+        child = dir;
+    }
+    None
+}
+
+// Surveille le dossier du fichier (les éditeurs remplacent le fichier plutôt que de le réécrire).
+// Tant que ce dossier manque, c'est son plus proche parent qui est surveillé, puis la surveillance
+// descend à mesure que le chemin apparaît. Impossible : signalé, SIGHUP reste possible
+pub fn watch(source: Arc<ConfigSource>, tx: watch::Sender<Arc<Config>>) {
+    let Some((mut dir, mut name)) = watch_target(source.path()) else {
+        warn!(path = %source.path().display(), "chemin de configuration sans dossier surveillable : pas de rechargement automatique");
+        return;
+    };
+    let (events_tx, mut events_rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else { return };
+        if !matches!(event.kind, EventKind::Access(_)) {
+            let _ = events_tx.send(event.paths);
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!(error = %e, "surveillance de la configuration indisponible");
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        warn!(dir = %dir.display(), error = %e, "dossier de configuration non surveillé : pas de rechargement automatique (SIGHUP reste possible)");
+        return;
+    }
+
+    info!(path = %source.path().display(), dir = %dir.display(), "configuration surveillée");
+    // Le watcher vit avec la tâche
+    tokio::spawn(async move {
+        while let Some(paths) = events_rx.recv().await {
+            if !paths.iter().any(|p| p.file_name() == Some(name.as_os_str())) {
+                continue;
+            }
+            tokio::time::sleep(RELOAD_DEBOUNCE).await;
+            while events_rx.try_recv().is_ok() {}
+
+            // Dossier du chemin créé (ou supprimé) : surveillance déplacée
+            if let Some((next_dir, next_name)) = watch_target(source.path()).filter(|(next, _)| *next != dir) {
+                let _ = watcher.unwatch(&dir);
+                if let Err(e) = watcher.watch(&next_dir, RecursiveMode::NonRecursive) {
+                    warn!(dir = %next_dir.display(), error = %e, "dossier de configuration non surveillé : pas de rechargement automatique (SIGHUP reste possible)");
+                    return;
+                }
+                debug!(dir = %next_dir.display(), "surveillance de la configuration déplacée");
+                (dir, name) = (next_dir, next_name);
+            }
+            reload(&source, &tx);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn cold_change_is_reported_once() {
+        let source = ConfigSource::new(&Args::parse_from(["phonecam-ultimate"]));
+        let current = Config::default();
+
+        let mut new = current.clone();
+        new.queues.ingest = 128;
+        assert_eq!(source.new_restart_sections(&current, &new), ["queues"]);
+        // Fichier relu sans changement, ou seul un réglage à chaud modifié : rien de nouveau
+        assert!(source.new_restart_sections(&current, &new).is_empty());
+        new.output.fps = 60;
+        assert!(source.new_restart_sections(&current, &new).is_empty());
+
+        new.record.start = !new.record.start;
+        assert_eq!(source.new_restart_sections(&current, &new), ["record"]);
+        // Retour à la valeur en vigueur : plus rien en attente pour cette section
+        new.queues.ingest = current.queues.ingest;
+        assert!(source.new_restart_sections(&current, &new).is_empty());
+    }
+
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::parse(Path::new("phonecam.toml"), text)
+    }
+
+    #[test]
+    fn hot_settings_apply_without_restart() {
+        let current = Config::default();
+        let new = parse(
+            "[server]\nstats_interval_ms = 250\n\n[output]\nfps = 60\nrotate = 90\nflip_h = true\n\n\
+             [phone]\nmax_bitrate_kbps = 4000\n\n[log]\nlevel = \"debug\"\n",
+        )
+        .unwrap();
+        assert!(current.restart_required(&new).is_empty());
+        assert_eq!(current.hot_update(&new), new);
+    }
+
+    #[test]
+    fn cold_settings_wait_for_restart() {
+        let current = Config::default();
+        let new = parse("[output]\nfps = 60\nresolution = \"1920x1080\"\n\n[record]\ncontainer = \"mkv\"\n").unwrap();
+        assert_eq!(current.restart_required(&new), ["output", "record"]);
+
+        // Seule la cadence passe ; résolution et enregistrement restent ceux en vigueur
+        let next = current.hot_update(&new);
+        assert_eq!(next.output.fps, 60);
+        assert_eq!(next.output.resolution, current.output.resolution);
+        assert_eq!(next.record, current.record);
+        assert_eq!(next.restart_required(&new), ["output", "record"]);
+    }
+
+    #[test]
+    fn invalid_file_names_the_offending_key() {
+        let key = |text: &str| match parse(text) {
+            Err(ConfigError::Parse { key, .. }) => key,
+            other => panic!("erreur de lecture attendue : {:?}", other),
+        };
+        assert_eq!(key("[output]\nfps = \"vite\"\n"), "output.fps");
+        assert_eq!(key("[output]\npixel_format = \"rgb565\"\n"), "output.pixel_format");
+        assert_eq!(key("[queues]\ningest = -1\n"), "queues.ingest");
+        assert_eq!(key("[affinity.pins]\ndecode = \"3-1\"\n"), "affinity.pins");
+        // Clé inconnue : son chemin, pour la retrouver dans le fichier
+        assert!(key("[output]\nfsp = 30\n").starts_with("output"));
+        // Erreur de syntaxe : pas de clé
+        assert_eq!(key("[output\n"), "");
+
+        let message = parse("[output]\nfps = \"vite\"\n").unwrap_err().to_string();
+        assert!(message.starts_with("phonecam.toml : clé output.fps : "), "{}", message);
+    }
+
+    #[test]
+    fn system_chosen_udp_port_survives_reloads() {
+        let path = std::env::temp_dir().join(format!("phonecam-config-ports-{}.toml", std::process::id()));
+        std::fs::write(&path, "[server]\nhttp_port = 0\nudp_port = 0\n").unwrap();
+        let source = ConfigSource::new(&Args::parse_from(["phonecam-ultimate", "--config", path.to_str().unwrap()]));
+
+        let config = source.load().unwrap();
+        assert_eq!((config.server.http_port, config.server.udp_port), (0, 0));
+        source.set_bound_udp_port(41000);
+        let reloaded = source.load().unwrap();
+        assert_eq!(reloaded.server.udp_port, 41000);
+        std::fs::remove_file(&path).unwrap();
+
+        // Ports fixés identiques : refusé
+        let mut config = Config::default();
+        config.server.udp_port = config.server.http_port;
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid { key: "server.udp_port", .. })));
+    }
+
+    #[test]
+    fn watch_targets_the_nearest_existing_directory() {
+        let root = std::env::temp_dir().join(format!("phonecam-config-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("a/b/phonecam.toml");
+
+        // Rien n'est créé : on guette le premier dossier manquant
+        assert_eq!(watch_target(&path), Some((root.clone(), "a".into())));
+        assert!(!root.join("a").exists());
+        std::fs::create_dir_all(root.join("a/b")).unwrap();
+        assert_eq!(watch_target(&path), Some((root.join("a/b"), "phonecam.toml".into())));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(watch_target(Path::new("phonecam.toml")), Some((PathBuf::from("."), "phonecam.toml".into())));
+    }
+}
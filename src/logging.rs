@@ -0,0 +1,143 @@
+// Journalisation via tracing, sur stderr (stdout reste libre pour --sink stdout) : texte lisible
+// ou JSON, une ligne par événement. Filtre : --log-level, sinon RUST_LOG, sinon "info".
+use std::io::IsTerminal;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+use crate::metrics::{MetricsSnapshot, ServerMetrics, StageSnapshot};
+
+const DEFAULT_FILTER: &str = "info";
+// Période du résumé des compteurs (remplace les logs "tous les N paquets")
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    // Objets JSON avec les champs de l'événement et les spans englobants (session, étage)
+    Json,
+}
+
+impl LogFormat {
+    pub const ALL: [LogFormat; 2] = [LogFormat::Text, LogFormat::Json];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|f| f.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    // Directives EnvFilter ("debug", "info,phonecam_ultimate::pipeline=trace"…), prioritaires sur RUST_LOG
+    pub filter: Option<String>,
+    pub format: LogFormat,
+}
+
+fn env_filter(directives: Option<&str>) -> EnvFilter {
+    match directives {
+        Some(directives) => EnvFilter::new(directives),
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER)),
+    }
+}
+
+// Filtre remplaçable à chaud (rechargement de la configuration) ; le format est fixé au démarrage
+pub struct LogHandle(reload::Handle<EnvFilter, Registry>);
+
+impl LogHandle {
+    pub fn set_filter(&self, directives: Option<&str>) -> Result<(), reload::Error> {
+        self.0.reload(env_filter(directives))
+    }
+}
+
+pub fn init(config: &LogConfig) -> LogHandle {
+    let (filter, handle) = reload::Layer::new(env_filter(config.filter.as_deref()));
+    let registry = tracing_subscriber::registry().with(filter);
+    let layer = fmt::layer().with_writer(std::io::stderr);
+    match config.format {
+        LogFormat::Text => registry.with(layer.with_ansi(std::io::stderr().is_terminal())).init(),
+        LogFormat::Json => registry.with(layer.json().with_current_span(true).with_span_list(true)).init(),
+    }
+    LogHandle(handle)
+}
+
+// Un message répétitif (une erreur par image) au plus une fois par période, avec le nombre d'occurrences tues
+#[derive(Debug)]
+pub struct Throttle {
+    period: Duration,
+    last: Option<Instant>,
+    suppressed: u64,
+}
+
+impl Throttle {
+    pub const fn new(period: Duration) -> Self {
+        Self {
+            period,
+            last: None,
+            suppressed: 0,
+        }
+    }
+
+    // Some(n) : à journaliser maintenant, n occurrences tues depuis le message précédent
+    pub fn ready(&mut self) -> Option<u64> {
+        let now = Instant::now();
+        if self.last.is_some_and(|last| now.duration_since(last) < self.period) {
+            self.suppressed += 1;
+            return None;
+        }
+        self.last = Some(now);
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+// Résumé périodique de l'activité ; rien n'est écrit tant que le flux est à l'arrêt
+pub fn spawn_summary(metrics: Arc<ServerMetrics>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SUMMARY_INTERVAL);
+        let mut previous = metrics.snapshot();
+        loop {
+            interval.tick().await;
+            let current = metrics.snapshot();
+            log_summary(&previous, &current);
+            previous = current;
+        }
+    });
+}
+
+fn log_summary(previous: &MetricsSnapshot, current: &MetricsSnapshot) {
+    let delta = |f: fn(&MetricsSnapshot) -> u64| f(current).saturating_sub(f(previous));
+    let stage_delta = |f: fn(&StageSnapshot) -> u64| {
+        let sum = |s: &MetricsSnapshot| s.stages.iter().map(f).sum::<u64>();
+        sum(current).saturating_sub(sum(previous))
+    };
+
+    let packets = delta(|s| s.totals.packets);
+    let frames = delta(|s| s.totals.frames_out);
+    if packets == 0 && frames == 0 {
+        return;
+    }
+    info!(
+        sessions = current.sessions.len(),
+        packets,
+        lost = delta(|s| s.totals.packets_lost),
+        frames,
+        rejected = delta(|s| s.totals.frames_rejected),
+        dropped = stage_delta(|s| s.dropped),
+        errors = stage_delta(|s| s.errors),
+        late = stage_delta(|s| s.late),
+        bitrate_mbps = (current.rates.bitrate_bps / 1e4).round() / 100.0,
+        output_fps = (current.rates.output_fps * 10.0).round() / 10.0,
+        "résumé des {} dernières secondes",
+        SUMMARY_INTERVAL.as_secs()
+    );
+}
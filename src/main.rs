@@ -1,9 +1,5 @@
-#![feature(portable_simd)]
-#![allow(unused)]
 
 use mimalloc::MiMalloc;
-use std::net::UdpSocket;
-use tokio::io::AsyncWriteExt;
 
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
@@ -13,62 +9,222 @@ mod memory;
 mod web;
 mod metrics;
 mod pipeline;
+mod record;
+mod sink;
 mod v4l2;
 mod codec;
+// Files génériques : une partie de l'API (lots, async, jauges) ne sert qu'aux benchs et aux tests
+#[allow(dead_code)]
+mod sync;
+mod cli;
+mod config;
+mod logging;
+mod shutdown;
 
 use local_ip_address::local_ip;
 use qrcode::QrCode;
 use qrcode::render::unicode;
+use clap::Parser;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{error, info, trace, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("🚀 PHONECAM ULTIMATE — VERSION MVP");
+    let args = cli::commands::Args::parse();
+    // Comme une option invalide : message et code 2, avant toute initialisation
+    let source = Arc::new(config::ConfigSource::new(&args));
+    let settings = match source.load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("configuration invalide : {}", e);
+            std::process::exit(2);
+        }
+    };
+    let log_config = settings.log_config();
+    let log_handle = logging::init(&log_config);
+    info!(version = env!("CARGO_PKG_VERSION"), config = %source.path().display(), "démarrage de PhoneCam Ultimate");
+
+    // Commandes ponctuelles sur les loopbacks : pas de serveur
+    if args.list_devices {
+        list_devices();
+        return Ok(());
+    }
+    if let Some(nr) = args.remove_loopback {
+        if let Err(e) = v4l2::loopback::remove_loopback(nr) {
+            error!(nr, error = %e, "suppression de /dev/video{} impossible", nr);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Configuration courante, republiée à chaque rechargement (fichier modifié ou SIGHUP)
+    let (config_tx, config_rx) = watch::channel(Arc::new(settings));
+    let settings = config_rx.borrow().clone();
+    let shutdown = shutdown::Shutdown::new();
+    shutdown::spawn_signals(shutdown.clone(), source.clone(), config_tx.clone())?;
     
     // 0. Initialisation des métriques
     let metrics = metrics::ServerMetrics::new();
-    let metrics_for_udp = metrics.clone();
     let metrics_for_web = metrics.clone();
+    logging::spawn_summary(metrics.clone());
 
     // 1. Détecter l'IP locale
     let my_ip = local_ip().unwrap_or_else(|_| "127.0.0.1".parse().unwrap());
     let https_url = format!("https://{}", my_ip);  // Port 443 par défaut
     
-    println!("✓ Ton IP locale : {}", my_ip);
-    println!("✓ URL HTTPS : {}", https_url);
-    println!("✓ Dashboard PC : {}/dashboard", https_url);
+    info!(ip = %my_ip, url = %https_url, dashboard = %format!("{}/dashboard", https_url), "adresses du serveur");
     
     // 2. Générer le QR Code pour le smartphone (HTTPS)
+    // Affichage interactif, pas un log : sur stderr (stdout peut porter les images), en mode texte seulement
     let code = QrCode::new(https_url.as_bytes())?;
-    let image = code.render::<unicode::Dense1x2>().build();
-    println!("\n📱 SCANNE MOI POUR CONNECTER TON SMARTPHONE :\n{}", image);
+    if log_config.format == logging::LogFormat::Text {
+        let image = code.render::<unicode::Dense1x2>().build();
+        eprintln!("\n📱 SCANNE MOI POUR CONNECTER TON SMARTPHONE :\n{}", image);
+    }
 
     // 3. Lancer le serveur UDP en tâche de fond
-    let udp_socket = std::net::UdpSocket::bind("0.0.0.0:9999")?;
+    let udp_socket = std::net::UdpSocket::bind(("0.0.0.0", settings.server.udp_port))?;
     udp_socket.set_nonblocking(true)?;
+    let udp_addr = udp_socket.local_addr()?;
+    if settings.server.udp_port == 0 {
+        // Port choisi par le système : c'est lui que visent les sessions, y compris après rechargement
+        source.set_bound_udp_port(udp_addr.port());
+        config_tx.send_modify(|config| Arc::make_mut(config).server.udp_port = udp_addr.port());
+    }
+    info!(addr = %udp_addr, "relais UDP à l'écoute");
     
-    tokio::spawn(async move {
-        let mut buf = vec![0u8; 65536];
-        let socket = tokio::net::UdpSocket::from_std(udp_socket).unwrap();
+    // Les paquets /raw sont déjà comptés par leur session WebSocket (résumé périodique) : trace seulement
+    let packet_buffer = settings.server.packet_buffer;
+    let relay_shutdown = shutdown.clone();
+    tokio::spawn(shutdown.track(async move {
+        let socket = match tokio::net::UdpSocket::from_std(udp_socket) {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!(error = %e, "relais UDP indisponible");
+                return;
+            }
+        };
+        let mut buf = vec![0u8; packet_buffer];
+        let mut errors = logging::Throttle::new(std::time::Duration::from_secs(5));
         
         loop {
-            let (len, src) = socket.recv_from(&mut buf).await.unwrap();
-            if let Some(_header) = net::protocol::Header::parse(&buf[..len]) {
-                metrics_for_udp.record_packet(len as u64);
-                
-                let count = metrics_for_udp.packet_count.load(std::sync::atomic::Ordering::Relaxed);
-                if count % 100 == 0 {
-                    println!("📦 {} paquets reçus | Client: {}", count, src);
+            tokio::select! {
+                _ = relay_shutdown.requested() => break,
+                received = socket.recv_from(&mut buf) => match received {
+                    Ok((len, src)) => {
+                        if let Some(_header) = net::protocol::Header::parse(&buf[..len]) {
+                            trace!(bytes = len, client = %src, "paquet UDP");
+                        }
+                    }
+                    Err(e) => {
+                        if let Some(suppressed) = errors.ready() {
+                            warn!(error = %e, suppressed, "réception UDP en échec");
+                        }
+                    }
+                },
+            }
+        }
+    }));
+
+    // 4. Caméra virtuelle : /dev/videoN existant, sinon créé via /dev/v4l2loopback
+    let mut config = settings.pipeline_config();
+    let loopback = match config.sink {
+        sink::SinkConfig::V4l2 => Some(v4l2::loopback::setup_loopback(settings.loopback.video_nr, &settings.loopback_options())),
+        _ => None,
+    };
+    if let Some(Ok(loopback)) = &loopback {
+        config.video_nr = loopback.node.nr;
+    }
+
+    // 5. Initialiser la pipeline vidéo (décodage + sortie)
+    // Sans sortie utilisable, le serveur reste disponible pour l'aperçu du dashboard
+    let pipeline = match &loopback {
+        Some(Err(e)) => Err(e.to_string()),
+        _ => pipeline::Pipeline::new(&config, metrics.clone()).map_err(|e| e.to_string()),
+    };
+
+    // Rechargement du fichier : réglages à chaud publiés aux sessions, aux logs et à la pipeline
+    config::watch(source.clone(), config_tx);
+    spawn_hot_reload(config_rx.clone(), log_handle, pipeline.as_ref().ok().cloned());
+
+    // 6. Lancer le serveur Web, jusqu'à la demande d'arrêt
+    let served = match &pipeline {
+        Ok(pipeline) => web::server::start_server(config_rx, metrics_for_web, pipeline.clone(), shutdown.clone()).await,
+        Err(e) => {
+            error!(error = %e, "sortie vidéo indisponible, aperçu du dashboard seulement");
+            web::server::start_server_without_pipeline(config_rx, metrics_for_web, shutdown.clone()).await
+        }
+    };
+    if let Err(e) = &served {
+        error!(port = settings.server.http_port, error = %e, "serveur web arrêté");
+    }
+
+    // 7. Arrêt ordonné : sessions fermées (trame Close), pipeline vidée, sortie et
+    // enregistrement fermés, puis seulement suppression de la loopback (refusée tant qu'un fd est ouvert)
+    shutdown.trigger();
+    if !shutdown.wait_tasks(shutdown::SESSIONS_TIMEOUT).await {
+        warn!(timeout_s = shutdown::SESSIONS_TIMEOUT.as_secs(), "sessions WebSocket encore ouvertes");
+    }
+    if let Ok(pipeline) = pipeline {
+        let drained = tokio::time::timeout(shutdown::DRAIN_TIMEOUT, tokio::task::spawn_blocking(move || pipeline.shutdown())).await;
+        if drained.is_err() {
+            warn!(timeout_s = shutdown::DRAIN_TIMEOUT.as_secs(), "pipeline non vidée à temps");
+        }
+    }
+
+    // Loopback créée pour cette session : on ne laisse pas de caméra fantôme derrière nous
+    if let Some(Ok(loopback)) = loopback {
+        if loopback.created {
+            if let Err(e) = loopback.remove() {
+                warn!(error = %e, "loopback non supprimée");
+            }
+        }
+    }
+
+    info!("arrêt terminé");
+    if served.is_err() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Niveau de log, cadence et orientation de sortie ; le débit et la période des stats sont lus par les sessions
+fn spawn_hot_reload(mut rx: watch::Receiver<Arc<config::Config>>, log: logging::LogHandle, pipeline: Option<Arc<pipeline::Pipeline>>) {
+    tokio::spawn(async move {
+        let mut current = rx.borrow_and_update().clone();
+        while rx.changed().await.is_ok() {
+            let next = rx.borrow_and_update().clone();
+            if next.log.level != current.log.level {
+                match log.set_filter(next.log.level.as_deref()) {
+                    Ok(()) => info!(level = next.log.level.as_deref().unwrap_or("défaut"), "niveau de log modifié"),
+                    Err(e) => warn!(error = %e, "niveau de log inchangé"),
+                }
+            }
+            if let Some(pipeline) = &pipeline {
+                if next.output.fps != current.output.fps {
+                    pipeline.set_output_fps(next.output.fps);
+                    info!(fps = next.output.fps, "cadence de sortie modifiée");
+                }
+                if next.orientation() != current.orientation() {
+                    pipeline.orientation().set_manual(next.orientation());
+                    info!(orientation = ?next.orientation(), "orientation forcée modifiée");
                 }
             }
+            current = next;
         }
     });
+}
 
-    // 4. Initialiser la pipeline vidéo (décodage + V4L2)
-    // TEMPORAIREMENT DÉSACTIVÉ : mmap() ne fonctionne pas directement avec v4l2loopback
-    // let pipeline = pipeline::Pipeline::new(10)?; // /dev/video10 (v4l2loopback)
-    
-    // 5. Lancer le serveur Web (bloquant)
-    web::server::start_server_without_pipeline(8080, 9999, metrics_for_web).await;
-    
-    Ok(())
+fn list_devices() {
+    match v4l2::loopback::discover() {
+        Ok(nodes) if nodes.is_empty() => println!("Aucun périphérique vidéo"),
+        Ok(nodes) => {
+            for node in nodes {
+                let kind = if node.is_loopback() { "loopback" } else { node.driver.as_deref().unwrap_or("?") };
+                println!("{:<14} {:<12} {}", node.path.display(), kind, node.name);
+            }
+        }
+        Err(e) => error!(error = %e, "lecture de /sys/class/video4linux impossible"),
+    }
 }
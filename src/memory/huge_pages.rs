@@ -1,12 +1,124 @@
+// Grands tampons d'images sur huge pages : moins d'entrées TLB pour les conversions qui
+// parcourent plusieurs Mo par image. Essais successifs, du plus au moins favorable :
+// MAP_HUGETLB, memfd MFD_HUGETLB, pages normales + madvise(MADV_HUGEPAGE) (THP), pages normales.
+// Les deux premiers demandent des huge pages réservées (vm.nr_hugepages) ; aucun ne demande root.
+use std::fs::File;
 use std::io;
-use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::ptr;
+use crate::memory::numa;
 
-pub fn enable() -> io::Result<()> {
-    // Tente d'allouer des huge pages via sysfs si elles ne sont pas déjà allouées
-    // Note: Cela nécessite des privilèges root ou une configuration préalable via tuning.sh
-    let path = "/sys/kernel/mm/hugepages/hugepages-2048kB/nr_hugepages";
-    if fs::metadata(path).is_ok() {
-        fs::write(path, "1024")?;
+const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+    // mmap anonyme MAP_HUGETLB
+    HugeTlb,
+    // memfd_create(MFD_HUGETLB) projeté
+    HugeTlbMemfd,
+    // Huge pages transparentes demandées par madvise ; le noyau décide à la première écriture
+    Transparent,
+    Normal,
+}
+
+// Projection anonyme initialisée à zéro, libérée au Drop
+pub struct HugeBuffer {
+    addr: *mut u8,
+    // Taille projetée (arrondie à la huge page)
+    mapped: usize,
+    backing: Backing,
+}
+
+// Zone possédée par un seul propriétaire à la fois
+unsafe impl Send for HugeBuffer {}
+unsafe impl Sync for HugeBuffer {}
+
+impl HugeBuffer {
+    // Au moins `size` octets ; n'échoue que si même les pages normales sont refusées
+    pub fn allocate(size: usize) -> io::Result<Self> {
+        let mapped = size.max(1).next_multiple_of(HUGE_PAGE_SIZE);
+        if let Ok(addr) = map(mapped, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_HUGETLB, -1) {
+            return Ok(Self::new(addr, mapped, Backing::HugeTlb));
+        }
+        if let Some(addr) = map_hugetlb_memfd(mapped) {
+            return Ok(Self::new(addr, mapped, Backing::HugeTlbMemfd));
+        }
+
+        let addr = map(mapped, libc::MAP_PRIVATE | libc::MAP_ANONYMOUS, -1)?;
+        // SAFETY : zone tout juste projetée, de longueur `mapped`
+        let thp = unsafe { libc::madvise(addr as *mut _, mapped, libc::MADV_HUGEPAGE) } == 0;
+        Ok(Self::new(addr, mapped, if thp { Backing::Transparent } else { Backing::Normal }))
+    }
+
+    fn new(addr: *mut u8, mapped: usize, backing: Backing) -> Self {
+        Self { addr, mapped, backing }
     }
-    Ok(())
+
+    pub fn backing(&self) -> Backing {
+        self.backing
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.mapped
+    }
+
+    // À appeler avant la première écriture : les pages déjà présentes ne migrent pas
+    pub fn bind_to_node(&self, node: usize) -> io::Result<()> {
+        numa::bind(self.addr, self.mapped, node)
+    }
+}
+
+fn map(len: usize, flags: libc::c_int, fd: libc::c_int) -> io::Result<*mut u8> {
+    // SAFETY : nouvelle projection, sans adresse imposée
+    let addr = unsafe { libc::mmap(ptr::null_mut(), len, libc::PROT_READ | libc::PROT_WRITE, flags, fd, 0) };
+    if addr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(addr as *mut u8)
+}
+
+// Le memfd peut être fermé sitôt projeté : la projection garde la référence
+fn map_hugetlb_memfd(len: usize) -> Option<*mut u8> {
+    // SAFETY : nom C statique, descripteur vérifié avant usage
+    let fd = unsafe { libc::memfd_create(c"phonecam-frames".as_ptr(), libc::MFD_HUGETLB | libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return None;
+    }
+    // SAFETY : descripteur valide dont on prend possession
+    let file = unsafe { File::from_raw_fd(fd) };
+    file.set_len(len as u64).ok()?;
+    map(len, libc::MAP_SHARED, file.as_raw_fd()).ok()
+}
+
+impl Deref for HugeBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY : projection valide et initialisée (pages anonymes à zéro) jusqu'au Drop
+        unsafe { std::slice::from_raw_parts(self.addr, self.mapped) }
+    }
+}
+
+impl DerefMut for HugeBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY : idem, accès exclusif via &mut
+        unsafe { std::slice::from_raw_parts_mut(self.addr, self.mapped) }
+    }
+}
+
+impl Drop for HugeBuffer {
+    fn drop(&mut self) {
+        // SAFETY : projection créée par HugeBuffer::allocate
+        unsafe { libc::munmap(self.addr as *mut _, self.mapped) };
+    }
+}
+
+// Huge pages réservées et libres (/proc/meminfo), pour expliquer un repli sur THP
+pub fn free_huge_pages() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("HugePages_Free:"))
+        .and_then(|value| value.trim().parse().ok())
 }
@@ -1 +1,3 @@
-// Modules simplifiés pour MVP
+pub mod huge_pages;
+pub mod numa;
+pub mod pool;
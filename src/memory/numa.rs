@@ -0,0 +1,54 @@
+// Placement NUMA via les appels système de <linux/mempolicy.h> (absents de la libc standard)
+use std::fs;
+use std::io;
+
+// Préférence plutôt que contrainte : la mémoire déborde sur un autre nœud si besoin
+const MPOL_PREFERRED: libc::c_int = 1;
+
+// Masque de nœuds pour set_mempolicy/mbind (un seul mot : 64 nœuds au plus)
+fn node_mask(node: usize) -> io::Result<libc::c_ulong> {
+    if node >= libc::c_ulong::BITS as usize {
+        return Err(io::Error::from(io::ErrorKind::InvalidInput));
+    }
+    Ok(1 << node)
+}
+
+// Allocations à venir du thread appelant sur `node`
+pub fn set_preferred_node(node: usize) -> io::Result<()> {
+    let mask = node_mask(node)?;
+    // SAFETY : masque d'un mot, maxnode en bits
+    let ret = unsafe { libc::syscall(libc::SYS_set_mempolicy, MPOL_PREFERRED, &mask as *const libc::c_ulong, libc::c_ulong::BITS as libc::c_ulong) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// Pages d'une projection (pas encore touchées) placées sur `node`
+pub fn bind(addr: *mut u8, len: usize, node: usize) -> io::Result<()> {
+    let mask = node_mask(node)?;
+    // SAFETY : l'appelant fournit une projection valide de `len` octets, alignée sur la page
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            addr as *mut libc::c_void,
+            len as libc::c_ulong,
+            MPOL_PREFERRED,
+            &mask as *const libc::c_ulong,
+            libc::c_ulong::BITS as libc::c_ulong,
+            0 as libc::c_uint,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+// /sys/devices/system/cpu/cpuN/nodeM
+pub fn cpu_node(cpu: usize) -> Option<usize> {
+    fs::read_dir(format!("/sys/devices/system/cpu/cpu{}", cpu))
+        .ok()?
+        .filter_map(Result::ok)
+        .find_map(|entry| entry.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+}
@@ -1,41 +1,459 @@
+// Tampons réutilisés d'une image à l'autre, par classe de taille. Un PooledBuf retourne seul
+// dans sa classe quand il est relâché ; une classe épuisée est comptée dans les métriques.
 use crossbeam::queue::ArrayQueue;
-use tokio::sync::Semaphore;
+use parking_lot::Mutex;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use tracing::{info, warn};
+use crate::memory::huge_pages::{self, Backing, HugeBuffer};
+use crate::metrics::{ServerMetrics, Stage};
+use crate::pipeline::affinity::AffinityConfig;
+use crate::pipeline::QueueSizes;
+use crate::sink::FrameInfo;
 
-pub struct BufferPool {
-    queue: ArrayQueue<Vec<u8>>,
-    semaphore: Semaphore,
-    buffer_capacity: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeClass {
+    // Message réseau brut (en-tête "PC" + charge)
+    Packet,
+    // Image compressée H.264/HEVC, keyframes comprises
+    EncodedFrame,
+    // Image décodée au format de sortie
+    RawFrame,
 }
 
-impl BufferPool {
-    pub fn new(count: usize, capacity: usize) -> Self {
-        let queue = ArrayQueue::new(count);
-        for _ in 0..count {
-            queue.push(vec![0u8; capacity]).unwrap();
+impl SizeClass {
+    pub const ALL: [SizeClass; 3] = [SizeClass::Packet, SizeClass::EncodedFrame, SizeClass::RawFrame];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SizeClass::Packet => "packet",
+            SizeClass::EncodedFrame => "encoded_frame",
+            SizeClass::RawFrame => "raw_frame",
         }
-        
+    }
+}
+
+// Nombre et capacité initiale des tampons d'une classe
+#[derive(Debug, Clone, Copy)]
+pub struct ClassConfig {
+    pub count: usize,
+    pub capacity: usize,
+    // Tampons de taille fixe sur huge pages (cf. huge_pages), sinon tas
+    pub huge_pages: bool,
+    // Nœud NUMA des tampons projetés ; les tampons du tas suivent la politique du thread qui les remplit
+    pub numa_node: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PoolConfig {
+    pub classes: [ClassConfig; 3],
+}
+
+impl PoolConfig {
+    // Dimensionné d'après les files de la pipeline : de quoi remplir chaque file, plus les tampons en cours
+    pub fn for_frames(info: &FrameInfo, queues: &QueueSizes, affinity: &AffinityConfig) -> Self {
         Self {
-            queue,
-            semaphore: Semaphore::new(count),
-            buffer_capacity: capacity,
-        }
-    }
-    
-    pub async fn acquire(&self) -> Vec<u8> {
-        let _permit = self.semaphore.acquire().await.unwrap();
-        self.queue.pop().expect("Buffer missing despite permit")
-    }
-    
-    pub fn release(&self, mut buffer: Vec<u8>) {
-        buffer.clear();
-        // Resize back to original capacity if it was shrunk, or keep if it's the same
-        if buffer.capacity() < self.buffer_capacity {
-             buffer.reserve(self.buffer_capacity);
-        }
-        unsafe { buffer.set_len(self.buffer_capacity); }
-        
-        self.queue.push(buffer).unwrap();
-        self.semaphore.add_permits(1);
+            classes: [
+                ClassConfig {
+                    count: queues.ingest + 16,
+                    capacity: 64 * 1024,
+                    huge_pages: false,
+                    numa_node: affinity.buffer_node(Stage::Ingest, Stage::Depacketize),
+                },
+                ClassConfig {
+                    count: queues.encoded + 8,
+                    capacity: 512 * 1024,
+                    huge_pages: false,
+                    numa_node: affinity.buffer_node(Stage::Depacketize, Stage::Decode),
+                },
+                // Plusieurs Mo parcourus à chaque conversion : c'est là que les huge pages comptent
+                ClassConfig {
+                    count: 2 * queues.frame + 2,
+                    capacity: info.frame_size(),
+                    huge_pages: true,
+                    numa_node: affinity.buffer_node(Stage::Convert, Stage::Output),
+                },
+            ],
+        }
+    }
+}
+
+struct Class {
+    class: SizeClass,
+    capacity: usize,
+    free: ArrayQueue<Storage>,
+    // Corrigé quand un tampon huge pages trop petit est remplacé par le tas
+    backing: Mutex<BackingReport>,
+}
+
+// Répartition des tampons d'une classe selon la mémoire obtenue
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BackingReport {
+    pub heap: usize,
+    pub hugetlb: usize,
+    pub thp: usize,
+    pub normal_pages: usize,
+}
+
+impl BackingReport {
+    fn add(&mut self, storage: &Storage) {
+        match storage {
+            Storage::Heap(_) => self.heap += 1,
+            Storage::Huge { buf, .. } => *self.count(buf.backing()) += 1,
+        }
+    }
+
+    // Un tampon projeté a été remplacé par un Vec : il compte désormais dans le tas
+    fn moved_to_heap(&mut self, from: Backing) {
+        let count = self.count(from);
+        *count = count.saturating_sub(1);
+        self.heap += 1;
+    }
+
+    fn count(&mut self, backing: Backing) -> &mut usize {
+        match backing {
+            Backing::HugeTlb | Backing::HugeTlbMemfd => &mut self.hugetlb,
+            Backing::Transparent => &mut self.thp,
+            Backing::Normal => &mut self.normal_pages,
+        }
+    }
+}
+
+impl std::fmt::Display for BackingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = [("tas", self.heap), ("hugetlb", self.hugetlb), ("thp", self.thp), ("pages normales", self.normal_pages)];
+        let parts: Vec<String> = parts.iter().filter(|(_, n)| *n > 0).map(|(name, n)| format!("{} {}", n, name)).collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+// Mémoire d'un tampon : Vec sur le tas, ou zone huge pages de capacité fixe
+enum Storage {
+    Heap(Vec<u8>),
+    // Toujours initialisée (projection à zéro) : `len` se déplace sans set_len
+    Huge { buf: HugeBuffer, len: usize },
+}
+
+impl Storage {
+    fn allocate(capacity: usize, huge_pages: bool, numa_node: Option<usize>) -> Self {
+        if huge_pages {
+            match HugeBuffer::allocate(capacity) {
+                Ok(buf) => {
+                    if let Some(node) = numa_node {
+                        if let Err(e) = buf.bind_to_node(node) {
+                            warn!(node, error = %e, "tampon non placé sur le nœud NUMA");
+                        }
+                    }
+                    return Storage::Huge { buf, len: 0 };
+                }
+                Err(e) => warn!(error = %e, "projection huge pages impossible, tampon sur le tas"),
+            }
+        }
+        Storage::Heap(Vec::with_capacity(capacity))
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Storage::Heap(vec) => vec,
+            Storage::Huge { buf, len } => &buf[..*len],
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        match self {
+            Storage::Heap(vec) => vec,
+            Storage::Huge { buf, len } => &mut buf[..*len],
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Storage::Heap(vec) => vec.clear(),
+            Storage::Huge { len, .. } => *len = 0,
+        }
+    }
+
+    // Une zone huge pages trop petite est remplacée par un Vec (contenu conservé) ;
+    // renvoie alors la mémoire perdue, pour le bilan de la classe
+    fn reserve_total(&mut self, total: usize) -> Option<Backing> {
+        let Storage::Huge { buf, len } = self else {
+            return None;
+        };
+        if total <= buf.capacity() {
+            return None;
+        }
+        let backing = buf.backing();
+        let mut vec = Vec::with_capacity(total);
+        vec.extend_from_slice(&buf[..*len]);
+        *self = Storage::Heap(vec);
+        Some(backing)
+    }
+
+    // resize et extend_from_slice : capacité déjà assurée par reserve_total
+    fn resize(&mut self, new_len: usize) {
+        match self {
+            Storage::Heap(vec) => vec.resize(new_len, 0),
+            Storage::Huge { buf, len } => {
+                if new_len > *len {
+                    buf[*len..new_len].fill(0);
+                }
+                *len = new_len;
+            }
+        }
+    }
+
+    fn extend_from_slice(&mut self, data: &[u8]) {
+        match self {
+            Storage::Heap(vec) => vec.extend_from_slice(data),
+            Storage::Huge { buf, len } => {
+                buf[*len..*len + data.len()].copy_from_slice(data);
+                *len += data.len();
+            }
+        }
+    }
+}
+
+pub struct FramePool {
+    classes: [Class; 3],
+    metrics: Arc<ServerMetrics>,
+}
+
+impl FramePool {
+    // Tous les tampons sont alloués d'emblée : pas d'allocation sur le chemin critique
+    pub fn new(config: PoolConfig, metrics: Arc<ServerMetrics>) -> Arc<Self> {
+        let classes = SizeClass::ALL.map(|class| {
+            let ClassConfig { count, capacity, huge_pages, numa_node } = config.classes[class as usize];
+            let free = ArrayQueue::new(count.max(1));
+            let mut backing = BackingReport::default();
+            for _ in 0..count {
+                let storage = Storage::allocate(capacity, huge_pages, numa_node);
+                backing.add(&storage);
+                let _ = free.push(storage);
+            }
+            let m = metrics.pool(class);
+            m.capacity.store(count as u64, Ordering::Relaxed);
+            m.in_use.store(0, Ordering::Relaxed);
+            if huge_pages {
+                info!(class = class.name(), buffers = count, capacity_kb = capacity / 1024, backing = %backing, "pool alloué");
+                if backing.hugetlb < count && huge_pages::free_huge_pages() == Some(0) {
+                    info!("aucune huge page réservée (sysctl vm.nr_hugepages) : repli sur THP / pages normales");
+                }
+            }
+            Class {
+                class,
+                capacity,
+                free,
+                backing: Mutex::new(backing),
+            }
+        });
+        Arc::new(Self { classes, metrics })
+    }
+
+    // Mémoire obtenue pour chaque tampon de la classe (le bilan est aussi loggé à l'allocation)
+    #[cfg(test)]
+    pub fn backing(&self, class: SizeClass) -> BackingReport {
+        *self.classes[class as usize].backing.lock()
+    }
+
+    // Tampon vide (len 0) de la classe ; None si elle est épuisée
+    pub fn try_acquire(self: &Arc<Self>, class: SizeClass) -> Option<PooledBuf> {
+        let m = self.metrics.pool(class);
+        match self.classes[class as usize].free.pop() {
+            Some(buf) => {
+                m.in_use.fetch_add(1, Ordering::Relaxed);
+                Some(PooledBuf {
+                    buf,
+                    pool: Some((self.clone(), class)),
+                })
+            }
+            None => {
+                m.exhausted.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    // Comme try_acquire, mais une classe épuisée donne un tampon hors pool plutôt qu'une image perdue
+    pub fn acquire(self: &Arc<Self>, class: SizeClass) -> PooledBuf {
+        self.try_acquire(class).unwrap_or_else(|| PooledBuf {
+            buf: Storage::Heap(Vec::with_capacity(self.classes[class as usize].capacity)),
+            pool: None,
+        })
+    }
+
+    // Copie de `data` dans un tampon de la classe
+    pub fn copy_from(self: &Arc<Self>, class: SizeClass, data: &[u8]) -> PooledBuf {
+        let mut buf = self.acquire(class);
+        buf.extend_from_slice(data);
+        buf
+    }
+
+    fn release(&self, class: SizeClass, mut buf: Storage) {
+        let pool = &self.classes[class as usize];
+        // Une keyframe exceptionnelle ne doit pas garder sa mémoire indéfiniment
+        if matches!(&buf, Storage::Heap(vec) if vec.capacity() > pool.capacity * 4) {
+            buf = Storage::Heap(Vec::with_capacity(pool.capacity));
+        }
+        buf.clear();
+        let _ = pool.free.push(buf);
+        self.metrics.pool(pool.class).in_use.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // Tampon huge pages devenu un Vec : la classe a perdu définitivement une projection
+    fn moved_to_heap(&self, class: SizeClass, from: Backing, needed: usize) {
+        let pool = &self.classes[class as usize];
+        self.metrics.pool(class).oversized.fetch_add(1, Ordering::Relaxed);
+        let mut backing = pool.backing.lock();
+        backing.moved_to_heap(from);
+        warn!(class = class.name(), needed_kb = needed / 1024, capacity_kb = pool.capacity / 1024, backing = %*backing, "tampon huge pages trop petit, remplacé par le tas");
+    }
+}
+
+// Tampon emprunté au pool ; rendu à sa classe au Drop (les tampons hors pool sont simplement libérés)
+pub struct PooledBuf {
+    buf: Storage,
+    pool: Option<(Arc<FramePool>, SizeClass)>,
+}
+
+impl PooledBuf {
+    // Taille fixée, octets ajoutés mis à zéro (jamais de mémoire non initialisée)
+    pub fn resize(&mut self, len: usize) {
+        self.reserve_total(len);
+        self.buf.resize(len);
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[u8]) {
+        self.reserve_total(self.buf.as_slice().len() + data.len());
+        self.buf.extend_from_slice(data);
+    }
+
+    fn reserve_total(&mut self, total: usize) {
+        let Some(from) = self.buf.reserve_total(total) else {
+            return;
+        };
+        if let Some((pool, class)) = &self.pool {
+            // Les images brutes ont toutes la taille de sortie : la classe est dimensionnée pour
+            if *class == SizeClass::RawFrame {
+                warn!(bytes = total, "image brute plus grande que sa classe de tampons");
+            }
+            pool.moved_to_heap(*class, from, total);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn is_pooled(&self) -> bool {
+        self.pool.is_some()
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.buf.as_mut_slice()
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some((pool, class)) = self.pool.take() {
+            pool.release(class, std::mem::replace(&mut self.buf, Storage::Heap(Vec::new())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heap_pool(count: usize) -> (Arc<FramePool>, Arc<ServerMetrics>) {
+        let heap = ClassConfig { count, capacity: 1024, huge_pages: false, numa_node: None };
+        let metrics = ServerMetrics::new();
+        (FramePool::new(PoolConfig { classes: [heap; 3] }, metrics.clone()), metrics)
+    }
+
+    fn gauges(metrics: &ServerMetrics, class: SizeClass) -> (u64, u64, u64) {
+        let m = metrics.pool(class);
+        (m.capacity.load(Ordering::Relaxed), m.in_use.load(Ordering::Relaxed), m.exhausted.load(Ordering::Relaxed))
+    }
+
+    #[test]
+    fn dropped_buffer_returns_to_its_class() {
+        let (pool, metrics) = heap_pool(2);
+        let mut first = pool.try_acquire(SizeClass::Packet).unwrap();
+        first.extend_from_slice(b"paquet");
+        let second = pool.copy_from(SizeClass::Packet, b"suivant");
+        assert!(second.is_pooled());
+        assert_eq!(&second[..], b"suivant");
+        assert!(pool.try_acquire(SizeClass::Packet).is_none());
+        // Les autres classes ne sont pas touchées
+        assert!(pool.try_acquire(SizeClass::RawFrame).is_some());
+
+        drop(first);
+        // Rendu vidé, pas réalloué
+        let again = pool.try_acquire(SizeClass::Packet).unwrap();
+        assert!(again.is_empty());
+        assert_eq!(gauges(&metrics, SizeClass::Packet).1, 2);
+    }
+
+    #[test]
+    fn exhausted_class_is_counted() {
+        let (pool, metrics) = heap_pool(1);
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame), (1, 0, 0));
+
+        let held = pool.try_acquire(SizeClass::EncodedFrame).unwrap();
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame), (1, 1, 0));
+        assert!(pool.try_acquire(SizeClass::EncodedFrame).is_none());
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame), (1, 1, 1));
+
+        // acquire se replie sur un tampon hors pool, qui ne revient pas dans la classe
+        let extra = pool.acquire(SizeClass::EncodedFrame);
+        assert!(!extra.is_pooled());
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame), (1, 1, 2));
+        drop(extra);
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame).1, 1);
+
+        drop(held);
+        assert_eq!(gauges(&metrics, SizeClass::EncodedFrame), (1, 0, 2));
+        assert!(pool.try_acquire(SizeClass::EncodedFrame).is_some());
+    }
+
+    #[test]
+    fn oversized_heap_buffer_shrinks_on_release() {
+        let (pool, _) = heap_pool(1);
+        let mut buf = pool.acquire(SizeClass::EncodedFrame);
+        buf.resize(64 * 1024);
+        drop(buf);
+        let buf = pool.try_acquire(SizeClass::EncodedFrame).unwrap();
+        assert!(matches!(&buf.buf, Storage::Heap(vec) if vec.capacity() == 1024));
+    }
+
+    #[test]
+    fn outgrown_huge_buffer_counts_as_heap() {
+        let heap = ClassConfig { count: 1, capacity: 1024, huge_pages: false, numa_node: None };
+        let huge = ClassConfig { count: 2, huge_pages: true, ..heap };
+        let metrics = ServerMetrics::new();
+        let pool = FramePool::new(PoolConfig { classes: [heap, huge, heap] }, metrics.clone());
+        let before = pool.backing(SizeClass::EncodedFrame);
+        assert_eq!(before.heap, 0, "projection impossible dans cet environnement : {}", before);
+
+        // Au-delà d'une huge page : la zone projetée ne suffit plus
+        let mut buf = pool.acquire(SizeClass::EncodedFrame);
+        buf.extend_from_slice(b"keyframe");
+        buf.resize(3 * 1024 * 1024);
+        assert_eq!(&buf[..8], b"keyframe");
+        drop(buf);
+
+        let after = pool.backing(SizeClass::EncodedFrame);
+        assert_eq!(after.heap, 1);
+        assert_eq!(after.hugetlb + after.thp + after.normal_pages, 1);
+        assert_eq!(metrics.pool(SizeClass::EncodedFrame).oversized.load(Ordering::Relaxed), 1);
     }
 }
@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use serde::Serialize;
+
+// Bornes supérieures des buckets, en microsecondes (la dernière case = +Inf)
+pub const BUCKET_BOUNDS_US: [u64; 16] = [
+    50, 100, 250, 500, 1_000, 2_000, 4_000, 8_000, 16_000, 33_000, 66_000, 100_000, 250_000,
+    500_000, 1_000_000, 5_000_000,
+];
+
+// Histogramme de latence sans verrou : un compteur atomique par bucket
+#[derive(Debug, Default)]
+pub struct LatencyHistogram {
+    // Le total est la somme des buckets : pas de compteur séparé qui pourrait s'en écarter
+    buckets: [AtomicU64; BUCKET_BOUNDS_US.len() + 1],
+    sum_us: AtomicU64,
+}
+
+impl LatencyHistogram {
+    pub fn record(&self, latency: Duration) {
+        self.record_us(latency.as_micros() as u64);
+    }
+
+    pub fn record_us(&self, us: u64) {
+        let idx = BUCKET_BOUNDS_US
+            .iter()
+            .position(|&bound| us <= bound)
+            .unwrap_or(BUCKET_BOUNDS_US.len());
+        self.buckets[idx].fetch_add(1, Ordering::Relaxed);
+        self.sum_us.fetch_add(us, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let buckets: Vec<u64> = self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect();
+        let count = buckets.iter().sum();
+
+        HistogramSnapshot {
+            count,
+            sum_us: self.sum_us.load(Ordering::Relaxed),
+            p50_us: percentile(&buckets, count, 0.50),
+            p95_us: percentile(&buckets, count, 0.95),
+            p99_us: percentile(&buckets, count, 0.99),
+            buckets,
+        }
+    }
+}
+
+// Estimation par interpolation linéaire à l'intérieur du bucket qui contient le quantile
+fn percentile(buckets: &[u64], count: u64, q: f64) -> u64 {
+    if count == 0 {
+        return 0;
+    }
+
+    let rank = (q * count as f64).ceil().max(1.0) as u64;
+    let mut seen = 0u64;
+
+    for (idx, &n) in buckets.iter().enumerate() {
+        if n == 0 {
+            continue;
+        }
+        if seen + n >= rank {
+            let lower = if idx == 0 { 0 } else { BUCKET_BOUNDS_US[idx - 1] };
+            let upper = BUCKET_BOUNDS_US.get(idx).copied().unwrap_or(lower);
+            let fraction = (rank - seen) as f64 / n as f64;
+            return lower + ((upper - lower) as f64 * fraction) as u64;
+        }
+        seen += n;
+    }
+
+    *BUCKET_BOUNDS_US.last().unwrap()
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct HistogramSnapshot {
+    pub count: u64,
+    pub sum_us: u64,
+    pub p50_us: u64,
+    pub p95_us: u64,
+    pub p99_us: u64,
+    // Comptes par bucket (non cumulés), alignés sur BUCKET_BOUNDS_US + la case +Inf
+    pub buckets: Vec<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn histogram(samples: &[(u64, usize)]) -> HistogramSnapshot {
+        let h = LatencyHistogram::default();
+        for &(us, n) in samples {
+            for _ in 0..n {
+                h.record_us(us);
+            }
+        }
+        h.snapshot()
+    }
+
+    #[test]
+    fn samples_land_in_their_bucket() {
+        let s = histogram(&[(0, 1), (50, 1), (51, 1), (5_000_000, 1), (5_000_001, 2)]);
+        assert_eq!(s.buckets.len(), BUCKET_BOUNDS_US.len() + 1);
+        // Bornes supérieures incluses
+        assert_eq!(s.buckets[0], 2);
+        assert_eq!(s.buckets[1], 1);
+        assert_eq!(s.buckets[BUCKET_BOUNDS_US.len() - 1], 1);
+        assert_eq!(s.buckets[BUCKET_BOUNDS_US.len()], 2);
+        assert_eq!(s.count, 6);
+        assert_eq!(s.sum_us, 101 + 5_000_000 + 2 * 5_000_001);
+    }
+
+    #[test]
+    fn percentiles_interpolate_inside_the_bucket() {
+        let empty = histogram(&[]);
+        assert_eq!((empty.count, empty.p50_us, empty.p99_us), (0, 0, 0));
+
+        // 100 échantillons dans ]50, 100] : rang 50 à mi-bucket, rang 99 presque en haut
+        let s = histogram(&[(80, 100)]);
+        assert_eq!((s.p50_us, s.p95_us, s.p99_us), (75, 97, 99));
+
+        // Premier bucket : interpolation depuis 0
+        assert_eq!(histogram(&[(10, 10)]).p50_us, 25);
+
+        // 90 rapides, 10 lents dans ]1 s, 5 s] : p95 au milieu du bucket lent
+        let s = histogram(&[(40, 90), (2_000_000, 10)]);
+        assert_eq!(s.p50_us, 27);
+        assert_eq!(s.p95_us, 3_000_000);
+    }
+
+    #[test]
+    fn inf_bucket_reports_the_last_bound() {
+        let s = histogram(&[(10, 1), (60_000_000, 99)]);
+        assert_eq!(s.buckets[BUCKET_BOUNDS_US.len()], 99);
+        // Pas de borne supérieure : le quantile vaut la dernière borne connue
+        assert_eq!((s.p50_us, s.p99_us), (5_000_000, 5_000_000));
+        assert_eq!(histogram(&[(10, 99), (60_000_000, 1)]).p99_us, 50);
+    }
+}
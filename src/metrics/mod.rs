@@ -1,28 +1,146 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+pub mod histogram;
+pub mod prometheus;
+pub mod rate;
+pub mod session;
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
 use serde::Serialize;
+use crate::memory::pool::SizeClass;
+use crate::metrics::histogram::{HistogramSnapshot, LatencyHistogram};
+use crate::metrics::rate::{RateCounter, RATE_WINDOW_SECS};
+use crate::metrics::session::{SessionHandle, SessionMetrics, SessionSnapshot};
+use crate::pipeline::latency::FrameTimestamps;
+
+// Version du modèle JSON de snapshot()
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Étages de la pipeline vidéo (ingest → depacketize → decode → convert → output).
+// Depacketize fait office de jitter buffer : c'est là que les paquets attendent leur keyframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Ingest,
+    Depacketize,
+    Decode,
+    Convert,
+    Output,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Ingest,
+        Stage::Depacketize,
+        Stage::Decode,
+        Stage::Convert,
+        Stage::Output,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Ingest => "ingest",
+            Stage::Depacketize => "depacketize",
+            Stage::Decode => "decode",
+            Stage::Convert => "convert",
+            Stage::Output => "output",
+        }
+    }
 
-#[derive(Debug, Serialize, Default)]
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StageMetrics {
+    pub latency: LatencyHistogram,
+    pub processed: AtomicU64,
+    // Jetés sous surcharge (file pleine, image remplacée, attente de keyframe)
+    pub dropped: AtomicU64,
+    // Échecs de traitement (erreur du décodeur, conversion impossible)
+    pub errors: AtomicU64,
+    // Ticks de sortie sans image neuve (image précédente répétée)
+    pub late: AtomicU64,
+    // File d'entrée de l'étage, relevée par son consommateur
+    pub queue_depth: AtomicU64,
+    pub queue_capacity: AtomicU64,
+    // Éléments traités par seconde
+    pub rate: RateCounter,
+}
+
+// Occupation d'une classe du FramePool
+#[derive(Debug, Default)]
+pub struct PoolMetrics {
+    pub capacity: AtomicU64,
+    pub in_use: AtomicU64,
+    // try_acquire sans tampon libre
+    pub exhausted: AtomicU64,
+    // Tampons huge pages trop petits, remplacés par le tas
+    pub oversized: AtomicU64,
+}
+
+#[derive(Debug)]
 pub struct ServerMetrics {
+    started: Instant,
+    // Totaux de toutes les sessions /raw
     pub packet_count: AtomicU64,
     pub bytes_received: AtomicU64,
+    // Paquets refusés par la pipeline (file d'entrée pleine)
+    pub packets_lost: AtomicU64,
+    packet_rate: RateCounter,
+    byte_rate: RateCounter,
     pub width: AtomicU64,
     pub height: AtomicU64,
+    pub stages: [StageMetrics; 5],
+    // Capture téléphone → fin de chaque étage (frames effectivement écrites en sortie)
+    pub since_capture: [LatencyHistogram; 5],
+    pub clock_offset_us: AtomicI64,
+    pub clock_rtt_us: AtomicU64,
+    // Images refusées par la sortie (taille ou résolution différentes du format négocié)
+    pub frames_rejected: AtomicU64,
+    pub pools: [PoolMetrics; 3],
+    sessions: Mutex<Vec<Arc<SessionMetrics>>>,
+    next_session: AtomicU64,
 }
 
 impl ServerMetrics {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
+            started: Instant::now(),
             packet_count: AtomicU64::new(0),
             bytes_received: AtomicU64::new(0),
+            packets_lost: AtomicU64::new(0),
+            packet_rate: RateCounter::default(),
+            byte_rate: RateCounter::default(),
             width: AtomicU64::new(1280),
             height: AtomicU64::new(720),
+            stages: Default::default(),
+            since_capture: Default::default(),
+            clock_offset_us: AtomicI64::new(0),
+            clock_rtt_us: AtomicU64::new(0),
+            frames_rejected: AtomicU64::new(0),
+            pools: Default::default(),
+            sessions: Mutex::new(Vec::new()),
+            next_session: AtomicU64::new(0),
         })
     }
 
+    // Compteurs d'une nouvelle connexion, retirés du snapshot quand le garde est relâché
+    pub fn open_session(self: &Arc<Self>, peer: String) -> SessionHandle {
+        SessionHandle::open(self, peer)
+    }
+
     pub fn record_packet(&self, bytes: u64) {
         self.packet_count.fetch_add(1, Ordering::Relaxed);
         self.bytes_received.fetch_add(bytes, Ordering::Relaxed);
+        self.packet_rate.record(1);
+        self.byte_rate.record(bytes);
+    }
+
+    pub fn record_loss(&self) {
+        self.packets_lost.fetch_add(1, Ordering::Relaxed);
     }
 
     pub fn update_resolution(&self, w: u64, h: u64) {
@@ -30,20 +148,200 @@ impl ServerMetrics {
         self.height.store(h, Ordering::Relaxed);
     }
 
+    pub fn stage(&self, stage: Stage) -> &StageMetrics {
+        &self.stages[stage as usize]
+    }
+
+    pub fn record_stage(&self, stage: Stage, latency: Duration) {
+        let m = self.stage(stage);
+        m.processed.fetch_add(1, Ordering::Relaxed);
+        m.rate.record(1);
+        m.latency.record(latency);
+    }
+
+    pub fn record_drop(&self, stage: Stage) {
+        self.stage(stage).dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, stage: Stage) {
+        self.stage(stage).errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_late(&self, stage: Stage) {
+        self.stage(stage).late.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_queue_capacity(&self, stage: Stage, capacity: usize) {
+        self.stage(stage).queue_capacity.store(capacity as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_queue_depth(&self, stage: Stage, depth: usize) {
+        self.stage(stage).queue_depth.store(depth as u64, Ordering::Relaxed);
+    }
+
+    // Agrège la décomposition de latence d'une frame arrivée jusqu'à la sortie
+    pub fn record_frame_latency(&self, ts: &FrameTimestamps) {
+        for stage in Stage::ALL {
+            if let Some(us) = ts.since_capture(stage) {
+                self.since_capture[stage as usize].record_us(us);
+            }
+        }
+    }
+
+    pub fn pool(&self, class: SizeClass) -> &PoolMetrics {
+        &self.pools[class as usize]
+    }
+
+    pub fn record_rejected(&self) {
+        self.frames_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn update_clock(&self, offset_us: i64, rtt_us: u64) {
+        self.clock_offset_us.store(offset_us, Ordering::Relaxed);
+        self.clock_rtt_us.store(rtt_us, Ordering::Relaxed);
+    }
+
     pub fn snapshot(&self) -> MetricsSnapshot {
+        let output = self.stage(Stage::Output);
         MetricsSnapshot {
-            packet_count: self.packet_count.load(Ordering::Relaxed),
-            bytes_received: self.bytes_received.load(Ordering::Relaxed),
-            width: self.width.load(Ordering::Relaxed),
-            height: self.height.load(Ordering::Relaxed),
+            version: SCHEMA_VERSION,
+            uptime_s: self.started.elapsed().as_secs(),
+            totals: TotalsSnapshot {
+                packets: self.packet_count.load(Ordering::Relaxed),
+                bytes: self.bytes_received.load(Ordering::Relaxed),
+                packets_lost: self.packets_lost.load(Ordering::Relaxed),
+                frames_out: output.processed.load(Ordering::Relaxed),
+                frames_rejected: self.frames_rejected.load(Ordering::Relaxed),
+            },
+            rates: RatesSnapshot {
+                window_s: RATE_WINDOW_SECS,
+                packet_rate: self.packet_rate.rate(RATE_WINDOW_SECS),
+                bitrate_bps: self.byte_rate.rate(RATE_WINDOW_SECS) * 8.0,
+                output_fps: output.rate.rate(RATE_WINDOW_SECS),
+            },
+            resolution: ResolutionSnapshot {
+                width: self.width.load(Ordering::Relaxed),
+                height: self.height.load(Ordering::Relaxed),
+            },
+            stages: Stage::ALL
+                .iter()
+                .map(|&stage| {
+                    let m = self.stage(stage);
+                    StageSnapshot {
+                        stage: stage.name(),
+                        processed: m.processed.load(Ordering::Relaxed),
+                        dropped: m.dropped.load(Ordering::Relaxed),
+                        errors: m.errors.load(Ordering::Relaxed),
+                        late: m.late.load(Ordering::Relaxed),
+                        queue_depth: m.queue_depth.load(Ordering::Relaxed),
+                        queue_capacity: m.queue_capacity.load(Ordering::Relaxed),
+                        rate: m.rate.rate(RATE_WINDOW_SECS),
+                        latency: m.latency.snapshot(),
+                    }
+                })
+                .collect(),
+            pools: SizeClass::ALL
+                .iter()
+                .map(|&class| {
+                    let m = self.pool(class);
+                    PoolSnapshot {
+                        class: class.name(),
+                        capacity: m.capacity.load(Ordering::Relaxed),
+                        in_use: m.in_use.load(Ordering::Relaxed),
+                        exhausted: m.exhausted.load(Ordering::Relaxed),
+                        oversized: m.oversized.load(Ordering::Relaxed),
+                    }
+                })
+                .collect(),
+            sessions: self.sessions.lock().iter().map(|s| s.snapshot()).collect(),
+            latency: LatencySnapshot {
+                clock_offset_us: self.clock_offset_us.load(Ordering::Relaxed),
+                clock_rtt_us: self.clock_rtt_us.load(Ordering::Relaxed),
+                glass_to_glass: self.since_capture[Stage::Output as usize].snapshot(),
+                since_capture: Stage::ALL
+                    .iter()
+                    .map(|&stage| StageLatencySnapshot {
+                        stage: stage.name(),
+                        latency: self.since_capture[stage as usize].snapshot(),
+                    })
+                    .collect(),
+            },
         }
     }
 }
 
+// Modèle publié sur /stats ; incrémenter SCHEMA_VERSION à chaque changement incompatible
 #[derive(Serialize, Clone)]
 pub struct MetricsSnapshot {
-    pub packet_count: u64,
-    pub bytes_received: u64,
+    pub version: u32,
+    pub uptime_s: u64,
+    pub totals: TotalsSnapshot,
+    pub rates: RatesSnapshot,
+    pub resolution: ResolutionSnapshot,
+    pub stages: Vec<StageSnapshot>,
+    pub pools: Vec<PoolSnapshot>,
+    pub sessions: Vec<SessionSnapshot>,
+    pub latency: LatencySnapshot,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TotalsSnapshot {
+    pub packets: u64,
+    pub bytes: u64,
+    pub packets_lost: u64,
+    pub frames_out: u64,
+    pub frames_rejected: u64,
+}
+
+// Moyennes sur les `window_s` dernières secondes complètes
+#[derive(Serialize, Clone)]
+pub struct RatesSnapshot {
+    pub window_s: u64,
+    pub packet_rate: f64,
+    pub bitrate_bps: f64,
+    pub output_fps: f64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ResolutionSnapshot {
     pub width: u64,
     pub height: u64,
 }
+
+#[derive(Serialize, Clone)]
+pub struct StageSnapshot {
+    pub stage: &'static str,
+    pub processed: u64,
+    pub dropped: u64,
+    pub errors: u64,
+    pub late: u64,
+    pub queue_depth: u64,
+    pub queue_capacity: u64,
+    // Éléments traités par seconde
+    pub rate: f64,
+    pub latency: HistogramSnapshot,
+}
+
+#[derive(Serialize, Clone)]
+pub struct PoolSnapshot {
+    pub class: &'static str,
+    pub capacity: u64,
+    pub in_use: u64,
+    pub exhausted: u64,
+    pub oversized: u64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LatencySnapshot {
+    pub clock_offset_us: i64,
+    pub clock_rtt_us: u64,
+    // Capture téléphone → écriture V4L2
+    pub glass_to_glass: HistogramSnapshot,
+    pub since_capture: Vec<StageLatencySnapshot>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StageLatencySnapshot {
+    pub stage: &'static str,
+    pub latency: HistogramSnapshot,
+}
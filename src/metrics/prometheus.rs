@@ -0,0 +1,326 @@
+// Exposition texte pour Prometheus / OpenMetrics (route /metrics), construite à partir du même
+// MetricsSnapshot que /stats. Latences en secondes, histogrammes en buckets cumulés.
+use std::fmt::Write;
+use crate::metrics::histogram::{HistogramSnapshot, BUCKET_BOUNDS_US};
+use crate::metrics::session::SessionSnapshot;
+use crate::metrics::{MetricsSnapshot, StageSnapshot};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    // text/plain version 0.0.4
+    Prometheus,
+    // application/openmetrics-text 1.0.0 : familles de compteurs sans "_total", "# EOF" final
+    OpenMetrics,
+}
+
+impl Format {
+    // D'après l'en-tête Accept du scraper ; Prometheus par défaut
+    pub fn negotiate(accept: Option<&str>) -> Self {
+        match accept {
+            Some(accept) if accept.contains("application/openmetrics-text") => Format::OpenMetrics,
+            _ => Format::Prometheus,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Prometheus => "text/plain; version=0.0.4; charset=utf-8",
+            Format::OpenMetrics => "application/openmetrics-text; version=1.0.0; charset=utf-8",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+struct Exposition {
+    out: String,
+    format: Format,
+}
+
+type Labels<'a> = &'a [(&'a str, &'a str)];
+
+impl Exposition {
+    // En-têtes HELP/TYPE d'une famille ; les compteurs prennent "_total" à chaque échantillon
+    fn family(&mut self, name: &str, kind: Kind, help: &str) {
+        let family = match (kind, self.format) {
+            (Kind::Counter, Format::Prometheus) => format!("{}_total", name),
+            _ => name.to_string(),
+        };
+        let _ = writeln!(self.out, "# HELP {} {}", family, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", family, kind.name());
+    }
+
+    fn sample(&mut self, name: &str, labels: Labels, value: impl std::fmt::Display) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (key, value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{}=\"{}\"", key, escape(value));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    fn counter(&mut self, name: &str, labels: Labels, value: u64) {
+        self.sample(&format!("{}_total", name), labels, value);
+    }
+
+    fn histogram(&mut self, name: &str, labels: Labels, h: &HistogramSnapshot) {
+        let bucket = format!("{}_bucket", name);
+        let mut cumulative = 0;
+        for (i, n) in h.buckets.iter().enumerate() {
+            cumulative += n;
+            let le = match BUCKET_BOUNDS_US.get(i) {
+                Some(&bound) => self.bound(seconds(bound)),
+                None => "+Inf".to_string(),
+            };
+            let mut with_le = labels.to_vec();
+            with_le.push(("le", &le));
+            self.sample(&bucket, &with_le, cumulative);
+        }
+        self.sample(&format!("{}_sum", name), labels, seconds(h.sum_us));
+        self.sample(&format!("{}_count", name), labels, h.count);
+    }
+
+    // OpenMetrics impose la forme canonique des bornes ("1.0", pas "1") : sinon deux écritures
+    // de la même borne font deux séries
+    fn bound(&self, value: f64) -> String {
+        let text = value.to_string();
+        match self.format {
+            Format::OpenMetrics if !text.contains('.') => format!("{}.0", text),
+            _ => text,
+        }
+    }
+}
+
+fn seconds(us: u64) -> f64 {
+    us as f64 / 1e6
+}
+
+// Échappement des valeurs d'étiquettes : \, " et retour à la ligne
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+pub fn render(snapshot: &MetricsSnapshot, format: Format) -> String {
+    let mut e = Exposition { out: String::new(), format };
+    let totals = &snapshot.totals;
+    let rates = &snapshot.rates;
+
+    e.family("phonecam_uptime_seconds", Kind::Gauge, "Temps écoulé depuis le démarrage du serveur.");
+    e.sample("phonecam_uptime_seconds", &[], snapshot.uptime_s);
+
+    for (name, help, value) in [
+        ("phonecam_packets_received", "Paquets reçus sur toutes les sessions /raw.", totals.packets),
+        ("phonecam_bytes_received", "Octets reçus sur toutes les sessions /raw.", totals.bytes),
+        ("phonecam_packets_lost", "Paquets refusés par la file d'entrée de la pipeline.", totals.packets_lost),
+        ("phonecam_frames_output", "Images écrites sur la sortie.", totals.frames_out),
+        ("phonecam_frames_rejected", "Images refusées par la sortie (taille différente du format négocié).", totals.frames_rejected),
+    ] {
+        e.family(name, Kind::Counter, help);
+        e.counter(name, &[], value);
+    }
+
+    for (name, help, value) in [
+        ("phonecam_packets_per_second", "Débit de paquets reçus (moyenne glissante).", rates.packet_rate),
+        ("phonecam_bitrate_bits_per_second", "Débit reçu (moyenne glissante).", rates.bitrate_bps),
+        ("phonecam_output_frames_per_second", "Images écrites par seconde (moyenne glissante).", rates.output_fps),
+        ("phonecam_input_width_pixels", "Largeur annoncée par le téléphone.", snapshot.resolution.width as f64),
+        ("phonecam_input_height_pixels", "Hauteur annoncée par le téléphone.", snapshot.resolution.height as f64),
+        ("phonecam_clock_offset_seconds", "Décalage d'horloge téléphone/serveur estimé.", snapshot.latency.clock_offset_us as f64 / 1e6),
+        ("phonecam_clock_rtt_seconds", "Aller-retour WebSocket de la dernière estimation d'horloge.", seconds(snapshot.latency.clock_rtt_us)),
+    ] {
+        e.family(name, Kind::Gauge, help);
+        e.sample(name, &[], value);
+    }
+
+    // Par étage de la pipeline
+    type StageCounter = fn(&StageSnapshot) -> u64;
+    let counters: [(&str, &str, StageCounter); 4] = [
+        ("phonecam_stage_processed", "Éléments traités par l'étage.", |s| s.processed),
+        ("phonecam_stage_dropped", "Éléments jetés sous surcharge.", |s| s.dropped),
+        ("phonecam_stage_errors", "Échecs de traitement (décodage, conversion).", |s| s.errors),
+        ("phonecam_stage_late", "Ticks de sortie sans image neuve.", |s| s.late),
+    ];
+    for (name, help, value) in counters {
+        e.family(name, Kind::Counter, help);
+        for stage in &snapshot.stages {
+            e.counter(name, &[("stage", stage.stage)], value(stage));
+        }
+    }
+    type StageGauge = fn(&StageSnapshot) -> f64;
+    let gauges: [(&str, &str, StageGauge); 3] = [
+        ("phonecam_stage_queue_depth", "Éléments en attente dans la file d'entrée de l'étage.", |s| s.queue_depth as f64),
+        ("phonecam_stage_queue_capacity", "Capacité de la file d'entrée de l'étage.", |s| s.queue_capacity as f64),
+        ("phonecam_stage_items_per_second", "Éléments traités par seconde (moyenne glissante).", |s| s.rate),
+    ];
+    for (name, help, value) in gauges {
+        e.family(name, Kind::Gauge, help);
+        for stage in &snapshot.stages {
+            e.sample(name, &[("stage", stage.stage)], value(stage));
+        }
+    }
+    e.family("phonecam_stage_latency_seconds", Kind::Histogram, "Temps passé dans l'étage.");
+    for stage in &snapshot.stages {
+        e.histogram("phonecam_stage_latency_seconds", &[("stage", stage.stage)], &stage.latency);
+    }
+    e.family("phonecam_latency_since_capture_seconds", Kind::Histogram, "Capture téléphone → fin de l'étage (output : glass-to-glass).");
+    for stage in &snapshot.latency.since_capture {
+        e.histogram("phonecam_latency_since_capture_seconds", &[("stage", stage.stage)], &stage.latency);
+    }
+
+    // FramePool, par classe de taille
+    e.family("phonecam_pool_buffers", Kind::Gauge, "Tampons alloués dans la classe.");
+    for pool in &snapshot.pools {
+        e.sample("phonecam_pool_buffers", &[("class", pool.class)], pool.capacity);
+    }
+    e.family("phonecam_pool_buffers_in_use", Kind::Gauge, "Tampons empruntés.");
+    for pool in &snapshot.pools {
+        e.sample("phonecam_pool_buffers_in_use", &[("class", pool.class)], pool.in_use);
+    }
+    e.family("phonecam_pool_exhausted", Kind::Counter, "Demandes sans tampon libre.");
+    for pool in &snapshot.pools {
+        e.counter("phonecam_pool_exhausted", &[("class", pool.class)], pool.exhausted);
+    }
+    e.family("phonecam_pool_oversized", Kind::Counter, "Tampons huge pages trop petits, remplacés par le tas.");
+    for pool in &snapshot.pools {
+        e.counter("phonecam_pool_oversized", &[("class", pool.class)], pool.oversized);
+    }
+
+    // Sessions ouvertes ; les séries disparaissent à la déconnexion
+    type SessionValue = fn(&SessionSnapshot) -> f64;
+    let session_metrics: [(&str, Kind, &str, SessionValue); 8] = [
+        ("phonecam_session_packets", Kind::Counter, "Paquets reçus par la session.", |s| s.packets as f64),
+        ("phonecam_session_bytes", Kind::Counter, "Octets reçus par la session.", |s| s.bytes as f64),
+        ("phonecam_session_packets_lost", Kind::Counter, "Paquets de la session refusés par la pipeline.", |s| s.lost as f64),
+        ("phonecam_session_frames_per_second", Kind::Gauge, "Images compressées reçues par seconde.", |s| s.fps),
+        ("phonecam_session_bitrate_bits_per_second", Kind::Gauge, "Débit reçu de la session.", |s| s.bitrate_bps),
+        ("phonecam_session_width_pixels", Kind::Gauge, "Largeur annoncée par le téléphone.", |s| s.width as f64),
+        ("phonecam_session_height_pixels", Kind::Gauge, "Hauteur annoncée par le téléphone.", |s| s.height as f64),
+        ("phonecam_session_clock_rtt_seconds", Kind::Gauge, "Aller-retour de la dernière estimation d'horloge.", |s| seconds(s.clock_rtt_us)),
+    ];
+    for (name, kind, help, value) in session_metrics {
+        e.family(name, kind, help);
+        for s in &snapshot.sessions {
+            let id = s.id.to_string();
+            let labels = [("session", id.as_str()), ("peer", s.peer.as_str()), ("codec", s.codec.as_deref().unwrap_or(""))];
+            match kind {
+                Kind::Counter => e.counter(name, &labels, value(s) as u64),
+                _ => e.sample(name, &labels, value(s)),
+            }
+        }
+    }
+
+    if format == Format::OpenMetrics {
+        e.out.push_str("# EOF\n");
+    }
+    e.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use crate::metrics::{LatencySnapshot, PoolSnapshot, RatesSnapshot, ResolutionSnapshot, StageLatencySnapshot, TotalsSnapshot};
+
+    fn histogram(count: u64, sum_us: u64, filled: &[(usize, u64)]) -> HistogramSnapshot {
+        let mut buckets = vec![0; BUCKET_BOUNDS_US.len() + 1];
+        for &(i, n) in filled {
+            buckets[i] = n;
+        }
+        HistogramSnapshot { count, sum_us, p50_us: 0, p95_us: 0, p99_us: 0, buckets }
+    }
+
+    fn stage(stage: &'static str, processed: u64, latency: HistogramSnapshot) -> StageSnapshot {
+        StageSnapshot {
+            stage,
+            processed,
+            dropped: 2,
+            errors: 1,
+            late: 0,
+            queue_depth: 3,
+            queue_capacity: 32,
+            rate: 29.5,
+            latency,
+        }
+    }
+
+    // Valeurs fixes : bornes entières (1 s, 5 s), étiquettes à échapper, codec absent
+    fn snapshot() -> MetricsSnapshot {
+        let session = |id, peer: &str, codec: Option<&str>| SessionSnapshot {
+            id,
+            peer: peer.to_string(),
+            codec: codec.map(str::to_string),
+            uptime_s: 42,
+            width: 1920,
+            height: 1080,
+            packets: 1200,
+            bytes: 3_500_000,
+            lost: 4,
+            fps: 30.0,
+            bitrate_bps: 2_500_000.5,
+            clock_offset_us: -1500,
+            clock_rtt_us: 8000,
+        };
+        MetricsSnapshot {
+            version: 1,
+            uptime_s: 3600,
+            totals: TotalsSnapshot { packets: 1200, bytes: 3_500_000, packets_lost: 4, frames_out: 1100, frames_rejected: 0 },
+            rates: RatesSnapshot { window_s: 5, packet_rate: 30.0, bitrate_bps: 2_500_000.5, output_fps: 29.97 },
+            resolution: ResolutionSnapshot { width: 1920, height: 1080 },
+            stages: vec![
+                stage("decode", 1150, histogram(1150, 4_600_000, &[(5, 100), (6, 1000), (7, 50)])),
+                stage("output", 1100, histogram(1100, 1_100_000_000, &[(14, 1000), (15, 99), (16, 1)])),
+            ],
+            pools: vec![PoolSnapshot { class: "raw_frame", capacity: 8, in_use: 3, exhausted: 0, oversized: 1 }],
+            sessions: vec![session(1, "192.168.1.20:51234", Some("avc1.42E01F")), session(2, "[fe80::1]:\"x\"\n", None)],
+            latency: LatencySnapshot {
+                clock_offset_us: -1500,
+                clock_rtt_us: 8000,
+                glass_to_glass: histogram(0, 0, &[]),
+                since_capture: vec![StageLatencySnapshot { stage: "output", latency: histogram(2, 66_000, &[(9, 2)]) }],
+            },
+        }
+    }
+
+    // UPDATE_GOLDEN=1 cargo test metrics:: réécrit les fichiers après un changement voulu du format
+    fn check_golden(name: &str, rendered: &str) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, rendered).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{} : {}", path.display(), e));
+        assert!(rendered == expected, "{} diffère du rendu (UPDATE_GOLDEN=1 pour le régénérer)", path.display());
+    }
+
+    #[test]
+    fn prometheus_matches_golden() {
+        check_golden("metrics.prom", &render(&snapshot(), Format::Prometheus));
+    }
+
+    #[test]
+    fn openmetrics_matches_golden() {
+        let rendered = render(&snapshot(), Format::OpenMetrics);
+        assert!(rendered.contains("le=\"1.0\"") && rendered.contains("le=\"5.0\""));
+        check_golden("metrics.openmetrics", &rendered);
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::net::clock::now_us;
+
+// Secondes conservées : borne la fenêtre maximale de rate()
+const SLOTS: usize = 16;
+// Fenêtre des débits publiés dans les snapshots
+pub const RATE_WINDOW_SECS: u64 = 5;
+
+// Débit glissant sans verrou : un compteur par seconde dans un anneau. Au changement de seconde,
+// le premier thread qui recycle la case la remet à zéro ; un incrément concurrent à ce moment
+// précis peut être perdu, ce qui reste négligeable pour des débits affichés.
+#[derive(Debug, Default)]
+pub struct RateCounter {
+    slots: [Slot; SLOTS],
+}
+
+#[derive(Debug, Default)]
+struct Slot {
+    // Seconde (horloge monotone de now_us) à laquelle se rapporte `count`, +1 (0 : jamais utilisée)
+    second: AtomicU64,
+    count: AtomicU64,
+}
+
+fn current_second() -> u64 {
+    now_us() / 1_000_000 + 1
+}
+
+impl RateCounter {
+    pub fn record(&self, n: u64) {
+        self.record_at(current_second(), n);
+    }
+
+    fn record_at(&self, second: u64, n: u64) {
+        let slot = &self.slots[second as usize % SLOTS];
+        let seen = slot.second.load(Ordering::Acquire);
+        if seen != second && slot.second.compare_exchange(seen, second, Ordering::AcqRel, Ordering::Acquire).is_ok() {
+            slot.count.store(0, Ordering::Release);
+        }
+        slot.count.fetch_add(n, Ordering::Relaxed);
+    }
+
+    // Moyenne par seconde sur les `window` dernières secondes complètes (la seconde en cours est exclue)
+    pub fn rate(&self, window: u64) -> f64 {
+        self.rate_at(current_second(), window)
+    }
+
+    fn rate_at(&self, now: u64, window: u64) -> f64 {
+        let window = window.clamp(1, SLOTS as u64 - 1);
+        let total: u64 = self
+            .slots
+            .iter()
+            .filter(|slot| {
+                let second = slot.second.load(Ordering::Acquire);
+                second < now && second + window >= now
+            })
+            .map(|slot| slot.count.load(Ordering::Relaxed))
+            .sum();
+        total as f64 / window as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_averages_complete_seconds() {
+        let rate = RateCounter::default();
+        for second in 100..105 {
+            rate.record_at(second, 10);
+            rate.record_at(second, 20);
+        }
+        // Seconde en cours : exclue tant qu'elle n'est pas terminée
+        rate.record_at(105, 1_000);
+        assert_eq!(rate.rate_at(105, 5), 30.0);
+        assert_eq!(rate.rate_at(105, 2), 30.0);
+        assert_eq!(rate.rate_at(106, 5), (4.0 * 30.0 + 1_000.0) / 5.0);
+    }
+
+    #[test]
+    fn window_skips_old_and_idle_seconds() {
+        let rate = RateCounter::default();
+        rate.record_at(100, 50);
+        // Deux secondes sans rien : la moyenne les compte à zéro
+        rate.record_at(103, 10);
+        assert_eq!(rate.rate_at(104, 4), 15.0);
+        assert_eq!(rate.rate_at(104, 1), 10.0);
+        // Sortie de la fenêtre
+        assert_eq!(rate.rate_at(110, 5), 0.0);
+        // Fenêtre bornée par l'anneau ; 0 compte pour 1
+        assert_eq!(rate.rate_at(101, 0), 50.0);
+        assert_eq!(rate.rate_at(104, 100), 60.0 / (SLOTS as f64 - 1.0));
+    }
+
+    #[test]
+    fn recycled_slot_starts_from_zero() {
+        let rate = RateCounter::default();
+        rate.record_at(1, 7);
+        // Même case de l'anneau, SLOTS secondes plus tard
+        rate.record_at(1 + SLOTS as u64, 3);
+        assert_eq!(rate.rate_at(2 + SLOTS as u64, 1), 3.0);
+    }
+}
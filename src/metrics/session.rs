@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use parking_lot::Mutex;
+use serde::Serialize;
+use crate::metrics::rate::{RateCounter, RATE_WINDOW_SECS};
+use crate::metrics::ServerMetrics;
+
+// Compteurs d'une connexion téléphone (/raw) ; les totaux serveur sont mis à jour en même temps
+#[derive(Debug)]
+pub struct SessionMetrics {
+    pub id: u64,
+    pub peer: String,
+    started: Instant,
+    codec: Mutex<Option<String>>,
+    width: AtomicU64,
+    height: AtomicU64,
+    packets: AtomicU64,
+    bytes: AtomicU64,
+    // Paquets refusés par la pipeline (file pleine) : la session doit repartir d'une keyframe
+    lost: AtomicU64,
+    packet_rate: RateCounter,
+    byte_rate: RateCounter,
+    clock_offset_us: AtomicI64,
+    clock_rtt_us: AtomicU64,
+}
+
+// Session enregistrée dans ServerMetrics tant que le garde existe (fin du WebSocket)
+pub struct SessionHandle {
+    session: Arc<SessionMetrics>,
+    server: Arc<ServerMetrics>,
+}
+
+impl SessionHandle {
+    pub(super) fn open(server: &Arc<ServerMetrics>, peer: String) -> Self {
+        let session = Arc::new(SessionMetrics {
+            id: server.next_session.fetch_add(1, Ordering::Relaxed) + 1,
+            peer,
+            started: Instant::now(),
+            codec: Mutex::new(None),
+            width: AtomicU64::new(0),
+            height: AtomicU64::new(0),
+            packets: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            lost: AtomicU64::new(0),
+            packet_rate: RateCounter::default(),
+            byte_rate: RateCounter::default(),
+            clock_offset_us: AtomicI64::new(0),
+            clock_rtt_us: AtomicU64::new(0),
+        });
+        server.sessions.lock().push(session.clone());
+        Self {
+            session,
+            server: server.clone(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.session.id
+    }
+
+    pub fn record_packet(&self, bytes: u64) {
+        let s = &self.session;
+        s.packets.fetch_add(1, Ordering::Relaxed);
+        s.bytes.fetch_add(bytes, Ordering::Relaxed);
+        s.packet_rate.record(1);
+        s.byte_rate.record(bytes);
+        self.server.record_packet(bytes);
+    }
+
+    pub fn record_loss(&self) {
+        self.session.lost.fetch_add(1, Ordering::Relaxed);
+        self.server.record_loss();
+    }
+
+    pub fn set_codec(&self, codec: &str) {
+        *self.session.codec.lock() = Some(codec.to_string());
+    }
+
+    pub fn update_resolution(&self, w: u64, h: u64) {
+        self.session.width.store(w, Ordering::Relaxed);
+        self.session.height.store(h, Ordering::Relaxed);
+        self.server.update_resolution(w, h);
+    }
+
+    pub fn update_clock(&self, offset_us: i64, rtt_us: u64) {
+        self.session.clock_offset_us.store(offset_us, Ordering::Relaxed);
+        self.session.clock_rtt_us.store(rtt_us, Ordering::Relaxed);
+        self.server.update_clock(offset_us, rtt_us);
+    }
+}
+
+impl Drop for SessionHandle {
+    fn drop(&mut self) {
+        self.server.sessions.lock().retain(|s| s.id != self.session.id);
+    }
+}
+
+impl SessionMetrics {
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            id: self.id,
+            peer: self.peer.clone(),
+            codec: self.codec.lock().clone(),
+            uptime_s: self.started.elapsed().as_secs(),
+            width: self.width.load(Ordering::Relaxed),
+            height: self.height.load(Ordering::Relaxed),
+            packets: self.packets.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            lost: self.lost.load(Ordering::Relaxed),
+            fps: self.packet_rate.rate(RATE_WINDOW_SECS),
+            bitrate_bps: self.byte_rate.rate(RATE_WINDOW_SECS) * 8.0,
+            clock_offset_us: self.clock_offset_us.load(Ordering::Relaxed),
+            clock_rtt_us: self.clock_rtt_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct SessionSnapshot {
+    pub id: u64,
+    pub peer: String,
+    pub codec: Option<String>,
+    pub uptime_s: u64,
+    pub width: u64,
+    pub height: u64,
+    pub packets: u64,
+    pub bytes: u64,
+    pub lost: u64,
+    // Un message WebSocket = une image compressée
+    pub fps: f64,
+    pub bitrate_bps: f64,
+    pub clock_offset_us: i64,
+    pub clock_rtt_us: u64,
+}
@@ -0,0 +1,197 @@
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+// Nombre d'échanges ping/pong conservés pour l'estimation
+const SAMPLE_WINDOW: usize = 8;
+// Au-delà, la réponse est trop vieille pour être utile
+const MAX_RTT_US: u64 = 5_000_000;
+
+// Horloge du serveur en µs depuis l'epoch Unix, monotone : ancrée une seule fois sur
+// SystemTime puis avancée par Instant (insensible aux sauts NTP pendant la session).
+pub fn now_us() -> u64 {
+    static ANCHOR: OnceLock<(Instant, u64)> = OnceLock::new();
+    let (instant, epoch_us) = ANCHOR.get_or_init(|| {
+        let epoch_us = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        (Instant::now(), epoch_us)
+    });
+    epoch_us + instant.elapsed().as_micros() as u64
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClockEstimate {
+    // horloge téléphone - horloge serveur
+    pub offset_us: i64,
+    pub rtt_us: u64,
+}
+
+impl ClockEstimate {
+    // Ramène un horodatage du téléphone dans l'horloge du serveur
+    pub fn to_server_us(self, client_us: u64) -> u64 {
+        (client_us as i64 - self.offset_us).max(0) as u64
+    }
+}
+
+// Estimation du décalage d'horloge téléphone/serveur par ping/pong sur le WebSocket.
+// Comme NTP, on garde l'échantillon au RTT minimal : c'est le moins biaisé par la file réseau.
+pub struct ClockSync {
+    samples: VecDeque<ClockEstimate>,
+    // Pings sans réponse (id, envoi), du plus ancien au plus récent
+    pending: VecDeque<(u64, u64)>,
+    next_id: u64,
+}
+
+impl ClockSync {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(SAMPLE_WINDOW),
+            pending: VecDeque::with_capacity(SAMPLE_WINDOW),
+            next_id: 0,
+        }
+    }
+
+    // Message envoyé au téléphone, qui répond {"type":"pong", id, server_us, client_us}
+    pub fn ping_message(&mut self) -> String {
+        self.ping_message_at(now_us())
+    }
+
+    fn ping_message_at(&mut self, now: u64) -> String {
+        self.next_id += 1;
+        if self.pending.len() == SAMPLE_WINDOW {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((self.next_id, now));
+        serde_json::json!({
+            "type": "ping",
+            "id": self.next_id,
+            "server_us": now,
+        })
+        .to_string()
+    }
+
+    pub fn on_pong(&mut self, val: &serde_json::Value) -> Option<ClockEstimate> {
+        self.on_pong_at(val, now_us())
+    }
+
+    // Le pong doit répondre à un ping en attente : doublon, id inconnu ou réponse à un ping
+    // plus ancien que le dernier répondu sont ignorés
+    fn on_pong_at(&mut self, val: &serde_json::Value, now: u64) -> Option<ClockEstimate> {
+        let id = val["id"].as_u64()?;
+        let client_us = val["client_us"].as_u64()?;
+
+        let index = self.pending.iter().position(|&(pending, _)| pending == id)?;
+        let (_, server_us) = self.pending[index];
+        // Les pings antérieurs n'ont plus de réponse utile
+        self.pending.drain(..=index);
+
+        let rtt_us = now.checked_sub(server_us)?;
+        if rtt_us > MAX_RTT_US {
+            return None;
+        }
+
+        // Le client a horodaté la réception à mi-chemin (hypothèse de liens symétriques)
+        let offset_us = client_us as i64 - (server_us + rtt_us / 2) as i64;
+
+        if self.samples.len() == SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(ClockEstimate { offset_us, rtt_us });
+        self.estimate()
+    }
+
+    pub fn estimate(&self) -> Option<ClockEstimate> {
+        self.samples.iter().min_by_key(|s| s.rtt_us).copied()
+    }
+}
+
+impl Default for ClockSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pong(id: u64, client_us: u64) -> serde_json::Value {
+        json!({ "type": "pong", "id": id, "client_us": client_us })
+    }
+
+    #[test]
+    fn offset_assumes_symmetric_links() {
+        let mut clock = ClockSync::new();
+        clock.ping_message_at(1_000_000);
+        // Téléphone en avance de 250 ms, 40 ms aller-retour
+        let estimate = clock.on_pong_at(&pong(1, 1_270_000), 1_040_000).unwrap();
+        assert_eq!((estimate.offset_us, estimate.rtt_us), (250_000, 40_000));
+        assert_eq!(estimate.to_server_us(1_270_000), 1_020_000);
+
+        // Téléphone en retard : décalage négatif, horodatage ramené sans déborder
+        let mut clock = ClockSync::new();
+        clock.ping_message_at(5_000_000);
+        let estimate = clock.on_pong_at(&pong(1, 1_005_000), 5_010_000).unwrap();
+        assert_eq!(estimate.offset_us, -4_000_000);
+        assert_eq!(estimate.to_server_us(1_005_000), 5_005_000);
+        assert_eq!(ClockEstimate { offset_us: 10, rtt_us: 0 }.to_server_us(5), 0);
+    }
+
+    #[test]
+    fn lowest_rtt_sample_wins() {
+        let mut clock = ClockSync::new();
+        clock.ping_message_at(0);
+        clock.on_pong_at(&pong(1, 100_000), 80_000).unwrap();
+        clock.ping_message_at(1_000_000);
+        let best = clock.on_pong_at(&pong(2, 1_105_000), 1_010_000).unwrap();
+        assert_eq!((best.offset_us, best.rtt_us), (100_000, 10_000));
+        // Un échantillon plus lent ne remplace pas le meilleur
+        clock.ping_message_at(2_000_000);
+        let best = clock.on_pong_at(&pong(3, 2_200_000), 2_200_000).unwrap();
+        assert_eq!(best.rtt_us, 10_000);
+
+        // Le meilleur sort de la fenêtre après SAMPLE_WINDOW nouveaux échantillons
+        for i in 0..SAMPLE_WINDOW as u64 {
+            let sent = 10_000_000 + i * 1_000_000;
+            clock.ping_message_at(sent);
+            clock.on_pong_at(&pong(4 + i, sent + 100_000), sent + 50_000).unwrap();
+        }
+        assert_eq!(clock.estimate().unwrap().rtt_us, 50_000);
+    }
+
+    #[test]
+    fn pongs_are_matched_on_id() {
+        let mut clock = ClockSync::new();
+        clock.ping_message_at(0);
+        clock.ping_message_at(100_000);
+        clock.ping_message_at(200_000);
+
+        // Id inconnu, ou absent
+        assert!(clock.on_pong_at(&pong(9, 0), 210_000).is_none());
+        assert!(clock.on_pong_at(&json!({ "client_us": 0 }), 210_000).is_none());
+        // Réponse au ping 2 : RTT mesuré depuis son propre envoi, pas depuis le ping 3
+        assert_eq!(clock.on_pong_at(&pong(2, 0), 130_000).unwrap().rtt_us, 30_000);
+        // Doublon et réponse en retard au ping 1 : ignorés
+        assert!(clock.on_pong_at(&pong(2, 0), 140_000).is_none());
+        assert!(clock.on_pong_at(&pong(1, 0), 150_000).is_none());
+        assert_eq!(clock.on_pong_at(&pong(3, 0), 205_000).unwrap().rtt_us, 5_000);
+    }
+
+    #[test]
+    fn stale_pongs_are_dropped() {
+        let mut clock = ClockSync::new();
+        clock.ping_message_at(0);
+        assert!(clock.on_pong_at(&pong(1, 0), MAX_RTT_US + 1).is_none());
+        assert!(clock.estimate().is_none());
+
+        // Pings jamais répondus : seuls les SAMPLE_WINDOW derniers restent attendus
+        for i in 0..SAMPLE_WINDOW as u64 + 1 {
+            clock.ping_message_at(i);
+        }
+        assert!(clock.on_pong_at(&pong(2, 0), 1_000).is_none());
+        assert!(clock.on_pong_at(&pong(3, 0), 1_000).is_some());
+    }
+}
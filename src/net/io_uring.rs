@@ -3,16 +3,18 @@ use std::io;
 use std::net::UdpSocket;
 use std::os::unix::io::AsRawFd;
 use std::sync::Arc;
-use crate::memory::pool::BufferPool;
+use crate::memory::pool::{FramePool, PooledBuf, SizeClass};
+
+const MAX_DATAGRAM: usize = 64 * 1024;
 
 pub struct UringReceiver {
     ring: IoUring,
     socket: UdpSocket,
-    buffer_pool: Arc<BufferPool>,
+    buffer_pool: Arc<FramePool>,
 }
 
 impl UringReceiver {
-    pub fn new(port: u16, buffer_pool: Arc<BufferPool>) -> io::Result<Self> {
+    pub fn new(port: u16, buffer_pool: Arc<FramePool>) -> io::Result<Self> {
         let socket = UdpSocket::bind(format!("0.0.0.0:{}", port))?;
         socket.set_nonblocking(true)?;
         
@@ -28,9 +30,10 @@ impl UringReceiver {
         })
     }
     
-    pub async fn recv(&mut self) -> io::Result<Vec<u8>> {
+    pub async fn recv(&mut self) -> io::Result<PooledBuf> {
         let fd = self.socket.as_raw_fd();
-        let mut buffer = self.buffer_pool.acquire().await;
+        let mut buffer = self.buffer_pool.acquire(SizeClass::Packet);
+        buffer.resize(MAX_DATAGRAM);
         
         let recv_e = opcode::Recv::new(
             types::Fd(fd),
@@ -53,7 +56,7 @@ impl UringReceiver {
             return Err(io::Error::from_raw_os_error(-bytes_read));
         }
         
-        buffer.truncate(bytes_read as usize);
+        buffer.resize(bytes_read as usize);
         Ok(buffer)
     }
 }
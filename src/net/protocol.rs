@@ -1,13 +1,14 @@
 pub struct Header {
-    pub magic: [u8; 2],
-    pub frame_type: u8,
-    pub flags: u8,
     pub payload_length: u32,
+    // Horodatage de capture côté téléphone (µs, horloge du client), si FLAG_CAPTURE_TIMESTAMP
+    pub capture_us: Option<u64>,
 }
 
 impl Header {
     pub const SIZE: usize = 8;
     pub const MAGIC: [u8; 2] = [0x50, 0x43]; // "PC"
+    pub const FLAG_CAPTURE_TIMESTAMP: u8 = 0x01;
+    pub const TIMESTAMP_SIZE: usize = 8;
 
     pub fn parse(data: &[u8]) -> Option<Self> {
         if data.len() < Self::SIZE {
@@ -20,17 +21,33 @@ impl Header {
 
         let payload_length = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
 
+        // Extension optionnelle : u64 BE juste après l'en-tête
+        let capture_us = if data[3] & Self::FLAG_CAPTURE_TIMESTAMP != 0 {
+            let ts = data.get(Self::SIZE..Self::SIZE + Self::TIMESTAMP_SIZE)?;
+            Some(u64::from_be_bytes(ts.try_into().ok()?))
+        } else {
+            None
+        };
+
         Some(Self {
-            magic: [data[0], data[1]],
-            frame_type: data[2],
-            flags: data[3],
             payload_length,
+            capture_us,
         })
     }
-}
 
-pub enum FrameType {
-    P = 0x00,
-    I = 0x01,
-    B = 0x02,
+    pub fn payload_offset(&self) -> usize {
+        if self.capture_us.is_some() {
+            Self::SIZE + Self::TIMESTAMP_SIZE
+        } else {
+            Self::SIZE
+        }
+    }
+
+    // Payload borné à la taille réelle du message
+    pub fn payload<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        let start = self.payload_offset().min(data.len());
+        let end = (start + self.payload_length as usize).min(data.len());
+        &data[start..end]
+    }
 }
+
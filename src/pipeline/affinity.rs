@@ -0,0 +1,198 @@
+// Placement des threads d'étage : CPUs autorisés (sched_setaffinity), nœud NUMA des allocations
+// (set_mempolicy, mbind pour les tampons projetés) et priorité temps réel SCHED_FIFO optionnelle.
+// Tout est au mieux : un réglage refusé par le noyau est signalé puis ignoré.
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{info, warn};
+use crate::memory::numa;
+use crate::metrics::Stage;
+
+// Avertissement SCHED_FIFO une seule fois, pas une fois par étage
+static REALTIME_WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StagePlacement {
+    // CPUs autorisés ; vide : ceux du processus
+    pub cpus: Vec<usize>,
+    // Nœud NUMA des allocations ; None : celui des CPUs s'ils sont tous sur le même
+    pub numa_node: Option<usize>,
+}
+
+impl StagePlacement {
+    // "2,3" ou "4-7" ou "2-3@0" (CPUs puis nœud NUMA)
+    pub fn parse(value: &str) -> Option<Self> {
+        let (cpus, node) = match value.split_once('@') {
+            Some((cpus, node)) => (cpus, Some(node.trim().parse().ok()?)),
+            None => (value, None),
+        };
+        let mut list = Vec::new();
+        for part in cpus.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to): (usize, usize) = (from.trim().parse().ok()?, to.trim().parse().ok()?);
+                    if from > to {
+                        return None;
+                    }
+                    list.extend(from..=to);
+                }
+                None => list.push(part.parse().ok()?),
+            }
+        }
+        if list.is_empty() && node.is_none() {
+            return None;
+        }
+        list.sort_unstable();
+        list.dedup();
+        Some(Self { cpus: list, numa_node: node })
+    }
+
+    // Nœud explicite, sinon déduit des CPUs
+    pub fn node(&self) -> Option<usize> {
+        self.node_with(numa::cpu_node)
+    }
+
+    fn node_with(&self, cpu_node: impl Fn(usize) -> Option<usize>) -> Option<usize> {
+        self.numa_node.or_else(|| {
+            let mut nodes = self.cpus.iter().map(|&cpu| cpu_node(cpu));
+            let first = nodes.next()??;
+            nodes.all(|n| n == Some(first)).then_some(first)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AffinityConfig {
+    // Indexé par Stage. Ingest n'a pas de thread à lui (tâches WebSocket sur les workers tokio,
+    // partagés avec le reste du serveur) : son entrée reste vide, parse_pin le refuse
+    pub stages: [StagePlacement; 5],
+    // Priorité SCHED_FIFO (1-99) des threads d'étage ; None : ordonnancement normal
+    pub realtime: Option<i32>,
+}
+
+impl AffinityConfig {
+    pub fn stage(&self, stage: Stage) -> &StagePlacement {
+        &self.stages[stage as usize]
+    }
+
+    // Nœud NUMA des tampons partagés par deux étages : celui du lecteur, sinon de l'écrivain
+    pub fn buffer_node(&self, writer: Stage, reader: Stage) -> Option<usize> {
+        self.stage(reader).node().or_else(|| self.stage(writer).node())
+    }
+
+    // Appelé depuis le thread de l'étage, avant sa boucle
+    pub fn apply(&self, stage: Stage) {
+        let placement = self.stage(stage);
+        let mut applied = Vec::new();
+
+        if !placement.cpus.is_empty() {
+            match set_affinity(&placement.cpus) {
+                Ok(()) => applied.push(format!("CPU {}", cpu_list(&placement.cpus))),
+                Err(e) => warn!(cpus = %cpu_list(&placement.cpus), error = %e, "épinglage CPU refusé"),
+            }
+        }
+        if let Some(node) = placement.node() {
+            match numa::set_preferred_node(node) {
+                Ok(()) => applied.push(format!("NUMA {}", node)),
+                Err(e) => warn!(node, error = %e, "politique mémoire NUMA refusée"),
+            }
+        }
+        if let Some(priority) = self.realtime {
+            match set_fifo(priority) {
+                Ok(()) => applied.push(format!("SCHED_FIFO {}", priority)),
+                Err(e) => {
+                    if !REALTIME_WARNED.swap(true, Ordering::Relaxed) {
+                        warn!(error = %e, "SCHED_FIFO indisponible : ordonnancement normal (CAP_SYS_NICE ou RLIMIT_RTPRIO requis)");
+                    }
+                }
+            }
+        }
+
+        if !applied.is_empty() {
+            info!(placement = %applied.join(", "), "thread placé");
+        }
+    }
+}
+
+fn set_affinity(cpus: &[usize]) -> io::Result<()> {
+    // SAFETY : cpu_set_t est un simple masque de bits, valide à zéro
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(io::Error::from(io::ErrorKind::InvalidInput));
+        }
+        // SAFETY : indice vérifié ci-dessus
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    // SAFETY : pid 0 = thread appelant, masque valide
+    if unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn set_fifo(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param { sched_priority: priority.clamp(1, 99) };
+    // SAFETY : pid 0 = thread appelant
+    if unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn cpu_list(cpus: &[usize]) -> String {
+    cpus.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placement(cpus: &[usize], numa_node: Option<usize>) -> StagePlacement {
+        StagePlacement { cpus: cpus.to_vec(), numa_node }
+    }
+
+    // Deux nœuds de quatre CPUs ; au-delà, CPU inconnu
+    fn two_nodes(cpu: usize) -> Option<usize> {
+        (cpu < 8).then_some(cpu / 4)
+    }
+
+    #[test]
+    fn parses_lists_ranges_and_nodes() {
+        assert_eq!(StagePlacement::parse("2,3"), Some(placement(&[2, 3], None)));
+        assert_eq!(StagePlacement::parse("4-7"), Some(placement(&[4, 5, 6, 7], None)));
+        assert_eq!(StagePlacement::parse("2-3@0"), Some(placement(&[2, 3], Some(0))));
+        // Espaces, doublons et désordre
+        assert_eq!(StagePlacement::parse(" 5, 1-2 ,2,5 @ 1"), Some(placement(&[1, 2, 5], Some(1))));
+        // Nœud seul : CPUs du processus
+        assert_eq!(StagePlacement::parse("@1"), Some(placement(&[], Some(1))));
+        assert_eq!(StagePlacement::parse("3-3"), Some(placement(&[3], None)));
+    }
+
+    #[test]
+    fn rejects_malformed_placements() {
+        for value in ["", " ", ",", "7-4", "2,5-1", "a", "1-", "-3", "1@", "1@x", "1@0@1", "1.5"] {
+            assert_eq!(StagePlacement::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn node_is_explicit_or_shared_by_all_cpus() {
+        assert_eq!(placement(&[0, 7], Some(1)).node_with(two_nodes), Some(1));
+        assert_eq!(placement(&[4, 5, 7], None).node_with(two_nodes), Some(1));
+        // CPUs à cheval sur deux nœuds, inconnus, ou aucun : pas de préférence
+        assert_eq!(placement(&[3, 4], None).node_with(two_nodes), None);
+        assert_eq!(placement(&[0, 9], None).node_with(two_nodes), None);
+        assert_eq!(placement(&[9], None).node_with(two_nodes), None);
+        assert_eq!(placement(&[], None).node_with(two_nodes), None);
+    }
+
+    #[test]
+    fn buffers_follow_the_reader_then_the_writer() {
+        let mut config = AffinityConfig::default();
+        assert_eq!(config.buffer_node(Stage::Convert, Stage::Output), None);
+        config.stages[Stage::Convert as usize] = placement(&[], Some(0));
+        assert_eq!(config.buffer_node(Stage::Convert, Stage::Output), Some(0));
+        config.stages[Stage::Output as usize] = placement(&[], Some(1));
+        assert_eq!(config.buffer_node(Stage::Convert, Stage::Output), Some(1));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::pipeline::codec::{CodecConfig, VideoCodec};
+
+// Analyse légère du flux compressé : découpage NAL/OBU et détection des keyframes,
+// sans dépendre du parser FFmpeg (utile avant même d'avoir un décodeur ouvert).
+pub struct BitstreamParser {
+    codec: VideoCodec,
+    // Some(n) = NAL préfixés par une longueur sur n octets (avcC/hvcC), None = Annex-B
+    nal_length_size: Option<usize>,
+}
+
+impl BitstreamParser {
+    pub fn new(config: &CodecConfig) -> Self {
+        let nal_length_size = config.description.as_deref().and_then(|desc| match config.codec {
+            // avcC : configurationVersion(1) profile(1) compat(1) level(1) lengthSizeMinusOne(2 bits)
+            VideoCodec::H264 if desc.len() >= 5 && desc[0] == 1 => Some((desc[4] & 0x03) as usize + 1),
+            // hvcC : lengthSizeMinusOne dans les 2 bits bas de l'octet 21
+            VideoCodec::Hevc if desc.len() >= 22 && desc[0] == 1 => Some((desc[21] & 0x03) as usize + 1),
+            _ => None,
+        });
+
+        Self {
+            codec: config.codec,
+            nal_length_size,
+        }
+    }
+
+    pub fn is_keyframe(&self, data: &[u8]) -> bool {
+        match self.codec {
+            VideoCodec::H264 => self
+                .nal_units(data)
+                .iter()
+                .any(|nal| !nal.is_empty() && nal[0] & 0x1f == 5),
+            VideoCodec::Hevc => self
+                .nal_units(data)
+                .iter()
+                .any(|nal| !nal.is_empty() && (16..=21).contains(&((nal[0] >> 1) & 0x3f))),
+            // Frame tag VP8 : bit 0 à 0 = keyframe
+            VideoCodec::Vp8 => !data.is_empty() && data[0] & 0x01 == 0,
+            VideoCodec::Vp9 => vp9_is_keyframe(data),
+            VideoCodec::Av1 => av1_obus(data).iter().any(|&(obu_type, _)| obu_type == AV1_OBU_SEQUENCE_HEADER),
+        }
+    }
+
+    // SPS/PPS (H.264) ou VPS/SPS/PPS (HEVC) d'une keyframe Annex-B, concaténés avec
+    // leurs start codes : l'extradata attendue par les muxers quand il n'y a pas d'avcC/hvcC
+    pub fn parameter_sets(&self, data: &[u8]) -> Vec<u8> {
+        let is_parameter_set = |nal: &[u8]| match self.codec {
+            VideoCodec::H264 => matches!(nal[0] & 0x1f, 7 | 8),
+            VideoCodec::Hevc => (32..=34).contains(&((nal[0] >> 1) & 0x3f)),
+            _ => false,
+        };
+
+        let mut extradata = Vec::new();
+        for nal in self.nal_units(data).into_iter().filter(|nal| !nal.is_empty() && is_parameter_set(nal)) {
+            extradata.extend_from_slice(&[0, 0, 0, 1]);
+            extradata.extend_from_slice(nal);
+        }
+        extradata
+    }
+
+    // Découpe un chunk H.264/HEVC en NAL units (sans start code ni préfixe de longueur)
+    pub fn nal_units<'a>(&self, data: &'a [u8]) -> Vec<&'a [u8]> {
+        match self.nal_length_size {
+            Some(size) => split_length_prefixed(data, size),
+            None => split_annexb(data),
+        }
+    }
+}
+
+fn split_length_prefixed(data: &[u8], size: usize) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut pos = 0;
+
+    while pos + size <= data.len() {
+        let len = data[pos..pos + size]
+            .iter()
+            .fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        pos += size;
+
+        if len == 0 || pos + len > data.len() {
+            break;
+        }
+        units.push(&data[pos..pos + len]);
+        pos += len;
+    }
+
+    units
+}
+
+fn split_annexb(data: &[u8]) -> Vec<&[u8]> {
+    let mut units = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+
+    while i + 3 <= data.len() {
+        if data[i] == 0 && data[i + 1] == 0 && data[i + 2] == 1 {
+            if let Some(s) = start {
+                // Retirer le zéro de tête d'un start code sur 4 octets
+                let end = if i > s && data[i - 1] == 0 { i - 1 } else { i };
+                units.push(&data[s..end]);
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+
+    if let Some(s) = start {
+        if s < data.len() {
+            units.push(&data[s..]);
+        }
+    }
+
+    units
+}
+
+fn vp9_is_keyframe(data: &[u8]) -> bool {
+    let mut bits = BitReader::new(data);
+
+    // frame_marker = 0b10
+    if bits.read(2) != Some(2) {
+        return false;
+    }
+    let profile_low = bits.read(1).unwrap_or(0);
+    let profile_high = bits.read(1).unwrap_or(0);
+    if (profile_high << 1) | profile_low == 3 {
+        bits.read(1); // reserved_zero
+    }
+    // show_existing_frame : réaffichage d'une frame déjà décodée
+    if bits.read(1) != Some(0) {
+        return false;
+    }
+    // frame_type : 0 = KEY_FRAME
+    bits.read(1) == Some(0)
+}
+
+const AV1_OBU_SEQUENCE_HEADER: u8 = 1;
+
+// Liste (obu_type, payload) d'une temporal unit AV1 au format "low overhead".
+// WebCodecs répète le sequence header sur chaque keyframe, ce qui suffit à les repérer.
+fn av1_obus(data: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut obus = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let header = data[pos];
+        let obu_type = (header >> 3) & 0x0f;
+        let has_extension = header & 0x04 != 0;
+        let has_size = header & 0x02 != 0;
+        pos += 1 + has_extension as usize;
+
+        let size = if has_size {
+            match read_leb128(&data[pos.min(data.len())..]) {
+                Some((value, consumed)) => {
+                    pos += consumed;
+                    value as usize
+                }
+                None => break,
+            }
+        } else {
+            data.len().saturating_sub(pos)
+        };
+
+        if pos + size > data.len() {
+            break;
+        }
+        obus.push((obu_type, &data[pos..pos + size]));
+        pos += size;
+    }
+
+    obus
+}
+
+fn read_leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read(&mut self, n: usize) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            let byte = *self.data.get(self.pos / 8)?;
+            let bit = (byte >> (7 - self.pos % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(codec: VideoCodec, description: Option<Vec<u8>>) -> BitstreamParser {
+        BitstreamParser::new(&CodecConfig {
+            codec,
+            codec_string: String::new(),
+            width: 0,
+            height: 0,
+            description,
+        })
+    }
+
+    // avcC minimal : version 1, lengthSizeMinusOne dans l'octet 4
+    fn avcc(length_size: u8) -> Vec<u8> {
+        vec![1, 0x42, 0x00, 0x1f, 0xfc | (length_size - 1)]
+    }
+
+    // hvcC minimal : version 1, lengthSizeMinusOne dans l'octet 21
+    fn hvcc(length_size: u8) -> Vec<u8> {
+        let mut desc = vec![0; 23];
+        desc[0] = 1;
+        desc[21] = 0xfc | (length_size - 1);
+        desc
+    }
+
+    // SPS, PPS (start codes sur 4 octets) puis IDR (start code sur 3 octets)
+    const H264_IDR_ANNEXB: &[u8] = &[0, 0, 0, 1, 0x67, 0x42, 0x1f, 0, 0, 0, 1, 0x68, 0xce, 0, 0, 1, 0x65, 0x88, 0x84];
+    const H264_DELTA_ANNEXB: &[u8] = &[0, 0, 0, 1, 0x41, 0x9a, 0x02];
+
+    #[test]
+    fn annexb_split_strips_both_start_code_lengths() {
+        let p = parser(VideoCodec::H264, None);
+        assert_eq!(p.nal_units(H264_IDR_ANNEXB), [&[0x67, 0x42, 0x1f][..], &[0x68, 0xce], &[0x65, 0x88, 0x84]]);
+        assert!(p.nal_units(&[]).is_empty());
+        // Pas de start code : rien à découper
+        assert!(p.nal_units(&[0x65, 0x88]).is_empty());
+    }
+
+    #[test]
+    fn length_prefixed_split_follows_the_description() {
+        let p = parser(VideoCodec::H264, Some(avcc(4)));
+        let data = [0, 0, 0, 2, 0x65, 0x88, 0, 0, 0, 1, 0x06];
+        assert_eq!(p.nal_units(&data), [&[0x65, 0x88][..], &[0x06]]);
+
+        let p = parser(VideoCodec::H264, Some(avcc(2)));
+        assert_eq!(p.nal_units(&[0, 3, 0x41, 1, 2, 0, 1, 0x06]), [&[0x41, 1, 2][..], &[0x06]]);
+
+        // Longueur qui déborde du chunk : découpage arrêté, NAL précédents conservés
+        let p = parser(VideoCodec::H264, Some(avcc(4)));
+        assert_eq!(p.nal_units(&[0, 0, 0, 1, 0x06, 0, 0, 0, 9, 0x65]), [&[0x06][..]]);
+
+        let p = parser(VideoCodec::Hevc, Some(hvcc(4)));
+        assert_eq!(p.nal_units(&[0, 0, 0, 2, 0x26, 0x01]), [&[0x26, 0x01][..]]);
+    }
+
+    #[test]
+    fn description_without_version_falls_back_to_annexb() {
+        let mut desc = avcc(4);
+        desc[0] = 0;
+        let p = parser(VideoCodec::H264, Some(desc));
+        assert_eq!(p.nal_units(H264_DELTA_ANNEXB), [&[0x41, 0x9a, 0x02][..]]);
+        // hvcC tronqué
+        let p = parser(VideoCodec::Hevc, Some(vec![1; 10]));
+        assert_eq!(p.nal_units(&[0, 0, 1, 0x26, 0x01]), [&[0x26, 0x01][..]]);
+    }
+
+    #[test]
+    fn h264_keyframe_is_an_idr_slice() {
+        let p = parser(VideoCodec::H264, None);
+        assert!(p.is_keyframe(H264_IDR_ANNEXB));
+        assert!(!p.is_keyframe(H264_DELTA_ANNEXB));
+        assert!(!p.is_keyframe(&[]));
+
+        let p = parser(VideoCodec::H264, Some(avcc(4)));
+        assert!(p.is_keyframe(&[0, 0, 0, 1, 0x06, 0, 0, 0, 2, 0x65, 0x88]));
+        assert!(!p.is_keyframe(&[0, 0, 0, 2, 0x41, 0x9a]));
+    }
+
+    #[test]
+    fn hevc_keyframe_is_an_irap_picture() {
+        let p = parser(VideoCodec::Hevc, None);
+        // IDR_W_RADL (19), CRA (21)
+        assert!(p.is_keyframe(&[0, 0, 0, 1, 0x26, 0x01, 0xaf]));
+        assert!(p.is_keyframe(&[0, 0, 0, 1, 0x2a, 0x01, 0xaf]));
+        // TRAIL_R (1), et un VPS seul n'est pas une image
+        assert!(!p.is_keyframe(&[0, 0, 0, 1, 0x02, 0x01, 0xd0]));
+        assert!(!p.is_keyframe(&[0, 0, 0, 1, 0x40, 0x01, 0x0c]));
+    }
+
+    #[test]
+    fn parameter_sets_keep_only_sps_pps_with_start_codes() {
+        let p = parser(VideoCodec::H264, None);
+        assert_eq!(p.parameter_sets(H264_IDR_ANNEXB), [0, 0, 0, 1, 0x67, 0x42, 0x1f, 0, 0, 0, 1, 0x68, 0xce]);
+        assert!(p.parameter_sets(H264_DELTA_ANNEXB).is_empty());
+
+        let p = parser(VideoCodec::Hevc, None);
+        let keyframe = [
+            0, 0, 0, 1, 0x40, 0x01, // VPS
+            0, 0, 0, 1, 0x42, 0x01, // SPS
+            0, 0, 0, 1, 0x44, 0x01, // PPS
+            0, 0, 0, 1, 0x26, 0x01, 0xaf, // IDR
+        ];
+        assert_eq!(p.parameter_sets(&keyframe), keyframe[..18]);
+
+        // Pas de jeux de paramètres hors H.264/HEVC
+        assert!(parser(VideoCodec::Vp9, None).parameter_sets(&[0x80, 0x49]).is_empty());
+    }
+
+    #[test]
+    fn vp8_keyframe_bit() {
+        let p = parser(VideoCodec::Vp8, None);
+        assert!(p.is_keyframe(&[0x50, 0x42, 0x00]));
+        assert!(!p.is_keyframe(&[0x51, 0x42, 0x00]));
+        assert!(!p.is_keyframe(&[]));
+    }
+
+    #[test]
+    fn vp9_uncompressed_header() {
+        let p = parser(VideoCodec::Vp9, None);
+        // frame_marker, profil 0, show_existing_frame 0, frame_type KEY / NON_KEY
+        assert!(p.is_keyframe(&[0b1000_0000, 0x49]));
+        assert!(!p.is_keyframe(&[0b1000_0100, 0x49]));
+        // Profil 3 : un bit réservé avant show_existing_frame
+        assert!(p.is_keyframe(&[0b1011_1000]));
+        assert!(!p.is_keyframe(&[0b1011_0010]));
+        // Réaffichage d'une frame existante, marqueur invalide, chunk vide
+        assert!(!p.is_keyframe(&[0b1000_1000]));
+        assert!(!p.is_keyframe(&[0b0100_0000]));
+        assert!(!p.is_keyframe(&[]));
+    }
+
+    #[test]
+    fn av1_keyframe_carries_a_sequence_header() {
+        let p = parser(VideoCodec::Av1, None);
+        // Temporal delimiter, sequence header (2 octets), frame
+        assert!(p.is_keyframe(&[0x12, 0x00, 0x0a, 0x02, 0xaa, 0xbb, 0x32, 0x01, 0xcc]));
+        assert!(!p.is_keyframe(&[0x12, 0x00, 0x32, 0x01, 0xcc]));
+        // Octet d'extension avant la taille
+        assert!(p.is_keyframe(&[0x12, 0x00, 0x0e, 0x08, 0x01, 0xaa]));
+        // Sequence header sans champ de taille : il s'étend jusqu'à la fin
+        assert!(p.is_keyframe(&[0x12, 0x00, 0x08, 0xaa, 0xbb]));
+        // Taille qui déborde : OBU ignoré
+        assert!(!p.is_keyframe(&[0x0a, 0x05, 0xaa]));
+    }
+
+    #[test]
+    fn av1_obu_sizes_are_leb128() {
+        let mut frame = vec![0x32, 0x80, 0x01];
+        frame.extend(std::iter::repeat_n(0xcc, 128));
+        frame.extend([0x0a, 0x01, 0xaa]);
+        let obus = av1_obus(&frame);
+        assert_eq!(obus.iter().map(|&(t, p)| (t, p.len())).collect::<Vec<_>>(), [(6, 128), (1, 1)]);
+
+        assert_eq!(read_leb128(&[0xe5, 0x8e, 0x26]), Some((624_485, 3)));
+        assert_eq!(read_leb128(&[0x80, 0x80]), None);
+    }
+}
@@ -0,0 +1,151 @@
+use ffmpeg_next as ffmpeg;
+use serde::Serialize;
+
+// Codecs négociables via WebCodecs, par ordre de préférence (le plus efficace d'abord)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    Av1,
+    Hevc,
+    Vp9,
+    Vp8,
+    H264,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 5] = [
+        VideoCodec::Av1,
+        VideoCodec::Hevc,
+        VideoCodec::Vp9,
+        VideoCodec::Vp8,
+        VideoCodec::H264,
+    ];
+
+    // Parse une chaîne codec WebCodecs ("avc1.42001f", "hvc1.1.6.L93.B0", "vp09.00.10.08", "av01.0.04M.08"...)
+    pub fn from_codec_string(codec: &str) -> Option<Self> {
+        let fourcc = codec.split('.').next()?.to_ascii_lowercase();
+        match fourcc.as_str() {
+            "avc1" | "avc3" => Some(VideoCodec::H264),
+            "hvc1" | "hev1" => Some(VideoCodec::Hevc),
+            "vp8" => Some(VideoCodec::Vp8),
+            "vp09" | "vp9" => Some(VideoCodec::Vp9),
+            "av01" => Some(VideoCodec::Av1),
+            _ => None,
+        }
+    }
+
+    pub fn av_codec_id(self) -> ffmpeg::ffi::AVCodecID {
+        match self {
+            VideoCodec::H264 => ffmpeg::ffi::AVCodecID::AV_CODEC_ID_H264,
+            VideoCodec::Hevc => ffmpeg::ffi::AVCodecID::AV_CODEC_ID_HEVC,
+            VideoCodec::Vp8 => ffmpeg::ffi::AVCodecID::AV_CODEC_ID_VP8,
+            VideoCodec::Vp9 => ffmpeg::ffi::AVCodecID::AV_CODEC_ID_VP9,
+            VideoCodec::Av1 => ffmpeg::ffi::AVCodecID::AV_CODEC_ID_AV1,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::Hevc => "HEVC",
+            VideoCodec::Vp8 => "VP8",
+            VideoCodec::Vp9 => "VP9",
+            VideoCodec::Av1 => "AV1",
+        }
+    }
+
+    // Vérifie que FFmpeg dispose d'un décodeur pour ce codec
+    pub fn decoder_available(self) -> bool {
+        unsafe { !ffmpeg::ffi::avcodec_find_decoder(self.av_codec_id()).is_null() }
+    }
+
+    // Liste des codecs décodables par ce serveur, dans l'ordre de préférence
+    pub fn supported() -> Vec<VideoCodec> {
+        let _ = ffmpeg::init();
+        Self::ALL.iter().copied().filter(|c| c.decoder_available()).collect()
+    }
+}
+
+// Configuration négociée pour une session (message "v-config" du client)
+#[derive(Debug, Clone)]
+pub struct CodecConfig {
+    pub codec: VideoCodec,
+    pub codec_string: String,
+    pub width: u32,
+    pub height: u32,
+    // avcC / hvcC / av1C fourni par WebCodecs (absent en Annex-B, VP8 et VP9)
+    pub description: Option<Vec<u8>>,
+}
+
+impl CodecConfig {
+    pub fn from_v_config(val: &serde_json::Value) -> Option<Self> {
+        let codec_string = val["codec"].as_str()?.to_string();
+        let codec = VideoCodec::from_codec_string(&codec_string)?;
+
+        let description = val["description"].as_array().map(|bytes| {
+            bytes
+                .iter()
+                .filter_map(|b| b.as_u64())
+                .map(|b| b as u8)
+                .collect::<Vec<u8>>()
+        });
+
+        Some(Self {
+            codec,
+            codec_string,
+            width: val["width"].as_u64().unwrap_or(0) as u32,
+            height: val["height"].as_u64().unwrap_or(0) as u32,
+            description: description.filter(|d| !d.is_empty()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn codec_strings_map_to_codecs() {
+        let cases = [
+            ("avc1.42001f", Some(VideoCodec::H264)),
+            ("avc3.640028", Some(VideoCodec::H264)),
+            ("hvc1.1.6.L93.B0", Some(VideoCodec::Hevc)),
+            ("hev1.1.6.L93.B0", Some(VideoCodec::Hevc)),
+            ("vp8", Some(VideoCodec::Vp8)),
+            ("vp09.00.10.08", Some(VideoCodec::Vp9)),
+            ("vp9", Some(VideoCodec::Vp9)),
+            ("av01.0.04M.08", Some(VideoCodec::Av1)),
+            ("AVC1.42001F", Some(VideoCodec::H264)),
+            ("mp4v.20.9", None),
+            ("", None),
+        ];
+        for (codec, expected) in cases {
+            assert_eq!(VideoCodec::from_codec_string(codec), expected, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn v_config_message() {
+        let config = CodecConfig::from_v_config(&json!({
+            "codec": "avc1.42001f",
+            "width": 1280,
+            "height": 720,
+            "description": [1, 66, 0, 31, 255],
+        }))
+        .unwrap();
+        assert_eq!(config.codec, VideoCodec::H264);
+        assert_eq!(config.codec_string, "avc1.42001f");
+        assert_eq!((config.width, config.height), (1280, 720));
+        assert_eq!(config.description.as_deref(), Some(&[1, 66, 0, 31, 255][..]));
+
+        // Description vide ou absente : Annex-B
+        let config = CodecConfig::from_v_config(&json!({ "codec": "vp09.00.10.08", "description": [] })).unwrap();
+        assert_eq!(config.codec, VideoCodec::Vp9);
+        assert_eq!((config.width, config.height), (0, 0));
+        assert!(config.description.is_none());
+
+        assert!(CodecConfig::from_v_config(&json!({ "codec": "mp4v.20.9" })).is_none());
+        assert!(CodecConfig::from_v_config(&json!({ "width": 640 })).is_none());
+    }
+}
@@ -1,69 +1,146 @@
 use ffmpeg_next as ffmpeg;
 use std::ptr;
+use tracing::warn;
+use crate::codec::convert::{ColorMatrix, ColorRange, Colorimetry, Image, PixelFormat};
+use crate::codec::simd::Plane;
+use crate::pipeline::codec::CodecConfig;
 
 pub struct HardwareDecoder {
     decoder_ctx: *mut ffmpeg::ffi::AVCodecContext,
-    _hw_device_ctx: *mut ffmpeg::ffi::AVBufferRef,
+    // Null en décodage logiciel (pas de périphérique VAAPI)
+    hw_device_ctx: *mut ffmpeg::ffi::AVBufferRef,
 }
 
 unsafe impl Send for HardwareDecoder {}
 unsafe impl Sync for HardwareDecoder {}
 
 impl HardwareDecoder {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(config: &CodecConfig) -> Result<Self, Box<dyn std::error::Error>> {
         unsafe {
             ffmpeg::init()?;
             
-            let codec = ffmpeg::ffi::avcodec_find_decoder(
-                ffmpeg::ffi::AVCodecID::AV_CODEC_ID_H264
-            );
+            let codec = ffmpeg::ffi::avcodec_find_decoder(config.codec.av_codec_id());
             
             if codec.is_null() {
-                return Err("H264 decoder not found".into());
+                return Err(format!("{} decoder not found", config.codec.name()).into());
             }
 
             let decoder_ctx = ffmpeg::ffi::avcodec_alloc_context3(codec);
+            if decoder_ctx.is_null() {
+                return Err(format!("{} decoder context allocation failed", config.codec.name()).into());
+            }
+            (*decoder_ctx).width = config.width as i32;
+            (*decoder_ctx).height = config.height as i32;
+
+            // avcC / hvcC / av1C : FFmpeg attend l'extradata avec le padding de fin à zéro
+            if let Some(description) = &config.description {
+                let padding = ffmpeg::ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize;
+                let extradata = ffmpeg::ffi::av_mallocz(description.len() + padding) as *mut u8;
+                if !extradata.is_null() {
+                    ptr::copy_nonoverlapping(description.as_ptr(), extradata, description.len());
+                    (*decoder_ctx).extradata = extradata;
+                    (*decoder_ctx).extradata_size = description.len() as i32;
+                }
+            }
             
-            // Setup VAAPI
+            // VAAPI si disponible, sinon décodage logiciel
             let hw_type = ffmpeg::ffi::AVHWDeviceType::AV_HWDEVICE_TYPE_VAAPI;
             let mut hw_device_ctx: *mut ffmpeg::ffi::AVBufferRef = ptr::null_mut();
-            
-            ffmpeg::ffi::av_hwdevice_ctx_create(
+            let ret = ffmpeg::ffi::av_hwdevice_ctx_create(
                 &mut hw_device_ctx,
                 hw_type,
                 ptr::null(),
                 ptr::null_mut(),
                 0,
             );
-            
-            (*decoder_ctx).hw_device_ctx = ffmpeg::ffi::av_buffer_ref(hw_device_ctx);
-            
-            ffmpeg::ffi::avcodec_open2(decoder_ctx, codec, ptr::null_mut());
-            
-            Ok(Self { 
-                decoder_ctx, 
-                _hw_device_ctx: hw_device_ctx 
-            })
+            if ret < 0 || hw_device_ctx.is_null() {
+                warn!(codec = config.codec.name(), code = ret, "VAAPI indisponible, décodage logiciel");
+                hw_device_ctx = ptr::null_mut();
+            } else {
+                (*decoder_ctx).hw_device_ctx = ffmpeg::ffi::av_buffer_ref(hw_device_ctx);
+            }
+
+            if ffmpeg::ffi::avcodec_open2(decoder_ctx, codec, ptr::null_mut()) < 0 {
+                let mut ctx = decoder_ctx;
+                ffmpeg::ffi::avcodec_free_context(&mut ctx);
+                ffmpeg::ffi::av_buffer_unref(&mut hw_device_ctx);
+                return Err(format!("Failed to open {} decoder", config.codec.name()).into());
+            }
+
+            Ok(Self { decoder_ctx, hw_device_ctx })
         }
     }
-    
-    pub fn decode(&mut self, data: &[u8]) -> Result<*mut ffmpeg::ffi::AVFrame, Box<dyn std::error::Error>> {
+
+    // Toutes les frames rendues après ce paquet (vide : le décodeur attend d'autres paquets)
+    pub fn decode(&mut self, data: &[u8]) -> Result<Vec<FrameWrapper>, Box<dyn std::error::Error>> {
+        let mut frames = Vec::new();
         unsafe {
-            let packet = ffmpeg::ffi::av_packet_alloc();
+            let mut packet = ffmpeg::ffi::av_packet_alloc();
+            if packet.is_null() {
+                return Err("allocation du paquet impossible".into());
+            }
             (*packet).data = data.as_ptr() as *mut u8;
             (*packet).size = data.len() as i32;
-            
-            ffmpeg::ffi::avcodec_send_packet(self.decoder_ctx, packet);
-            ffmpeg::ffi::av_packet_free(&mut (packet as *mut ffmpeg::ffi::AVPacket));
-            
-            let mut frame = ffmpeg::ffi::av_frame_alloc();
-            let ret = ffmpeg::ffi::avcodec_receive_frame(self.decoder_ctx, frame);
-            
-            if ret == 0 {
-                Ok(frame)
-            } else {
-                ffmpeg::ffi::av_frame_free(&mut frame);
-                Err("Need more data or error".into())
+
+            // EAGAIN : frames en attente de lecture ; on les lit puis on renvoie le même paquet
+            let sent = loop {
+                let sent = ffmpeg::ffi::avcodec_send_packet(self.decoder_ctx, packet);
+                if sent != ffmpeg::ffi::AVERROR(libc::EAGAIN) {
+                    break sent;
+                }
+                let pending = frames.len();
+                if let Err(e) = self.receive_frames(&mut frames) {
+                    ffmpeg::ffi::av_packet_free(&mut packet);
+                    return Err(e);
+                }
+                // Ni paquet accepté ni frame rendue : le décodeur ne progressera plus
+                if frames.len() == pending {
+                    break sent;
+                }
+            };
+            ffmpeg::ffi::av_packet_free(&mut packet);
+            if sent < 0 {
+                return Err(format!("paquet refusé par le décodeur (code {})", sent).into());
+            }
+        }
+
+        self.receive_frames(&mut frames)?;
+        Ok(frames)
+    }
+
+    // Lit les frames disponibles jusqu'à EAGAIN (paquet suivant attendu) ou EOF
+    fn receive_frames(&mut self, frames: &mut Vec<FrameWrapper>) -> Result<(), Box<dyn std::error::Error>> {
+        unsafe {
+            loop {
+                let mut frame = ffmpeg::ffi::av_frame_alloc();
+                if frame.is_null() {
+                    return Err("allocation de la frame impossible".into());
+                }
+                let ret = ffmpeg::ffi::avcodec_receive_frame(self.decoder_ctx, frame);
+                if ret != 0 {
+                    ffmpeg::ffi::av_frame_free(&mut frame);
+                    if ret == ffmpeg::ffi::AVERROR(libc::EAGAIN) || ret == ffmpeg::ffi::AVERROR_EOF {
+                        return Ok(());
+                    }
+                    return Err(format!("frame non décodée (code {})", ret).into());
+                }
+
+                // Surface VAAPI : rapatriement en mémoire système (NV12) pour la conversion
+                if (*frame).format == ffmpeg::ffi::AVPixelFormat::AV_PIX_FMT_VAAPI as i32 {
+                    let mut sw_frame = ffmpeg::ffi::av_frame_alloc();
+                    let ret = ffmpeg::ffi::av_hwframe_transfer_data(sw_frame, frame, 0);
+                    if ret >= 0 {
+                        ffmpeg::ffi::av_frame_copy_props(sw_frame, frame);
+                    }
+                    ffmpeg::ffi::av_frame_free(&mut frame);
+                    if ret < 0 {
+                        ffmpeg::ffi::av_frame_free(&mut sw_frame);
+                        return Err("VAAPI frame transfer failed".into());
+                    }
+                    frame = sw_frame;
+                }
+
+                frames.push(FrameWrapper(frame));
             }
         }
     }
@@ -71,6 +148,9 @@ impl HardwareDecoder {
 
 // Wrapper pour libérer la frame automatiquement
 pub struct FrameWrapper(pub *mut ffmpeg::ffi::AVFrame);
+
+// La frame appartient à un seul étage à la fois (transmise par file entre threads)
+unsafe impl Send for FrameWrapper {}
 impl Drop for FrameWrapper {
     fn drop(&mut self) {
         unsafe { ffmpeg::ffi::av_frame_free(&mut self.0); }
@@ -84,10 +164,67 @@ impl std::ops::Deref for FrameWrapper {
     }
 }
 
+impl FrameWrapper {
+    pub fn pixel_format(&self) -> Option<PixelFormat> {
+        use ffmpeg::ffi::AVPixelFormat::*;
+        let format = self.format;
+        [
+            (AV_PIX_FMT_YUV420P, PixelFormat::I420),
+            (AV_PIX_FMT_YUVJ420P, PixelFormat::I420),
+            (AV_PIX_FMT_NV12, PixelFormat::Nv12),
+            (AV_PIX_FMT_YUYV422, PixelFormat::Yuyv),
+            (AV_PIX_FMT_UYVY422, PixelFormat::Uyvy),
+            (AV_PIX_FMT_RGB24, PixelFormat::Rgb24),
+            (AV_PIX_FMT_BGRA, PixelFormat::Bgra),
+            (AV_PIX_FMT_GRAY8, PixelFormat::Grey),
+        ]
+        .into_iter()
+        .find(|(av, _)| *av as i32 == format)
+        .map(|(_, f)| f)
+    }
+
+    // Matrice et plage annoncées par le flux, sinon déduites de la résolution
+    pub fn colorimetry(&self) -> Colorimetry {
+        use ffmpeg::ffi::{AVColorRange, AVColorSpace, AVPixelFormat};
+        let guess = Colorimetry::guess(self.height as usize);
+        let matrix = match self.colorspace {
+            AVColorSpace::AVCOL_SPC_BT709 => ColorMatrix::Bt709,
+            AVColorSpace::AVCOL_SPC_BT470BG | AVColorSpace::AVCOL_SPC_SMPTE170M => ColorMatrix::Bt601,
+            _ => guess.matrix,
+        };
+        let full = self.color_range == AVColorRange::AVCOL_RANGE_JPEG
+            || self.format == AVPixelFormat::AV_PIX_FMT_YUVJ420P as i32;
+        let range = if full { ColorRange::Full } else { ColorRange::Limited };
+        Colorimetry::new(matrix, range)
+    }
+
+    // Vue sur les plans de la frame (None si format non géré ou pas négatif)
+    pub fn image(&self) -> Option<Image<'_>> {
+        let format = self.pixel_format()?;
+        let (width, height) = (self.width as usize, self.height as usize);
+        let layout = format.layout(width, height);
+        let mut planes = [Plane::new(&[], 0); 3];
+
+        for (i, l) in layout.iter().enumerate().take(format.plane_count()) {
+            if self.linesize[i] <= 0 || self.data[i].is_null() {
+                return None;
+            }
+            let stride = self.linesize[i] as usize;
+            // SAFETY : FFmpeg alloue au moins linesize × lignes octets pour chaque plan
+            let data = unsafe { std::slice::from_raw_parts(self.data[i], stride * l.rows) };
+            planes[i] = Plane::new(data, stride);
+        }
+
+        Image::new(format, width, height, planes).ok()
+    }
+}
+
 impl Drop for HardwareDecoder {
     fn drop(&mut self) {
         unsafe {
             ffmpeg::ffi::avcodec_free_context(&mut self.decoder_ctx);
+            // Référence propre au décodeur (le contexte garde la sienne jusqu'à sa libération)
+            ffmpeg::ffi::av_buffer_unref(&mut self.hw_device_ctx);
         }
     }
 }
@@ -0,0 +1,73 @@
+use std::time::Duration;
+use crate::metrics::Stage;
+use crate::net::clock::now_us;
+
+// Horodatages d'une frame le long de la pipeline, en µs dans l'horloge du serveur.
+// Ils voyagent avec la frame d'un étage à l'autre et sont agrégés à la sortie V4L2.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTimestamps {
+    // Capture côté téléphone, déjà corrigée du décalage d'horloge
+    pub capture_us: Option<u64>,
+    stages: [Option<u64>; Stage::ALL.len()],
+}
+
+impl FrameTimestamps {
+    pub fn get(&self, stage: Stage) -> Option<u64> {
+        self.stages[stage as usize]
+    }
+
+    // Marque la fin d'un étage ; renvoie le temps passé depuis l'étage précédent (file + traitement)
+    pub fn stamp(&mut self, stage: Stage) -> Option<Duration> {
+        let now = now_us();
+        self.stages[stage as usize] = Some(now);
+
+        let previous = Stage::ALL[..stage as usize]
+            .iter()
+            .rev()
+            .find_map(|&s| self.get(s))?;
+        Some(Duration::from_micros(now.saturating_sub(previous)))
+    }
+
+    // Délai entre la capture sur le téléphone et la fin de l'étage
+    pub fn since_capture(&self, stage: Stage) -> Option<u64> {
+        Some(self.get(stage)?.saturating_sub(self.capture_us?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stamps_measure_from_the_previous_stamped_stage() {
+        let mut ts = FrameTimestamps::default();
+        // Premier étage horodaté : rien à mesurer
+        assert!(ts.stamp(Stage::Ingest).is_none());
+        let ingest = ts.get(Stage::Ingest).unwrap();
+
+        std::thread::sleep(Duration::from_millis(2));
+        // Depacketize sauté (frame hors pipeline) : Decode se mesure depuis Ingest
+        let decode = ts.stamp(Stage::Decode).unwrap();
+        assert!(decode >= Duration::from_millis(2));
+        assert!(ts.get(Stage::Depacketize).is_none());
+        assert_eq!(decode.as_micros() as u64, ts.get(Stage::Decode).unwrap() - ingest);
+
+        let output = ts.stamp(Stage::Output).unwrap();
+        assert_eq!(output.as_micros() as u64, ts.get(Stage::Output).unwrap() - ts.get(Stage::Decode).unwrap());
+    }
+
+    #[test]
+    fn since_capture_needs_both_ends() {
+        let mut ts = FrameTimestamps::default();
+        ts.stamp(Stage::Output);
+        assert!(ts.since_capture(Stage::Output).is_none());
+
+        let output = ts.get(Stage::Output).unwrap();
+        ts.capture_us = Some(output - 30_000);
+        assert_eq!(ts.since_capture(Stage::Output), Some(30_000));
+        assert!(ts.since_capture(Stage::Convert).is_none());
+        // Capture "dans le futur" (décalage d'horloge mal estimé) : pas de délai négatif
+        ts.capture_us = Some(output + 1_000);
+        assert_eq!(ts.since_capture(Stage::Output), Some(0));
+    }
+}
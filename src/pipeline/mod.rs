@@ -1,60 +1,305 @@
 pub mod hwaccel;
+pub mod codec;
+pub mod bitstream;
+pub mod stages;
+pub mod latency;
+pub mod pacer;
+pub mod transform;
+pub mod orientation;
+pub mod affinity;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
-use crate::pipeline::hwaccel::HardwareDecoder;
-use crate::codec::simd::yuv_convert_avx512;
-use crate::v4l2::device::Device;
+use std::thread::JoinHandle;
+use std::time::Instant;
+use parking_lot::Mutex;
+use tracing::{error, info_span};
+use crate::codec::convert::{Colorimetry, PixelFormat};
+use crate::codec::rotate::Orientation;
+use crate::codec::scale::ScaleConfig;
+use crate::memory::pool::{FramePool, PoolConfig};
+use crate::metrics::{ServerMetrics, Stage};
+use crate::net::clock::ClockEstimate;
+use crate::pipeline::affinity::AffinityConfig;
+use crate::pipeline::codec::CodecConfig;
+use crate::pipeline::latency::FrameTimestamps;
+use crate::pipeline::orientation::OrientationControl;
+use crate::pipeline::pacer::PacerConfig;
+use crate::pipeline::transform::FrameTransform;
+use crate::pipeline::stages::{Ingest, forwarded};
+use crate::record::{RecordConfig, Recorder};
+use crate::sink::{FrameInfo, FrameSink, SinkConfig};
+use crate::sync::{mpmc, spsc};
+use crate::v4l2::device::IoMode;
+use crate::v4l2::readers::ReaderMonitor;
 
-pub struct Pipeline {
-    decoder: Mutex<HardwareDecoder>,
-    output_device: Device,
+// Profondeur des files entre étages : courtes pour la latence, on jette sous surcharge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueueSizes {
+    // Paquets reçus des sessions WebSocket
+    pub ingest: usize,
+    // Images compressées en attente de décodage
+    pub encoded: usize,
+    // Images décodées puis converties
+    pub frame: usize,
+}
+
+impl Default for QueueSizes {
+    fn default() -> Self {
+        Self {
+            ingest: 64,
+            encoded: 32,
+            frame: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    // /dev/videoN (v4l2loopback)
+    pub video_nr: u16,
+    // Format écrit sur la loopback (celui que négocient les applis clientes)
+    pub output_format: PixelFormat,
+    // write() ou streaming mmap vers /dev/videoN
+    pub io: IoMode,
+    // Destination des images : loopback, fichier .yuv/.y4m ou stdout
+    pub sink: SinkConfig,
+    // Résolution fixe présentée quelle que soit l'orientation du téléphone
+    pub scale: ScaleConfig,
+    // Orientation forcée ; None : suivre les métadonnées du téléphone
+    pub orientation: Option<Orientation>,
+    pub pacer: PacerConfig,
+    // Enregistrement du flux reçu (MP4/MKV) et des images de sortie (Y4M)
+    pub record: RecordConfig,
+    // Sans lecteur sur /dev/videoN : keyframes seules et téléphone invité à ralentir
+    pub idle_when_unwatched: bool,
+    // CPUs, nœud NUMA et priorité temps réel des threads d'étage
+    pub affinity: AffinityConfig,
+    pub queues: QueueSizes,
 }
 
-unsafe impl Send for Pipeline {}
-unsafe impl Sync for Pipeline {}
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            video_nr: 10,
+            output_format: PixelFormat::Yuyv,
+            io: IoMode::default(),
+            sink: SinkConfig::default(),
+            scale: ScaleConfig::default(),
+            orientation: None,
+            pacer: PacerConfig::default(),
+            record: RecordConfig::default(),
+            idle_when_unwatched: true,
+            affinity: AffinityConfig::default(),
+            queues: QueueSizes::default(),
+        }
+    }
+}
+
+impl PipelineConfig {
+    // Images produites par l'étage convert, telles que les reçoit le sink
+    pub fn frame_info(&self) -> FrameInfo {
+        FrameInfo {
+            format: self.output_format,
+            width: self.scale.width,
+            height: self.scale.height,
+            fps: self.pacer.fps,
+            colorimetry: Colorimetry::guess(self.scale.height),
+        }
+    }
+}
+
+// ingest (tâche WebSocket) → depacketize → decode → convert → output,
+// chaque étage sur son propre thread OS, reliés par des files bornées
+// (MPMC en entrée car plusieurs sessions WebSocket peuvent pousser, SPSC ensuite).
+pub struct Pipeline {
+    ingest_tx: mpmc::Sender<Ingest>,
+    resync: Arc<AtomicBool>,
+    orientation: Arc<OrientationControl>,
+    recorder: Arc<Recorder>,
+    readers: Option<Arc<ReaderMonitor>>,
+    // Cadence du pacer, modifiable à chaud
+    output_fps: Arc<AtomicU32>,
+    // Threads d'étage, dans l'ordre du flux, joints à l'arrêt
+    threads: Mutex<Vec<(Stage, JoinHandle<()>)>>,
+    metrics: Arc<ServerMetrics>,
+}
 
 impl Pipeline {
-    pub fn new(video_nr: u16) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
-        let decoder = HardwareDecoder::new()?;
-        let output_device = Device::open(video_nr)?;
-        
+    pub fn new(config: &PipelineConfig, metrics: Arc<ServerMetrics>) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let sink = config.sink.open(&config.frame_info(), config.video_nr, config.io)?;
+        Self::with_sink(config, sink, metrics)
+    }
+
+    // Sink fourni par l'appelant (ex. MemorySink pour observer les images décodées) ; config.sink est ignoré
+    pub fn with_sink(
+        config: &PipelineConfig,
+        sink: Box<dyn FrameSink>,
+        metrics: Arc<ServerMetrics>,
+    ) -> Result<Arc<Self>, Box<dyn std::error::Error>> {
+        let orientation = Arc::new(OrientationControl::new(config.orientation));
+        let transform = FrameTransform::new(config.output_format, config.scale, orientation.clone());
+        let recorder = Recorder::new(config.record.clone(), config.frame_info());
+        if config.record.start {
+            if let Err(e) = recorder.start() {
+                error!(error = %e, "enregistrement impossible");
+            }
+        }
+
+        let pool = FramePool::new(PoolConfig::for_frames(&config.frame_info(), &config.queues, &config.affinity), metrics.clone());
+
+        let queues = config.queues;
+        let (ingest_tx, ingest_rx) = mpmc::bounded(queues.ingest);
+        let (encoded_tx, encoded_rx) = spsc::channel(queues.encoded);
+        let (decoded_tx, decoded_rx) = spsc::channel(queues.frame);
+        let (converted_tx, converted_rx) = spsc::channel(queues.frame);
+        let resync = Arc::new(AtomicBool::new(false));
+        let output_fps = Arc::new(AtomicU32::new(config.pacer.fps));
+        let readers = sink.readers().filter(|_| config.idle_when_unwatched);
+        metrics.set_queue_capacity(Stage::Depacketize, ingest_rx.capacity());
+        metrics.set_queue_capacity(Stage::Decode, encoded_rx.capacity());
+        metrics.set_queue_capacity(Stage::Convert, decoded_rx.capacity());
+        metrics.set_queue_capacity(Stage::Output, converted_rx.capacity());
+
+        let mut threads = Vec::with_capacity(4);
+        let (r, rec, w, p, m) = (resync.clone(), recorder.clone(), readers.clone(), pool.clone(), metrics.clone());
+        threads.push((Stage::Depacketize, spawn_stage(Stage::Depacketize, &config.affinity, move || stages::depacketize_loop(ingest_rx, encoded_tx, r, rec, w, p, m))?));
+        let m = metrics.clone();
+        threads.push((Stage::Decode, spawn_stage(Stage::Decode, &config.affinity, move || stages::decode_loop(encoded_rx, decoded_tx, m))?));
+        let m = metrics.clone();
+        threads.push((Stage::Convert, spawn_stage(Stage::Convert, &config.affinity, move || stages::convert_loop(decoded_rx, converted_tx, transform, pool, m))?));
+        let (info, p, fps, rec, m) = (config.frame_info(), config.pacer.clone(), output_fps.clone(), recorder.clone(), metrics.clone());
+        threads.push((Stage::Output, spawn_stage(Stage::Output, &config.affinity, move || stages::output_loop(converted_rx, sink, info, p, fps, rec, m))?));
+
         Ok(Arc::new(Self {
-            decoder: Mutex::new(decoder),
-            output_device,
+            ingest_tx,
+            resync,
+            orientation,
+            recorder,
+            readers,
+            output_fps,
+            threads: Mutex::new(threads),
+            metrics,
         }))
     }
 
-    pub async fn process_chunk(&self, data: &[u8], width: usize, height: usize) -> Result<(), Box<dyn std::error::Error>> {
-        let mut decoder = self.decoder.lock().await;
-        
-        // 1. Décodage matériel H264 -> YUV420
-        let frame_ptr = decoder.decode(data)?;
-        let frame = crate::pipeline::hwaccel::FrameWrapper(frame_ptr);
-        
-        unsafe {
-            let av_frame = &*frame;
-            
-            // 2. Conversion SIMD YUV420 -> YUYV (format attendu par V4L2 loopback)
-            // On prépare un buffer pour le YUYV (width * height * 2 octets)
-            let mut yuyv_buffer = vec![0u8; width * height * 2];
-            
-            let y_plane = std::slice::from_raw_parts((*frame.0).data[0], ((*frame.0).linesize[0] as usize) * height);
-            let u_plane = std::slice::from_raw_parts((*frame.0).data[1], ((*frame.0).linesize[1] as usize) * height / 2);
-            let v_plane = std::slice::from_raw_parts((*frame.0).data[2], ((*frame.0).linesize[2] as usize) * height / 2);
-            
-            yuv_convert_avx512::yuv420_to_yuyv_avx512(
-                y_plane,
-                u_plane,
-                v_plane,
-                &mut yuyv_buffer,
-                width,
-                height
-            );
-            
-            // 3. Envoi vers V4L2
-            self.output_device.write_frame_dmabuf(&yuyv_buffer)?;
-        }
-        
-        Ok(())
+    // Sélectionne le décodeur d'après la config négociée (codec string + description)
+    pub async fn configure(&self, config: CodecConfig) {
+        // Attend une place plutôt que de perdre la config ; ne bloque pas le runtime
+        if self.ingest_tx.push_async(Ingest::Configure(config)).await.is_err() {
+            error!("pipeline arrêtée : configuration du décodeur perdue");
+        }
+    }
+
+    // Rotation/miroir : métadonnées du téléphone, ou forçage depuis le dashboard
+    pub fn orientation(&self) -> &OrientationControl {
+        &self.orientation
+    }
+
+    // Démarrage/arrêt de l'enregistrement depuis le dashboard
+    pub fn recorder(&self) -> &Arc<Recorder> {
+        &self.recorder
+    }
+
+    // Rechargement de la configuration : nouvelle cadence prise au tick suivant. Le débit
+    // annoncé par la sortie (en-tête Y4M, timeperframe) reste celui du démarrage
+    pub fn set_output_fps(&self, fps: u32) {
+        self.output_fps.store(fps, Ordering::Relaxed);
+    }
+
+    // Nombre de lecteurs de /dev/videoN ; None quand la sortie n'est pas surveillée
+    pub fn readers(&self) -> Option<usize> {
+        self.readers.as_ref().map(|r| r.readers())
+    }
+
+    // Arrêt ordonné, bloquant : l'entrée est fermée, chaque étage vide sa file puis se termine, ce
+    // qui ferme la suivante. Output libère la sortie (fd et mmap de /dev/videoN) en dernier, puis
+    // l'enregistrement en cours est finalisé
+    pub fn shutdown(&self) {
+        self.ingest_tx.close();
+        for (stage, thread) in self.threads.lock().drain(..) {
+            if thread.join().is_err() {
+                error!(stage = stage.name(), "étage terminé sur une panique");
+            }
+        }
+        self.recorder.finish();
+    }
+
+    // Étage ingest : ne bloque jamais la tâche WebSocket. false : paquet perdu (file pleine ou pipeline arrêtée)
+    pub fn push_chunk(&self, data: Vec<u8>, clock: Option<ClockEstimate>) -> bool {
+        let start = Instant::now();
+        let mut ts = FrameTimestamps::default();
+        ts.stamp(Stage::Ingest);
+        let packet = Ingest::Packet { data, clock, ts };
+
+        if forwarded(self.ingest_tx.try_push(packet), &self.metrics, Stage::Depacketize) {
+            self.metrics.record_stage(Stage::Ingest, start.elapsed());
+            true
+        } else {
+            self.resync.store(true, Ordering::Release);
+            false
+        }
+    }
+}
+
+// Le placement (CPUs, NUMA, SCHED_FIFO) est appliqué par le thread lui-même avant sa boucle ;
+// tous ses logs portent le span de l'étage
+fn spawn_stage<F>(stage: Stage, affinity: &AffinityConfig, f: F) -> std::io::Result<JoinHandle<()>>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let affinity = affinity.clone();
+    std::thread::Builder::new()
+        .name(format!("pc-{}", stage.name()))
+        .spawn(move || {
+            let _span = info_span!("stage", stage = stage.name()).entered();
+            affinity.apply(stage);
+            f()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::codec::convert::black_frame;
+    use crate::sink::memory::MemorySink;
+
+    // Petite sortie à 100 i/s : les tests restent rapides
+    fn config() -> PipelineConfig {
+        PipelineConfig {
+            scale: ScaleConfig { width: 64, height: 48, ..ScaleConfig::default() },
+            pacer: PacerConfig { fps: 100, ..PacerConfig::default() },
+            ..PipelineConfig::default()
+        }
+    }
+
+    #[test]
+    fn memory_sink_captures_paced_output() {
+        let config = config();
+        let info = config.frame_info();
+        let metrics = ServerMetrics::new();
+        let (sink, capture) = MemorySink::new(&info, 16);
+        let pipeline = Pipeline::with_sink(&config, Box::new(sink), metrics.clone()).unwrap();
+
+        // Paquet reçu avant toute configuration du décodeur : jeté à l'entrée, la sortie continue
+        assert!(pipeline.push_chunk(vec![0; 32], None));
+        assert!(capture.wait_for(10, Duration::from_secs(5)), "sortie non cadencée : {} images", capture.count());
+        pipeline.shutdown();
+        assert_eq!(metrics.stage(Stage::Depacketize).dropped.load(Ordering::Relaxed), 1);
+
+        // Sans image décodée, le sink ne reçoit que du noir à la résolution configurée
+        let black = black_frame(info.format, info.width, info.height, info.colorimetry);
+        let frames = capture.take();
+        assert!(!frames.is_empty());
+        for frame in &frames {
+            assert!(frame.data == black, "image {} : pas une image noire {}x{}", frame.index, info.width, info.height);
+        }
+        assert!(frames.windows(2).all(|w| w[1].index == w[0].index + 1 && w[1].at >= w[0].at));
+        assert_eq!((capture.info().width, capture.info().height), (64, 48));
+
+        // Étages joints : plus rien n'est écrit après l'arrêt
+        let written = capture.count();
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(capture.count(), written);
     }
 }
@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use serde::Serialize;
+use tracing::info;
+use crate::codec::rotate::{Orientation, Rotation};
+
+// Aucune valeur (Orientation::to_bits n'utilise que 4 bits)
+const UNSET: u8 = 0xFF;
+
+// Orientation appliquée par l'étage convert : celle annoncée par le téléphone,
+// sauf si elle est forcée depuis la CLI ou le dashboard.
+pub struct OrientationControl {
+    phone: AtomicU8,
+    manual: AtomicU8,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrientationStatus {
+    pub current: Orientation,
+    pub phone: Option<Orientation>,
+    pub manual: Option<Orientation>,
+}
+
+fn load(cell: &AtomicU8) -> Option<Orientation> {
+    match cell.load(Ordering::Relaxed) {
+        UNSET => None,
+        bits => Some(Orientation::from_bits(bits)),
+    }
+}
+
+fn store(cell: &AtomicU8, orientation: Option<Orientation>) {
+    cell.store(orientation.map_or(UNSET, Orientation::to_bits), Ordering::Relaxed);
+}
+
+impl OrientationControl {
+    pub fn new(manual: Option<Orientation>) -> Self {
+        let control = Self {
+            phone: AtomicU8::new(UNSET),
+            manual: AtomicU8::new(UNSET),
+        };
+        store(&control.manual, manual);
+        control
+    }
+
+    pub fn current(&self) -> Orientation {
+        load(&self.manual).or_else(|| load(&self.phone)).unwrap_or_default()
+    }
+
+    // Message {"type":"orientation", "angle": screen.orientation.angle, "facing": "user"|"environment"}
+    pub fn on_phone_metadata(&self, val: &serde_json::Value) -> Option<Orientation> {
+        let angle = val["angle"].as_u64()? as u32;
+        // L'écran tourné de `angle` dans le sens trigonométrique : on compense dans l'autre sens
+        let rotation = Rotation::from_degrees((360 - angle % 360) % 360)?;
+        let orientation = Orientation {
+            rotation,
+            // Caméra avant : image miroir, on la remet à l'endroit
+            flip_h: val["facing"] == "user",
+            flip_v: false,
+        };
+        if load(&self.phone) != Some(orientation) {
+            info!(rotation = rotation.degrees(), mirror = orientation.flip_h, "orientation du téléphone");
+        }
+        store(&self.phone, Some(orientation));
+        Some(orientation)
+    }
+
+    // None : retour à l'orientation automatique
+    pub fn set_manual(&self, orientation: Option<Orientation>) {
+        store(&self.manual, orientation);
+    }
+
+    pub fn status(&self) -> OrientationStatus {
+        OrientationStatus {
+            current: self.current(),
+            phone: load(&self.phone),
+            manual: load(&self.manual),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rotation(rotation: Rotation, flip_h: bool) -> Orientation {
+        Orientation { rotation, flip_h, flip_v: false }
+    }
+
+    #[test]
+    fn screen_angle_is_compensated_counterclockwise() {
+        let control = OrientationControl::new(None);
+        assert!(control.current().is_identity());
+
+        let cases = [
+            (0, Rotation::R0),
+            (90, Rotation::R270),
+            (180, Rotation::R180),
+            (270, Rotation::R90),
+            (360, Rotation::R0),
+        ];
+        for (angle, expected) in cases {
+            let orientation = control.on_phone_metadata(&json!({ "type": "orientation", "angle": angle, "facing": "environment" }));
+            assert_eq!(orientation, Some(rotation(expected, false)), "{}°", angle);
+            assert_eq!(control.current(), rotation(expected, false));
+        }
+    }
+
+    #[test]
+    fn front_camera_is_unmirrored() {
+        let control = OrientationControl::new(None);
+        control.on_phone_metadata(&json!({ "angle": 90, "facing": "user" }));
+        assert_eq!(control.current(), rotation(Rotation::R270, true));
+        // Sans "facing" : caméra arrière supposée
+        control.on_phone_metadata(&json!({ "angle": 0 }));
+        assert_eq!(control.current(), rotation(Rotation::R0, false));
+    }
+
+    #[test]
+    fn invalid_angle_keeps_the_previous_orientation() {
+        let control = OrientationControl::new(None);
+        control.on_phone_metadata(&json!({ "angle": 90 }));
+        assert!(control.on_phone_metadata(&json!({ "angle": 45 })).is_none());
+        assert!(control.on_phone_metadata(&json!({ "angle": "90" })).is_none());
+        assert_eq!(control.current(), rotation(Rotation::R270, false));
+    }
+
+    #[test]
+    fn manual_overrides_the_phone() {
+        let forced = Orientation { rotation: Rotation::R180, flip_h: false, flip_v: true };
+        let control = OrientationControl::new(Some(forced));
+        control.on_phone_metadata(&json!({ "angle": 90 }));
+        assert_eq!(control.current(), forced);
+
+        let status = control.status();
+        assert_eq!((status.phone, status.manual), (Some(rotation(Rotation::R270, false)), Some(forced)));
+
+        // Retour à l'automatique
+        control.set_manual(None);
+        assert_eq!(control.current(), rotation(Rotation::R270, false));
+        assert!(control.status().manual.is_none());
+    }
+}
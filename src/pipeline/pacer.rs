@@ -0,0 +1,280 @@
+use std::time::{Duration, Instant};
+use tracing::info;
+
+// Intervalle entre deux bilans de cadence dans les logs
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct PacerConfig {
+    // Cadence fixe présentée sur /dev/videoN (15, 24, 30 ou 60)
+    pub fps: u32,
+    // Ne jamais laisser de trou dans la sortie quand le téléphone ne livre plus rien : dernière
+    // image répétée, puis image noire. false : rien n'est écrit, les lecteurs voient un arrêt
+    pub repeat_on_stall: bool,
+    // Au-delà, le flux est considéré coupé : image noire à la place de la dernière image
+    pub max_repeat: Duration,
+}
+
+impl Default for PacerConfig {
+    fn default() -> Self {
+        Self {
+            fps: 30,
+            repeat_on_stall: true,
+            max_repeat: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PaceAction {
+    // Écrire la nouvelle image
+    Fresh,
+    // Réécrire la dernière image (entrée en retard ou interrompue)
+    Repeat,
+    // Écrire une image noire (pas encore d'image, ou flux coupé depuis plus de max_repeat)
+    Placeholder,
+    // Rien à écrire sur ce tick (repeat_on_stall désactivé)
+    Idle,
+}
+
+#[derive(Debug, Default)]
+struct PacerStats {
+    input: u64,
+    fresh: u64,
+    repeated: u64,
+    placeholders: u64,
+    superseded: u64,
+    idle: u64,
+    late_ticks: u64,
+}
+
+// Cadence la sortie sur une horloge monotone : les téléphones livrent à cadence variable
+// (requestAnimationFrame / MediaStreamTrackProcessor) alors que les applis de visio attendent
+// un rythme régulier. Les images en trop sont jetées, les manquantes dupliquées, et sans
+// image récente une image noire garde la cadence.
+pub struct FramePacer {
+    config: PacerConfig,
+    interval: Duration,
+    next_tick: Instant,
+    last_fresh: Option<Instant>,
+    stats: PacerStats,
+    last_report: Instant,
+}
+
+impl FramePacer {
+    pub fn new(config: PacerConfig) -> Self {
+        let interval = Duration::from_secs(1) / config.fps.max(1);
+        let now = Instant::now();
+
+        Self {
+            config,
+            interval,
+            next_tick: now + interval,
+            last_fresh: None,
+            stats: PacerStats::default(),
+            last_report: now,
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.config.fps
+    }
+
+    // Changement de cadence à chaud : le prochain tick est recalé sur le nouvel intervalle
+    pub fn set_fps(&mut self, fps: u32) {
+        self.config.fps = fps;
+        self.interval = Duration::from_secs(1) / fps.max(1);
+        self.next_tick = Instant::now() + self.interval;
+    }
+
+    pub fn deadline(&self) -> Instant {
+        self.next_tick
+    }
+
+    pub fn on_input(&mut self) {
+        self.stats.input += 1;
+    }
+
+    // Une image arrivée avant le tick a été remplacée par une plus récente
+    pub fn on_superseded(&mut self) {
+        self.stats.superseded += 1;
+    }
+
+    // Appelé à l'échéance : décide quoi écrire et programme le tick suivant
+    pub fn tick(&mut self, has_fresh: bool, has_last: bool) -> PaceAction {
+        self.tick_at(Instant::now(), has_fresh, has_last)
+    }
+
+    fn tick_at(&mut self, now: Instant, has_fresh: bool, has_last: bool) -> PaceAction {
+        self.next_tick += self.interval;
+        // Trop de retard (machine chargée, suspension) : on se recale au lieu de rafaler
+        if now > self.next_tick + self.interval {
+            self.stats.late_ticks += 1;
+            self.next_tick = now + self.interval;
+        }
+
+        let action = if has_fresh {
+            self.last_fresh = Some(now);
+            PaceAction::Fresh
+        } else if !self.config.repeat_on_stall {
+            PaceAction::Idle
+        } else if has_last && self.last_fresh.is_some_and(|t| now.duration_since(t) < self.config.max_repeat) {
+            PaceAction::Repeat
+        } else {
+            PaceAction::Placeholder
+        };
+
+        match action {
+            PaceAction::Fresh => self.stats.fresh += 1,
+            PaceAction::Repeat => self.stats.repeated += 1,
+            PaceAction::Placeholder => self.stats.placeholders += 1,
+            PaceAction::Idle => self.stats.idle += 1,
+        }
+
+        action
+    }
+
+    pub fn report_if_due(&mut self) {
+        let elapsed = self.last_report.elapsed();
+        if elapsed < REPORT_INTERVAL {
+            return;
+        }
+
+        let secs = elapsed.as_secs_f64();
+        let s = &self.stats;
+        if s.input > 0 || s.repeated > 0 {
+            info!(
+                fps = self.config.fps,
+                input_fps = (s.input as f64 / secs * 10.0).round() / 10.0,
+                output_fps = ((s.fresh + s.repeated + s.placeholders) as f64 / secs * 10.0).round() / 10.0,
+                repeated = s.repeated,
+                placeholders = s.placeholders,
+                superseded = s.superseded,
+                idle = s.idle,
+                late_ticks = s.late_ticks,
+                "bilan de cadence"
+            );
+        }
+
+        self.stats = PacerStats::default();
+        self.last_report = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pacer(fps: u32) -> FramePacer {
+        FramePacer::new(PacerConfig { fps, ..PacerConfig::default() })
+    }
+
+    // Rejoue des arrivées (décalages depuis le départ) tick par tick, comme output_loop :
+    // Fresh si au moins une image est arrivée depuis le tick précédent
+    fn simulate(pacer: &mut FramePacer, arrivals: &[Duration], ticks: usize) -> Vec<PaceAction> {
+        let start = pacer.deadline() - pacer.interval;
+        let mut pending = arrivals.iter().map(|&offset| start + offset).peekable();
+        let mut has_last = false;
+        let mut actions = Vec::new();
+        for _ in 0..ticks {
+            let now = pacer.deadline();
+            let mut fresh = 0;
+            while pending.next_if(|&at| at <= now).is_some() {
+                pacer.on_input();
+                fresh += 1;
+            }
+            for _ in 1..fresh {
+                pacer.on_superseded();
+            }
+            actions.push(pacer.tick_at(now, fresh > 0, has_last));
+            has_last |= fresh > 0;
+        }
+        actions
+    }
+
+    fn every(interval: Duration, count: u32) -> Vec<Duration> {
+        (0..count).map(|i| interval * i).collect()
+    }
+
+    #[test]
+    fn upsampling_repeats_every_other_tick() {
+        let mut pacer = pacer(60);
+        // 30 fps décalé d'un quart d'intervalle de sortie : jamais pile sur un tick
+        let arrivals = every(Duration::from_secs(1) / 30, 30)
+            .into_iter()
+            .map(|t| t + Duration::from_millis(4))
+            .collect::<Vec<_>>();
+        let actions = simulate(&mut pacer, &arrivals, 58);
+        for pair in actions.chunks(2) {
+            assert_eq!(pair, [PaceAction::Fresh, PaceAction::Repeat]);
+        }
+        assert_eq!((pacer.stats.fresh, pacer.stats.repeated, pacer.stats.late_ticks), (29, 29, 0));
+    }
+
+    #[test]
+    fn downsampling_keeps_the_newest_frame() {
+        let mut pacer = pacer(30);
+        let arrivals = every(Duration::from_secs(1) / 60, 60)
+            .into_iter()
+            .map(|t| t + Duration::from_millis(2))
+            .collect::<Vec<_>>();
+        let actions = simulate(&mut pacer, &arrivals, 29);
+        assert!(actions.iter().all(|a| *a == PaceAction::Fresh), "{:?}", actions);
+        // Deux images par tick : une écrite, l'autre remplacée
+        assert_eq!((pacer.stats.input, pacer.stats.fresh, pacer.stats.superseded), (58, 29, 29));
+    }
+
+    #[test]
+    fn jittered_input_repeats_only_when_a_tick_is_missed() {
+        let mut pacer = pacer(30);
+        let interval = Duration::from_secs(1) / 30;
+        // ±8 ms autour d'un 30 fps nominal, avec un trou (image 10 en retard de 40 ms)
+        let jitter = [3, 8, 1, 6, 2, 7, 4, 8, 0, 5];
+        let arrivals = (0..30u32)
+            .map(|i| interval * i + Duration::from_millis(jitter[i as usize % 10] + 8))
+            .map(|t| if t > interval * 10 && t < interval * 11 { t + Duration::from_millis(40) } else { t })
+            .collect::<Vec<_>>();
+        let actions = simulate(&mut pacer, &arrivals, 29);
+
+        let repeats = actions.iter().filter(|a| **a == PaceAction::Repeat).count();
+        assert_eq!(repeats, 1, "{:?}", actions);
+        assert_eq!(actions[10], PaceAction::Repeat);
+        // L'image en retard ne décale pas la cadence : deux images arrivent pour le tick suivant
+        assert_eq!(pacer.stats.superseded, 1);
+        assert_eq!(pacer.stats.late_ticks, 0);
+    }
+
+    #[test]
+    fn late_tick_resyncs_instead_of_bursting() {
+        let mut pacer = pacer(30);
+        let interval = pacer.interval;
+        let late = pacer.deadline() + interval * 5;
+        assert_eq!(pacer.tick_at(late, true, false), PaceAction::Fresh);
+        assert_eq!(pacer.stats.late_ticks, 1);
+        assert_eq!(pacer.deadline(), late + interval);
+
+        // Retard d'un seul intervalle : rattrapé sans recalage
+        let deadline = pacer.deadline();
+        pacer.tick_at(deadline + interval, true, true);
+        assert_eq!(pacer.stats.late_ticks, 1);
+        assert_eq!(pacer.deadline(), deadline + interval);
+    }
+
+    #[test]
+    fn stall_repeats_then_goes_black() {
+        let mut pacer = pacer(10);
+        let start = pacer.deadline();
+        // Pas encore d'image
+        assert_eq!(pacer.tick_at(start, false, false), PaceAction::Placeholder);
+        assert_eq!(pacer.tick_at(pacer.deadline(), true, false), PaceAction::Fresh);
+        let fresh_at = start + pacer.interval;
+        while pacer.deadline() < fresh_at + pacer.config.max_repeat {
+            assert_eq!(pacer.tick_at(pacer.deadline(), false, true), PaceAction::Repeat);
+        }
+        assert_eq!(pacer.tick_at(pacer.deadline(), false, true), PaceAction::Placeholder);
+
+        let mut pacer = FramePacer::new(PacerConfig { fps: 10, repeat_on_stall: false, ..PacerConfig::default() });
+        assert_eq!(pacer.tick_at(pacer.deadline(), true, false), PaceAction::Fresh);
+        assert_eq!(pacer.tick_at(pacer.deadline(), false, true), PaceAction::Idle);
+    }
+}
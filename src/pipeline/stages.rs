@@ -0,0 +1,336 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use crate::codec::convert::black_frame;
+use crate::logging::Throttle;
+use crate::memory::pool::{FramePool, PooledBuf, SizeClass};
+use crate::metrics::{ServerMetrics, Stage};
+use crate::net::clock::ClockEstimate;
+use crate::net::protocol::Header;
+use crate::pipeline::bitstream::BitstreamParser;
+use crate::pipeline::codec::CodecConfig;
+use crate::pipeline::hwaccel::{FrameWrapper, HardwareDecoder};
+use crate::pipeline::latency::FrameTimestamps;
+use crate::pipeline::pacer::{FramePacer, PaceAction, PacerConfig};
+use crate::pipeline::transform::FrameTransform;
+use crate::record::Recorder;
+use crate::sink::{FrameInfo, FrameSink, SinkError};
+use crate::sync::{mpmc, spsc, PushError};
+use crate::v4l2::readers::ReaderMonitor;
+
+// Au plus un log d'erreur par étage et par période ; le total est dans les métriques
+const ERROR_LOG_PERIOD: Duration = Duration::from_secs(5);
+
+// Messages reçus du WebSocket (paquets bruts "PC" + en-tête)
+pub enum Ingest {
+    Configure(CodecConfig),
+    Packet {
+        data: Vec<u8>,
+        // Décalage d'horloge de la session, pour ramener la capture dans l'horloge serveur
+        clock: Option<ClockEstimate>,
+        ts: FrameTimestamps,
+    },
+}
+
+pub enum Encoded {
+    Configure(CodecConfig),
+    Chunk { payload: PooledBuf, ts: FrameTimestamps },
+}
+
+pub struct Decoded {
+    frame: FrameWrapper,
+    ts: FrameTimestamps,
+}
+
+pub struct Converted {
+    data: PooledBuf,
+    ts: FrameTimestamps,
+}
+
+// Résultat d'un envoi non bloquant : sous surcharge, l'élément est jeté et compté pour l'étage suivant
+pub fn forwarded<T>(result: Result<(), PushError<T>>, metrics: &ServerMetrics, next: Stage) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(PushError::Full(_)) => {
+            metrics.record_drop(next);
+            false
+        }
+        Err(PushError::Closed(_)) => false,
+    }
+}
+
+// Retire l'en-tête "PC" et ne laisse passer les deltas qu'après une keyframe
+pub fn depacketize_loop(
+    rx: mpmc::Receiver<Ingest>,
+    mut tx: spsc::Producer<Encoded>,
+    resync: Arc<AtomicBool>,
+    recorder: Arc<Recorder>,
+    readers: Option<Arc<ReaderMonitor>>,
+    pool: Arc<FramePool>,
+    metrics: Arc<ServerMetrics>,
+) {
+    let mut parser: Option<BitstreamParser> = None;
+    let mut waiting_keyframe = true;
+    let mut keyframes_only = false;
+    let unwatched = || readers.as_ref().is_some_and(|r| !r.is_watched());
+
+    while let Some(msg) = rx.pop_blocking() {
+        metrics.set_queue_depth(Stage::Depacketize, rx.len());
+        match msg {
+            Ingest::Configure(config) => {
+                parser = Some(BitstreamParser::new(&config));
+                waiting_keyframe = true;
+                recorder.on_configure(&config);
+                // La config ne doit jamais être perdue : envoi bloquant
+                if tx.push_blocking(Encoded::Configure(config)).is_err() {
+                    break;
+                }
+            }
+            Ingest::Packet { data, clock, mut ts } => {
+                let Some(parser) = parser.as_ref() else {
+                    metrics.record_drop(Stage::Depacketize);
+                    continue;
+                };
+                let Some(header) = Header::parse(&data) else {
+                    metrics.record_drop(Stage::Depacketize);
+                    continue;
+                };
+                let payload = header.payload(&data);
+                ts.capture_us = header.capture_us.zip(clock).map(|(capture, clock)| clock.to_server_us(capture));
+
+                // Un paquet perdu en amont casse la chaîne de références : attendre une keyframe
+                if resync.swap(false, Ordering::AcqRel) {
+                    waiting_keyframe = true;
+                }
+                if waiting_keyframe {
+                    if !parser.is_keyframe(payload) {
+                        metrics.record_drop(Stage::Depacketize);
+                        continue;
+                    }
+                    waiting_keyframe = false;
+                }
+
+                if let Some(latency) = ts.stamp(Stage::Depacketize) {
+                    metrics.record_stage(Stage::Depacketize, latency);
+                }
+                recorder.on_packet(payload, &ts);
+
+                // Personne ne lit la sortie : seules les keyframes sont décodées. Quand un lecteur
+                // revient, on attend la keyframe suivante (les deltas jetées ont cassé la chaîne)
+                if unwatched() {
+                    keyframes_only = true;
+                }
+                if keyframes_only {
+                    if !parser.is_keyframe(payload) {
+                        continue;
+                    }
+                    keyframes_only = unwatched();
+                }
+                // Pool épuisé : tampon hors pool, une image perdue casserait la chaîne de références
+                let chunk = Encoded::Chunk {
+                    payload: pool.copy_from(SizeClass::EncodedFrame, payload),
+                    ts,
+                };
+                if !forwarded(tx.try_push(chunk), &metrics, Stage::Decode) {
+                    waiting_keyframe = true;
+                }
+            }
+        }
+    }
+}
+
+pub fn decode_loop(mut rx: spsc::Consumer<Encoded>, mut tx: spsc::Producer<Decoded>, metrics: Arc<ServerMetrics>) {
+    let mut decoder: Option<HardwareDecoder> = None;
+    let mut errors = Throttle::new(ERROR_LOG_PERIOD);
+
+    while let Some(msg) = rx.pop_blocking() {
+        metrics.set_queue_depth(Stage::Decode, rx.len());
+        match msg {
+            Encoded::Configure(config) => {
+                decoder = match HardwareDecoder::new(&config) {
+                    Ok(d) => {
+                        info!(codec = config.codec.name(), codec_string = %config.codec_string, width = config.width, height = config.height, "décodeur prêt");
+                        Some(d)
+                    }
+                    Err(e) => {
+                        error!(codec = config.codec.name(), error = %e, "décodeur indisponible");
+                        None
+                    }
+                };
+            }
+            Encoded::Chunk { payload, mut ts } => {
+                let Some(decoder) = decoder.as_mut() else {
+                    metrics.record_drop(Stage::Decode);
+                    continue;
+                };
+                let frames = match decoder.decode(&payload) {
+                    Ok(frames) => frames,
+                    Err(e) => {
+                        metrics.record_error(Stage::Decode);
+                        // Un flux corrompu échoue en rafale
+                        if let Some(suppressed) = errors.ready() {
+                            warn!(error = %e, suppressed, "échec du décodage");
+                        }
+                        continue;
+                    }
+                };
+                // Aucune frame : le décodeur attend d'autres paquets
+                if frames.is_empty() {
+                    continue;
+                }
+
+                if let Some(latency) = ts.stamp(Stage::Decode) {
+                    metrics.record_stage(Stage::Decode, latency);
+                }
+                // Plusieurs frames pour un paquet (file interne du décodeur vidée) : même horodatage
+                for frame in frames {
+                    forwarded(tx.try_push(Decoded { frame, ts }), &metrics, Stage::Convert);
+                }
+            }
+        }
+    }
+}
+
+pub fn convert_loop(
+    mut rx: spsc::Consumer<Decoded>,
+    mut tx: spsc::Producer<Converted>,
+    mut transform: FrameTransform,
+    pool: Arc<FramePool>,
+    metrics: Arc<ServerMetrics>,
+) {
+    let mut errors = Throttle::new(ERROR_LOG_PERIOD);
+    while let Some(Decoded { frame, mut ts }) = rx.pop_blocking() {
+        metrics.set_queue_depth(Stage::Convert, rx.len());
+        // Formats inconnus ou plans inexploitables : frame ignorée
+        let Some(image) = frame.image() else {
+            metrics.record_error(Stage::Convert);
+            continue;
+        };
+
+        // I420 (logiciel) ou NV12 (VAAPI) -> résolution et format de la loopback, SIMD choisi à l'exécution
+        // Tous les tampons encore en file ou retenus par la sortie : image perdue
+        let Some(mut buffer) = pool.try_acquire(SizeClass::RawFrame) else {
+            metrics.record_drop(Stage::Convert);
+            continue;
+        };
+        buffer.resize(transform.output_size());
+        if let Err(e) = transform.apply(&image, frame.colorimetry(), &mut buffer) {
+            if let Some(suppressed) = errors.ready() {
+                warn!(format = %image.format, width = image.width, height = image.height, error = %e, suppressed, "échec de la conversion");
+            }
+            metrics.record_error(Stage::Convert);
+            continue;
+        }
+
+        if let Some(latency) = ts.stamp(Stage::Convert) {
+            metrics.record_stage(Stage::Convert, latency);
+        }
+        let converted = Converted { data: buffer, ts };
+        forwarded(tx.try_push(converted), &metrics, Stage::Output);
+    }
+}
+
+// Sortie cadencée : on garde la frame la plus récente jusqu'au tick suivant
+pub fn output_loop(
+    mut rx: spsc::Consumer<Converted>,
+    mut sink: Box<dyn FrameSink>,
+    info: FrameInfo,
+    pacer_config: PacerConfig,
+    fps: Arc<AtomicU32>,
+    recorder: Arc<Recorder>,
+    metrics: Arc<ServerMetrics>,
+) {
+    let mut pacer = FramePacer::new(pacer_config);
+    let mut errors = Throttle::new(ERROR_LOG_PERIOD);
+    let mut pending: Option<Converted> = None;
+    let mut last: Option<PooledBuf> = None;
+    // Image noire à la taille du sink, construite au premier besoin
+    let mut black: Option<Vec<u8>> = None;
+    // File fermée (arrêt) : dernier tour pour écrire l'image en attente
+    let mut closing = false;
+
+    loop {
+        let target = fps.load(Ordering::Relaxed);
+        if target != pacer.fps() {
+            pacer.set_fps(target);
+        }
+        let deadline = pacer.deadline();
+        match rx.pop_until(deadline) {
+            Some(frame) => {
+                metrics.set_queue_depth(Stage::Output, rx.len());
+                pacer.on_input();
+                if pending.replace(frame).is_some() {
+                    pacer.on_superseded();
+                    metrics.record_drop(Stage::Output);
+                }
+                if Instant::now() < deadline {
+                    continue;
+                }
+            }
+            None if rx.is_closed() => {
+                if pending.is_none() {
+                    break;
+                }
+                closing = true;
+            }
+            None => {}
+        }
+
+        match pacer.tick(pending.is_some(), last.is_some()) {
+            PaceAction::Fresh => {
+                let Some(Converted { data, mut ts }) = pending.take() else {
+                    continue;
+                };
+                if let Err(e) = sink.write_frame(&data) {
+                    report_write_error(&e, &mut errors);
+                    if matches!(e, SinkError::FrameSize { .. }) {
+                        metrics.record_rejected();
+                    }
+                    metrics.record_drop(Stage::Output);
+                    continue;
+                }
+                if let Some(latency) = ts.stamp(Stage::Output) {
+                    metrics.record_stage(Stage::Output, latency);
+                }
+                metrics.record_frame_latency(&ts);
+                recorder.on_frame(&data);
+                last = Some(data);
+            }
+            PaceAction::Repeat => {
+                metrics.record_late(Stage::Output);
+                if let Some(data) = last.as_deref() {
+                    match sink.write_frame(data) {
+                        Ok(()) => recorder.on_frame(data),
+                        Err(e) => report_write_error(&e, &mut errors),
+                    }
+                }
+            }
+            PaceAction::Placeholder => {
+                metrics.record_late(Stage::Output);
+                // Pas d'image récente : du noir plutôt qu'un trou dans la sortie
+                let data = black.get_or_insert_with(|| black_frame(info.format, info.width, info.height, info.colorimetry));
+                match sink.write_frame(data) {
+                    Ok(()) => recorder.on_frame(data),
+                    Err(e) => report_write_error(&e, &mut errors),
+                }
+            }
+            PaceAction::Idle => {}
+        }
+
+        pacer.report_if_due();
+        if closing {
+            break;
+        }
+    }
+}
+
+// Busy : le lecteur ne suit pas, l'image est simplement perdue (comptée dans les métriques)
+fn report_write_error(e: &SinkError, errors: &mut Throttle) {
+    if matches!(e, SinkError::Busy) {
+        return;
+    }
+    if let Some(suppressed) = errors.ready() {
+        warn!(error = %e, suppressed, "échec de l'écriture en sortie");
+    }
+}
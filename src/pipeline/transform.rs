@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use tracing::info;
+use crate::codec::convert::{self, ConvertError, Image, PixelFormat};
+use crate::codec::rotate;
+use crate::codec::scale::{ScaleConfig, Scaler};
+use crate::pipeline::orientation::OrientationControl;
+
+// Mise au format de la caméra virtuelle : orientation, résolution fixe (scaler) puis format de pixels.
+// Les tampons intermédiaires sont gardés d'une frame à l'autre.
+pub struct FrameTransform {
+    format: PixelFormat,
+    scale: ScaleConfig,
+    orientation: Arc<OrientationControl>,
+    scaler: Option<Scaler>,
+    i420: Vec<u8>,
+    rotated: Vec<u8>,
+    scaled: Vec<u8>,
+}
+
+impl FrameTransform {
+    pub fn new(format: PixelFormat, scale: ScaleConfig, orientation: Arc<OrientationControl>) -> Self {
+        Self {
+            format,
+            scale,
+            orientation,
+            scaler: None,
+            i420: Vec::new(),
+            rotated: Vec::new(),
+            scaled: Vec::new(),
+        }
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.format.frame_size(self.scale.width, self.scale.height)
+    }
+
+    pub fn apply(&mut self, image: &Image, colorimetry: convert::Colorimetry, out: &mut [u8]) -> Result<(), ConvertError> {
+        let (width, height) = (self.scale.width, self.scale.height);
+        let orientation = self.orientation.current();
+        if orientation.is_identity() && (image.width, image.height) == (width, height) {
+            // Un éventuel passage par I420 réutilise `i420`, libre sur ce chemin
+            return convert::convert_with(image, out, self.format, colorimetry, &mut self.i420);
+        }
+
+        // Rotation et scaler travaillent en I420 : NV12 (VAAPI) et autres y passent d'abord
+        let i420 = if image.format == PixelFormat::I420 {
+            *image
+        } else {
+            self.i420.resize(PixelFormat::I420.frame_size(image.width, image.height), 0);
+            convert::convert(image, &mut self.i420, PixelFormat::I420, colorimetry)?;
+            Image::packed(PixelFormat::I420, &self.i420, image.width, image.height)?
+        };
+
+        let oriented = if orientation.is_identity() {
+            i420
+        } else {
+            let (ow, oh) = orientation.output_size(image.width, image.height);
+            self.rotated.resize(PixelFormat::I420.frame_size(ow, oh), 0);
+            rotate::orient_i420(&i420.as_yuv420(), &mut self.rotated, orientation);
+            Image::packed(PixelFormat::I420, &self.rotated, ow, oh)?
+        };
+        if (oriented.width, oriented.height) == (width, height) {
+            return convert::convert(&oriented, out, self.format, colorimetry);
+        }
+
+        // Nouvelle taille source (rotation du téléphone, changement de résolution)
+        let source = (oriented.width, oriented.height);
+        if self.scaler.as_ref().is_none_or(|s| s.source_size() != source) {
+            info!(
+                source_width = source.0,
+                source_height = source.1,
+                width,
+                height,
+                filter = self.scale.filter.name(),
+                fit = self.scale.fit.name(),
+                "mise à l'échelle"
+            );
+            self.scaler = Some(Scaler::new(source.0, source.1, self.scale));
+        }
+        let scaler = self.scaler.as_mut().expect("scaler initialisé");
+
+        self.scaled.resize(PixelFormat::I420.frame_size(width, height), 0);
+        scaler.scale(&oriented.as_yuv420(), &mut self.scaled, colorimetry.range);
+
+        let scaled = Image::packed(PixelFormat::I420, &self.scaled, width, height)?;
+        convert::convert(&scaled, out, self.format, colorimetry)
+    }
+}
@@ -0,0 +1,721 @@
+pub mod mux;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::{error, info};
+use crate::codec::convert::PixelFormat;
+use crate::metrics::Stage;
+use crate::pipeline::bitstream::BitstreamParser;
+use crate::pipeline::codec::CodecConfig;
+use crate::pipeline::latency::FrameTimestamps;
+use crate::sink::file::FileSink;
+use crate::sink::{FrameInfo, FrameSink};
+use crate::sync::{mpmc, PushError};
+use mux::Muxer;
+
+// Les images Y4M pèsent plusieurs Mo : file courte, on jette si le disque ne suit pas
+const RECORD_QUEUE: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+impl Container {
+    pub const ALL: [Container; 2] = [Container::Mp4, Container::Mkv];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+
+    // Nom du muxer FFmpeg
+    pub fn format_name(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "matroska",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordConfig {
+    pub dir: PathBuf,
+    pub container: Container,
+    // Copie Y4M des images envoyées à la sortie (après conversion et cadencement)
+    pub y4m: bool,
+    // Nouveau fichier au-delà de cette taille ou de cette durée (à la keyframe suivante pour le flux)
+    pub max_bytes: Option<u64>,
+    pub max_duration: Option<Duration>,
+    // Démarrer dès le lancement
+    pub start: bool,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("recordings"),
+            container: Container::default(),
+            y4m: false,
+            max_bytes: None,
+            max_duration: None,
+            start: false,
+        }
+    }
+}
+
+impl RecordConfig {
+    fn segment_full(&self, bytes: u64, elapsed: Duration) -> bool {
+        self.max_bytes.is_some_and(|max| bytes >= max) || self.max_duration.is_some_and(|max| elapsed >= max)
+    }
+
+    // Le flux ne se coupe que sur une keyframe, pour que chaque fichier soit lisible seul
+    fn rotate_stream(&self, keyframe: bool, bytes: u64, first_pts_us: u64, pts_us: u64) -> bool {
+        keyframe && self.segment_full(bytes, Duration::from_micros(pts_us.saturating_sub(first_pts_us)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecordStatus {
+    pub recording: bool,
+    // Fichiers en cours d'écriture
+    pub stream_file: Option<PathBuf>,
+    pub y4m_file: Option<PathBuf>,
+    // Depuis le dernier démarrage
+    pub files: u32,
+    pub bytes: u64,
+    pub dropped: u64,
+    pub error: Option<String>,
+}
+
+enum RecordMsg {
+    Configure(CodecConfig),
+    Packet { data: Vec<u8>, pts_us: u64 },
+    Frame(Vec<u8>),
+}
+
+// Enregistrement du flux reçu du téléphone (remuxé en MP4/MKV) et, en option, des images
+// de sortie en Y4M. Les étages de la pipeline ne font que pousser dans une file : l'écriture
+// disque se fait sur un thread dédié, démarré et arrêté depuis la CLI ou le dashboard.
+pub struct Recorder {
+    config: RecordConfig,
+    frame_info: FrameInfo,
+    active: AtomicBool,
+    tx: Mutex<Option<mpmc::Sender<RecordMsg>>>,
+    // Dernière config du décodeur, rejouée quand l'enregistrement démarre en cours de session
+    codec: Mutex<Option<CodecConfig>>,
+    // Paquet jeté (file pleine) : le flux enregistré doit reprendre sur une keyframe
+    packet_lost: AtomicBool,
+    dropped: AtomicU64,
+    // Incrémenté à chaque démarrage : un thread encore en train de fermer ses fichiers
+    // ne doit pas écraser l'état de l'enregistrement suivant
+    generation: AtomicU64,
+    status: Mutex<RecordStatus>,
+    // Threads d'écriture, attendus à l'arrêt du serveur : celui d'un enregistrement arrêté
+    // peut encore fermer ses fichiers quand le suivant démarre
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Recorder {
+    pub fn new(config: RecordConfig, frame_info: FrameInfo) -> Arc<Self> {
+        Arc::new(Self {
+            config,
+            frame_info,
+            active: AtomicBool::new(false),
+            tx: Mutex::new(None),
+            codec: Mutex::new(None),
+            packet_lost: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+            status: Mutex::new(RecordStatus::default()),
+            workers: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn start(self: &Arc<Self>) -> Result<RecordStatus, String> {
+        let mut tx_slot = self.tx.lock();
+        if tx_slot.is_some() {
+            return Ok(self.status());
+        }
+        std::fs::create_dir_all(&self.config.dir).map_err(|e| format!("{} : {}", self.config.dir.display(), e))?;
+
+        let (tx, rx) = mpmc::bounded(RECORD_QUEUE);
+        if let Some(codec) = self.codec.lock().clone() {
+            let _ = tx.try_push(RecordMsg::Configure(codec));
+        }
+        let generation = self.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        *self.status.lock() = RecordStatus {
+            recording: true,
+            ..RecordStatus::default()
+        };
+        self.dropped.store(0, Ordering::Relaxed);
+
+        let recorder = self.clone();
+        let worker = std::thread::Builder::new()
+            .name("pc-record".into())
+            .spawn(move || recorder.run(rx, generation))
+            .map_err(|e| e.to_string())?;
+        let mut workers = self.workers.lock();
+        join_workers(workers.extract_if(.., |w| w.is_finished()));
+        workers.push(worker);
+        drop(workers);
+        *tx_slot = Some(tx);
+        self.active.store(true, Ordering::Release);
+        info!(dir = %self.config.dir.display(), "enregistrement démarré");
+        Ok(self.status())
+    }
+
+    // Le thread d'écriture vide la file puis ferme les fichiers
+    pub fn stop(&self) -> RecordStatus {
+        self.active.store(false, Ordering::Release);
+        if self.tx.lock().take().is_some() {
+            self.status.lock().recording = false;
+            info!("enregistrement arrêté");
+        }
+        self.status()
+    }
+
+    // Arrêt du serveur : comme stop, puis attente de la fermeture des fichiers (index MP4/MKV)
+    pub fn finish(&self) {
+        self.stop();
+        let workers = std::mem::take(&mut *self.workers.lock());
+        join_workers(workers);
+    }
+
+    pub fn status(&self) -> RecordStatus {
+        let mut status = self.status.lock().clone();
+        status.dropped = self.dropped.load(Ordering::Relaxed);
+        status
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub fn wants_frames(&self) -> bool {
+        self.config.y4m && self.is_recording()
+    }
+
+    // Étage depacketize : nouvelle session ou changement de codec
+    pub fn on_configure(&self, config: &CodecConfig) {
+        *self.codec.lock() = Some(config.clone());
+        // Attente hors du verrou : stop() et les autres étages ne doivent pas rester bloqués
+        let tx = self.tx.lock().clone();
+        if let Some(tx) = tx {
+            // Rare et indispensable à la suite du flux : attente d'une place
+            let _ = tx.push_blocking(RecordMsg::Configure(config.clone()));
+        }
+    }
+
+    // Étage depacketize : chunk compressé déjà filtré (on ne démarre que sur keyframe)
+    pub fn on_packet(&self, payload: &[u8], ts: &FrameTimestamps) {
+        if !self.is_recording() {
+            return;
+        }
+        // Capture téléphone si l'horloge est synchronisée, sinon arrivée sur le serveur
+        let Some(pts_us) = ts.capture_us.or(ts.get(Stage::Depacketize)) else {
+            return;
+        };
+        let packet = RecordMsg::Packet {
+            data: payload.to_vec(),
+            pts_us,
+        };
+        if !self.push(packet) {
+            self.packet_lost.store(true, Ordering::Release);
+        }
+    }
+
+    // Étage output : image effectivement présentée (fraîche ou répétée), au format de sortie
+    pub fn on_frame(&self, data: &[u8]) {
+        if self.wants_frames() {
+            self.push(RecordMsg::Frame(data.to_vec()));
+        }
+    }
+
+    fn push(&self, msg: RecordMsg) -> bool {
+        let tx = self.tx.lock();
+        let Some(tx) = tx.as_ref() else {
+            return false;
+        };
+        match tx.try_push(msg) {
+            Ok(()) => true,
+            Err(PushError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(PushError::Closed(_)) => false,
+        }
+    }
+
+    fn run(self: Arc<Self>, rx: mpmc::Receiver<RecordMsg>, generation: u64) {
+        let mut writer = SegmentWriter::new(&self.config, self.frame_info);
+
+        while let Some(msg) = rx.pop_blocking() {
+            match msg {
+                RecordMsg::Configure(config) => writer.configure(config),
+                RecordMsg::Packet { data, pts_us } => {
+                    if self.packet_lost.swap(false, Ordering::AcqRel) {
+                        writer.waiting_keyframe = true;
+                    }
+                    writer.packet(&data, pts_us);
+                }
+                RecordMsg::Frame(data) => writer.frame(&data),
+            }
+            self.report(&writer, generation);
+        }
+
+        writer.close();
+        self.report(&writer, generation);
+    }
+
+    fn report(&self, writer: &SegmentWriter, generation: u64) {
+        let mut status = self.status.lock();
+        if self.generation.load(Ordering::Acquire) == generation {
+            writer.report(&mut status);
+        }
+    }
+}
+
+struct StreamSegment {
+    muxer: Muxer,
+    path: PathBuf,
+    first_pts_us: u64,
+}
+
+struct FrameSegment {
+    sink: FileSink,
+    path: PathBuf,
+    opened: Instant,
+    bytes: u64,
+}
+
+// État du thread d'enregistrement : fichiers ouverts et rotation
+struct SegmentWriter {
+    config: RecordConfig,
+    frame_info: FrameInfo,
+    // Préfixe commun aux fichiers de cet enregistrement : phonecam-AAAAMMJJ-HHMMSS
+    prefix: String,
+    codec: Option<(CodecConfig, BitstreamParser)>,
+    stream: Option<StreamSegment>,
+    frames: Option<FrameSegment>,
+    waiting_keyframe: bool,
+    files: u32,
+    bytes: u64,
+    error: Option<String>,
+}
+
+impl SegmentWriter {
+    fn new(config: &RecordConfig, frame_info: FrameInfo) -> Self {
+        Self {
+            config: config.clone(),
+            frame_info,
+            prefix: format!("phonecam-{}", local_timestamp()),
+            codec: None,
+            stream: None,
+            frames: None,
+            waiting_keyframe: true,
+            files: 0,
+            bytes: 0,
+            error: None,
+        }
+    }
+
+    fn configure(&mut self, config: CodecConfig) {
+        // Nouveau codec ou nouvelle résolution : nouveau fichier
+        self.close_stream();
+        let parser = BitstreamParser::new(&config);
+        self.codec = Some((config, parser));
+        self.waiting_keyframe = true;
+    }
+
+    fn packet(&mut self, data: &[u8], pts_us: u64) {
+        let Some((_, parser)) = self.codec.as_ref() else {
+            return;
+        };
+        let keyframe = parser.is_keyframe(data);
+        if self.waiting_keyframe && !keyframe {
+            return;
+        }
+
+        let full = self
+            .stream
+            .as_ref()
+            .is_some_and(|s| self.config.rotate_stream(keyframe, s.muxer.bytes(), s.first_pts_us, pts_us));
+        if full {
+            self.close_stream();
+        }
+        if self.stream.is_none() && !self.open_stream(data, pts_us) {
+            return;
+        }
+
+        let Some(stream) = self.stream.as_mut() else {
+            return;
+        };
+        let pts = pts_us.saturating_sub(stream.first_pts_us) as i64;
+        match stream.muxer.write(data, pts, keyframe) {
+            Ok(()) => {
+                self.waiting_keyframe = false;
+                self.bytes += data.len() as u64;
+            }
+            Err(e) => {
+                error!(path = %stream.path.display(), error = %e, "écriture de l'enregistrement impossible");
+                self.error = Some(e.to_string());
+                self.waiting_keyframe = true;
+            }
+        }
+    }
+
+    // Nouveau fichier, toujours sur une keyframe
+    fn open_stream(&mut self, keyframe: &[u8], pts_us: u64) -> bool {
+        let Some((config, parser)) = &self.codec else {
+            return false;
+        };
+        let extradata = match &config.description {
+            Some(description) => description.clone(),
+            None => parser.parameter_sets(keyframe),
+        };
+        let path = self.next_path(self.config.container.name());
+
+        match Muxer::create(&path, self.config.container, config, &extradata) {
+            Ok(muxer) => {
+                info!(path = %path.display(), codec = config.codec.name(), container = self.config.container.name(), "segment d'enregistrement ouvert");
+                self.files += 1;
+                self.stream = Some(StreamSegment {
+                    muxer,
+                    path,
+                    first_pts_us: pts_us,
+                });
+                true
+            }
+            Err(e) => {
+                // Inutile de réessayer à chaque keyframe : on attend une nouvelle config
+                error!(path = %path.display(), error = %e, "enregistrement impossible");
+                let _ = std::fs::remove_file(&path);
+                self.error = Some(e.to_string());
+                self.codec = None;
+                false
+            }
+        }
+    }
+
+    fn frame(&mut self, data: &[u8]) {
+        if !self.config.y4m {
+            return;
+        }
+        let full = self
+            .frames
+            .as_ref()
+            .is_some_and(|f| self.config.segment_full(f.bytes, f.opened.elapsed()));
+        if full {
+            self.frames = None;
+        }
+
+        if self.frames.is_none() {
+            let path = self.next_path("y4m");
+            match FileSink::create(&path, &self.frame_info) {
+                Ok(sink) => {
+                    info!(path = %path.display(), sink = %sink.describe(), "segment d'images ouvert");
+                    self.files += 1;
+                    self.frames = Some(FrameSegment {
+                        sink,
+                        path,
+                        opened: Instant::now(),
+                        bytes: 0,
+                    });
+                }
+                Err(e) => {
+                    error!(path = %path.display(), error = %e, "enregistrement des images impossible");
+                    self.error = Some(e.to_string());
+                    self.config.y4m = false;
+                    return;
+                }
+            }
+        }
+
+        let Some(frames) = self.frames.as_mut() else {
+            return;
+        };
+        match frames.sink.write_frame(data) {
+            Ok(()) => {
+                let written = y4m_frame_size(&self.frame_info) as u64;
+                frames.bytes += written;
+                self.bytes += written;
+            }
+            Err(e) => {
+                error!(path = %frames.path.display(), error = %e, "écriture des images enregistrées impossible");
+                self.error = Some(e.to_string());
+                self.frames = None;
+                self.config.y4m = false;
+            }
+        }
+    }
+
+    fn close_stream(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            info!(path = %stream.path.display(), bytes = stream.muxer.bytes(), "segment d'enregistrement fermé");
+        }
+        self.waiting_keyframe = true;
+    }
+
+    fn close(&mut self) {
+        self.close_stream();
+        self.frames = None;
+    }
+
+    // phonecam-AAAAMMJJ-HHMMSS-001.mp4, sans écraser un fichier existant
+    fn next_path(&self, extension: &str) -> PathBuf {
+        (self.files + 1..)
+            .map(|n| self.config.dir.join(format!("{}-{:03}.{}", self.prefix, n, extension)))
+            .find(|path| !path.exists())
+            .unwrap_or_else(|| self.config.dir.join(format!("{}.{}", self.prefix, extension)))
+    }
+
+    fn report(&self, status: &mut RecordStatus) {
+        status.stream_file = self.stream.as_ref().map(|s| s.path.clone());
+        status.y4m_file = self.frames.as_ref().map(|f| f.path.clone());
+        status.files = self.files;
+        status.bytes = self.bytes;
+        status.error = self.error.clone();
+    }
+}
+
+fn join_workers(workers: impl IntoIterator<Item = JoinHandle<()>>) {
+    for worker in workers {
+        if worker.join().is_err() {
+            error!("thread d'enregistrement terminé sur une panique");
+        }
+    }
+}
+
+// En-tête "FRAME\n" + image planaire (FileSink convertit en I420 si besoin)
+fn y4m_frame_size(info: &FrameInfo) -> usize {
+    let format = if info.format == PixelFormat::Grey { PixelFormat::Grey } else { PixelFormat::I420 };
+    6 + format.frame_size(info.width, info.height)
+}
+
+// Heure locale AAAAMMJJ-HHMMSS, pour nommer les fichiers
+fn local_timestamp() -> String {
+    // SAFETY : time/localtime_r sur des variables locales
+    unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&now, &mut tm);
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            tm.tm_year + 1900,
+            tm.tm_mon + 1,
+            tm.tm_mday,
+            tm.tm_hour,
+            tm.tm_min,
+            tm.tm_sec
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::codec::convert::Colorimetry;
+    use crate::pipeline::codec::VideoCodec;
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("phonecam-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn files(&self) -> Vec<PathBuf> {
+            let mut files: Vec<PathBuf> = fs::read_dir(&self.0).unwrap().map(|e| e.unwrap().path()).collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn frame_info(format: PixelFormat) -> FrameInfo {
+        FrameInfo {
+            format,
+            width: 4,
+            height: 2,
+            fps: 30,
+            colorimetry: Colorimetry::default(),
+        }
+    }
+
+    fn h264() -> CodecConfig {
+        CodecConfig {
+            codec: VideoCodec::H264,
+            codec_string: "avc1.42001f".into(),
+            width: 4,
+            height: 2,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn segment_full_on_either_limit() {
+        let mut config = RecordConfig::default();
+        assert!(!config.segment_full(u64::MAX, Duration::MAX));
+
+        config.max_bytes = Some(1000);
+        assert!(!config.segment_full(999, Duration::MAX));
+        assert!(config.segment_full(1000, Duration::ZERO));
+
+        config.max_bytes = None;
+        config.max_duration = Some(Duration::from_secs(60));
+        assert!(!config.segment_full(u64::MAX, Duration::from_secs(59)));
+        assert!(config.segment_full(0, Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn stream_rotates_only_on_keyframes() {
+        let config = RecordConfig {
+            max_duration: Some(Duration::from_secs(10)),
+            ..RecordConfig::default()
+        };
+        // Durée dépassée : la delta reste dans le fichier courant, la keyframe suivante coupe
+        assert!(!config.rotate_stream(false, 0, 1_000_000, 20_000_000));
+        assert!(config.rotate_stream(true, 0, 1_000_000, 20_000_000));
+        assert!(!config.rotate_stream(true, 0, 1_000_000, 5_000_000));
+        // pts antérieur au début du segment (horloge resynchronisée) : pas de coupure
+        assert!(!config.rotate_stream(true, 0, 20_000_000, 1_000_000));
+    }
+
+    #[test]
+    fn y4m_frame_size_counts_planar_output() {
+        // FileSink écrit du I420 quel que soit le format de sortie YUV
+        assert_eq!(y4m_frame_size(&frame_info(PixelFormat::I420)), 6 + 12);
+        assert_eq!(y4m_frame_size(&frame_info(PixelFormat::Yuyv)), 6 + 12);
+        assert_eq!(y4m_frame_size(&frame_info(PixelFormat::Grey)), 6 + 8);
+    }
+
+    #[test]
+    fn next_path_never_overwrites() {
+        let dir = TempDir::new("record-next");
+        let config = RecordConfig {
+            dir: dir.0.clone(),
+            ..RecordConfig::default()
+        };
+        let writer = SegmentWriter::new(&config, frame_info(PixelFormat::I420));
+        let first = writer.next_path("mp4");
+        assert_eq!(first, dir.0.join(format!("{}-001.mp4", writer.prefix)));
+
+        // Enregistrement précédent dans la même seconde
+        fs::write(&first, b"").unwrap();
+        fs::write(dir.0.join(format!("{}-002.mp4", writer.prefix)), b"").unwrap();
+        assert_eq!(writer.next_path("mp4"), dir.0.join(format!("{}-003.mp4", writer.prefix)));
+        assert_eq!(writer.next_path("y4m"), dir.0.join(format!("{}-001.y4m", writer.prefix)));
+    }
+
+    #[test]
+    fn y4m_segments_rotate_on_size() {
+        let dir = TempDir::new("record-y4m");
+        let info = frame_info(PixelFormat::I420);
+        let frame_size = y4m_frame_size(&info) as u64;
+        let config = RecordConfig {
+            dir: dir.0.clone(),
+            y4m: true,
+            max_bytes: Some(2 * frame_size),
+            ..RecordConfig::default()
+        };
+        let mut writer = SegmentWriter::new(&config, info);
+        for _ in 0..3 {
+            writer.frame(&[0u8; 12]);
+        }
+        writer.close();
+
+        let files = dir.files();
+        assert_eq!(files.len(), 2);
+        assert_eq!(writer.files, 2);
+        assert_eq!(writer.bytes, 3 * frame_size);
+        let sizes: Vec<u64> = files.iter().map(|f| fs::metadata(f).unwrap().len()).collect();
+        // Même en-tête, deux images puis une
+        assert_eq!(sizes[0] - sizes[1], frame_size);
+        assert!(writer.error.is_none());
+    }
+
+    #[test]
+    fn stream_waits_for_a_keyframe() {
+        let delta = [0, 0, 0, 1, 0x41, 0x9a];
+        let idr = [0, 0, 0, 1, 0x65, 0x88];
+        // Chemin invalide : l'ouverture échoue avant d'atteindre FFmpeg
+        let config = RecordConfig {
+            dir: PathBuf::from("/nonexistent\0dir"),
+            ..RecordConfig::default()
+        };
+        let mut writer = SegmentWriter::new(&config, frame_info(PixelFormat::I420));
+
+        // Sans config de codec, rien n'est enregistré
+        writer.packet(&idr, 0);
+        assert!(writer.error.is_none());
+
+        writer.configure(h264());
+        writer.packet(&delta, 1_000);
+        assert!(writer.waiting_keyframe);
+        assert!(writer.stream.is_none());
+        assert!(writer.error.is_none());
+
+        // La keyframe tente l'ouverture ; l'échec coupe l'enregistrement jusqu'à la config suivante
+        writer.packet(&idr, 2_000);
+        assert!(writer.error.is_some());
+        assert!(writer.codec.is_none());
+        assert_eq!((writer.files, writer.bytes), (0, 0));
+
+        writer.configure(h264());
+        assert!(writer.codec.is_some());
+        assert!(writer.waiting_keyframe);
+    }
+
+    #[test]
+    fn restart_keeps_every_worker_until_finish() {
+        let dir = TempDir::new("record-restart");
+        let config = RecordConfig {
+            dir: dir.0.clone(),
+            y4m: true,
+            ..RecordConfig::default()
+        };
+        let recorder = Recorder::new(config, frame_info(PixelFormat::I420));
+
+        for _ in 0..2 {
+            recorder.start().unwrap();
+            assert!(recorder.wants_frames());
+            recorder.on_frame(&[0u8; 12]);
+            recorder.on_configure(&h264());
+            assert!(!recorder.stop().recording);
+        }
+        assert!(!recorder.workers.lock().is_empty());
+        recorder.finish();
+        assert!(recorder.workers.lock().is_empty());
+
+        // Un fichier par enregistrement, tous fermés et complets
+        let files = dir.files();
+        assert_eq!(files.len(), 2);
+        let sizes: Vec<u64> = files.iter().map(|f| fs::metadata(f).unwrap().len()).collect();
+        assert_eq!(sizes[0], sizes[1]);
+        assert_eq!(recorder.status().files, 1);
+    }
+}
@@ -0,0 +1,147 @@
+use ffmpeg_next as ffmpeg;
+use std::ffi::CString;
+use std::path::Path;
+use std::ptr;
+use crate::pipeline::codec::CodecConfig;
+use crate::record::Container;
+
+// Horodatages fournis au muxer en µs (horloge serveur, relatifs au début du segment)
+const TIME_BASE_US: ffmpeg::ffi::AVRational = ffmpeg::ffi::AVRational { num: 1, den: 1_000_000 };
+
+// Remuxage du flux compressé reçu, sans réencodage
+pub struct Muxer {
+    ctx: *mut ffmpeg::ffi::AVFormatContext,
+    stream: *mut ffmpeg::ffi::AVStream,
+    last_dts: Option<i64>,
+    bytes: u64,
+}
+
+// Le muxer n'est utilisé que par le thread d'enregistrement
+unsafe impl Send for Muxer {}
+
+fn av_error(ret: i32) -> String {
+    let mut buf = [0 as libc::c_char; 128];
+    unsafe {
+        ffmpeg::ffi::av_strerror(ret, buf.as_mut_ptr(), buf.len());
+        std::ffi::CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+    }
+}
+
+impl Muxer {
+    // `extradata` : avcC/hvcC de WebCodecs, ou paramètres Annex-B extraits de la première keyframe
+    pub fn create(path: &Path, container: Container, config: &CodecConfig, extradata: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        ffmpeg::init()?;
+        let filename = CString::new(path.to_string_lossy().as_bytes())?;
+        let format_name = CString::new(container.format_name())?;
+
+        unsafe {
+            let mut ctx: *mut ffmpeg::ffi::AVFormatContext = ptr::null_mut();
+            let ret = ffmpeg::ffi::avformat_alloc_output_context2(&mut ctx, ptr::null(), format_name.as_ptr(), filename.as_ptr());
+            if ret < 0 || ctx.is_null() {
+                return Err(format!("muxer {} : {}", container.name(), av_error(ret)).into());
+            }
+            // Libéré par Drop, y compris sur les erreurs ci-dessous
+            let mut muxer = Self {
+                ctx,
+                stream: ptr::null_mut(),
+                last_dts: None,
+                bytes: 0,
+            };
+
+            let stream = ffmpeg::ffi::avformat_new_stream(ctx, ptr::null());
+            if stream.is_null() {
+                return Err("avformat_new_stream a échoué".into());
+            }
+            muxer.stream = stream;
+            (*stream).time_base = TIME_BASE_US;
+
+            let par = (*stream).codecpar;
+            (*par).codec_type = ffmpeg::ffi::AVMediaType::AVMEDIA_TYPE_VIDEO;
+            (*par).codec_id = config.codec.av_codec_id();
+            (*par).width = config.width as i32;
+            (*par).height = config.height as i32;
+            if !extradata.is_empty() {
+                let padding = ffmpeg::ffi::AV_INPUT_BUFFER_PADDING_SIZE as usize;
+                let buf = ffmpeg::ffi::av_mallocz(extradata.len() + padding) as *mut u8;
+                if buf.is_null() {
+                    return Err("allocation de l'extradata impossible".into());
+                }
+                ptr::copy_nonoverlapping(extradata.as_ptr(), buf, extradata.len());
+                (*par).extradata = buf;
+                (*par).extradata_size = extradata.len() as i32;
+            }
+
+            let ret = ffmpeg::ffi::avio_open(&mut (*ctx).pb, filename.as_ptr(), ffmpeg::ffi::AVIO_FLAG_WRITE);
+            if ret < 0 {
+                return Err(format!("ouverture de {} : {}", path.display(), av_error(ret)).into());
+            }
+
+            // MP4 fragmenté : le fichier reste lisible même si le processus est interrompu
+            let mut options: *mut ffmpeg::ffi::AVDictionary = ptr::null_mut();
+            if container == Container::Mp4 {
+                let key = CString::new("movflags")?;
+                let value = CString::new("frag_keyframe+empty_moov+default_base_moof")?;
+                ffmpeg::ffi::av_dict_set(&mut options, key.as_ptr(), value.as_ptr(), 0);
+            }
+            let ret = ffmpeg::ffi::avformat_write_header(ctx, &mut options);
+            ffmpeg::ffi::av_dict_free(&mut options);
+            if ret < 0 {
+                // Pas d'en-tête écrit : pas de trailer non plus
+                ffmpeg::ffi::avio_closep(&mut (*ctx).pb);
+                return Err(format!("en-tête {} ({}) : {}", container.name(), config.codec.name(), av_error(ret)).into());
+            }
+
+            Ok(muxer)
+        }
+    }
+
+    pub fn write(&mut self, data: &[u8], pts_us: i64, keyframe: bool) -> Result<(), Box<dyn std::error::Error>> {
+        unsafe {
+            let time_base = (*self.stream).time_base;
+            let mut ts = ffmpeg::ffi::av_rescale_q(pts_us, TIME_BASE_US, time_base);
+            // Horodatages strictement croissants (gigue réseau, horloge recalée)
+            if let Some(last) = self.last_dts {
+                ts = ts.max(last + 1);
+            }
+
+            let mut packet = ffmpeg::ffi::av_packet_alloc();
+            if packet.is_null() || ffmpeg::ffi::av_new_packet(packet, data.len() as i32) < 0 {
+                ffmpeg::ffi::av_packet_free(&mut packet);
+                return Err("allocation du paquet impossible".into());
+            }
+            ptr::copy_nonoverlapping(data.as_ptr(), (*packet).data, data.len());
+            (*packet).pts = ts;
+            (*packet).dts = ts;
+            (*packet).stream_index = (*self.stream).index;
+            if keyframe {
+                (*packet).flags |= ffmpeg::ffi::AV_PKT_FLAG_KEY;
+            }
+
+            let ret = ffmpeg::ffi::av_interleaved_write_frame(self.ctx, packet);
+            ffmpeg::ffi::av_packet_free(&mut packet);
+            if ret < 0 {
+                return Err(av_error(ret).into());
+            }
+            self.last_dts = Some(ts);
+            self.bytes += data.len() as u64;
+            Ok(())
+        }
+    }
+
+    // Volume de flux écrit dans ce fichier (hors en-têtes du conteneur)
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for Muxer {
+    fn drop(&mut self) {
+        unsafe {
+            if !(*self.ctx).pb.is_null() {
+                ffmpeg::ffi::av_write_trailer(self.ctx);
+                ffmpeg::ffi::avio_closep(&mut (*self.ctx).pb);
+            }
+            ffmpeg::ffi::avformat_free_context(self.ctx);
+        }
+    }
+}
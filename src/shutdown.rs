@@ -0,0 +1,86 @@
+// Arrêt coordonné : SIGINT/SIGTERM annulent un jeton partagé par toutes les tâches (relais UDP,
+// serveur web, sessions WebSocket), puis main vide la pipeline et libère les périphériques dans
+// l'ordre. SIGHUP relit la configuration. Un second SIGINT/SIGTERM coupe court.
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFuture};
+use tokio_util::task::task_tracker::TrackedFuture;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+use crate::config::{self, Config, ConfigSource};
+
+// Échange des trames Close avec le client
+pub const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+// Fin des sessions WebSocket après l'envoi des trames Close
+pub const SESSIONS_TIMEOUT: Duration = Duration::from_secs(3);
+// Vidage des files, fermeture de la sortie et finalisation de l'enregistrement
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Code de sortie d'un arrêt forcé (128 + SIGINT, comme un shell)
+const FORCED_EXIT_CODE: i32 = 130;
+
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    // Tâches détachées par axum (WebSockets après upgrade) : le serveur ne les attend pas
+    tasks: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // Se termine quand l'arrêt est demandé (branche de select!)
+    pub fn requested(&self) -> WaitForCancellationFuture<'_> {
+        self.token.cancelled()
+    }
+
+    pub fn track<F: Future>(&self, task: F) -> TrackedFuture<F> {
+        self.tasks.track_future(task)
+    }
+
+    // false : des tâches suivies tournaient encore à l'échéance
+    pub async fn wait_tasks(&self, timeout: Duration) -> bool {
+        self.tasks.close();
+        tokio::time::timeout(timeout, self.tasks.wait()).await.is_ok()
+    }
+}
+
+pub fn spawn_signals(shutdown: Shutdown, source: Arc<ConfigSource>, config: watch::Sender<Arc<Config>>) -> std::io::Result<()> {
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    tokio::spawn(async move {
+        loop {
+            let name = tokio::select! {
+                _ = interrupt.recv() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+                _ = hangup.recv() => {
+                    info!(path = %source.path().display(), "SIGHUP : relecture de la configuration");
+                    config::reload(&source, &config);
+                    continue;
+                }
+            };
+            if shutdown.is_requested() {
+                warn!(signal = name, "second signal : arrêt immédiat");
+                std::process::exit(FORCED_EXIT_CODE);
+            }
+            info!(signal = name, "arrêt demandé");
+            shutdown.trigger();
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use crate::codec::convert::{self, ColorRange, Image, PixelFormat};
+use crate::sink::{FrameInfo, FrameSink, SinkError};
+
+// Enregistrement brut (.yuv) ou YUV4MPEG2 (.y4m) des images de sortie
+pub struct FileSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    info: FrameInfo,
+    y4m: bool,
+    // Y4M ne connaît que les formats planaires : conversion vers I420 si besoin
+    planar: Vec<u8>,
+    frames: u64,
+}
+
+impl FileSink {
+    pub fn create(path: &Path, info: &FrameInfo) -> Result<Self, SinkError> {
+        let y4m = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("y4m"));
+        if y4m && !info.format.is_yuv() {
+            return Err(SinkError::UnsupportedFormat { sink: "y4m", format: info.format });
+        }
+
+        let mut writer = BufWriter::with_capacity(info.frame_size() + 64, File::create(path)?);
+        if y4m {
+            writer.write_all(y4m_header(info).as_bytes())?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer,
+            info: *info,
+            y4m,
+            planar: Vec::new(),
+            frames: 0,
+        })
+    }
+
+    fn write_y4m(&mut self, data: &[u8]) -> Result<(), SinkError> {
+        let info = &self.info;
+        let planar = if matches!(info.format, PixelFormat::I420 | PixelFormat::Grey) {
+            data
+        } else {
+            // Conversion avant l'en-tête FRAME : une erreur ne laisse pas d'image tronquée
+            let image = Image::packed(info.format, data, info.width, info.height)?;
+            self.planar.resize(PixelFormat::I420.frame_size(info.width, info.height), 0);
+            convert::convert(&image, &mut self.planar, PixelFormat::I420, info.colorimetry)?;
+            &self.planar
+        };
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(planar)?;
+        Ok(())
+    }
+}
+
+fn y4m_header(info: &FrameInfo) -> String {
+    let chroma = if info.format == PixelFormat::Grey { "mono" } else { "420jpeg" };
+    let range = match info.colorimetry.range {
+        ColorRange::Limited => "LIMITED",
+        ColorRange::Full => "FULL",
+    };
+    format!(
+        "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C{} XCOLORRANGE={}\n",
+        info.width, info.height, info.fps, chroma, range
+    )
+}
+
+impl FrameSink for FileSink {
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), SinkError> {
+        self.info.check(data)?;
+        if self.y4m {
+            self.write_y4m(data)?;
+        } else {
+            self.writer.write_all(data)?;
+        }
+        // Fichier lisible pendant l'enregistrement (et complet si le processus est tué)
+        self.writer.flush()?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let kind = if self.y4m { "y4m".to_string() } else { format!("{} brut", self.info.format) };
+        format!("{} ({}, {}x{})", self.path.display(), kind, self.info.width, self.info.height)
+    }
+}
+
+impl Drop for FileSink {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+        info!(path = %self.path.display(), frames = self.frames, "fichier de sortie fermé");
+    }
+}
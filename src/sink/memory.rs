@@ -0,0 +1,147 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::{Condvar, Mutex};
+use crate::sink::{FrameInfo, FrameSink, SinkError};
+
+#[derive(Debug, Clone)]
+pub struct CapturedFrame {
+    // Rang de l'image depuis la création du sink (les plus anciennes peuvent avoir été évincées)
+    pub index: u64,
+    pub data: Vec<u8>,
+    pub at: Instant,
+}
+
+struct Shared {
+    frames: Mutex<Captured>,
+    added: Condvar,
+}
+
+struct Captured {
+    frames: VecDeque<CapturedFrame>,
+    total: u64,
+    // Format des images, aussi lu par le handle
+    info: FrameInfo,
+}
+
+// Garde en mémoire les dernières images écrites : pour les tests d'intégration de la pipeline
+pub struct MemorySink {
+    shared: Arc<Shared>,
+    capacity: usize,
+}
+
+// Côté test : lecture des images capturées depuis un autre thread
+#[derive(Clone)]
+pub struct CaptureHandle {
+    shared: Arc<Shared>,
+}
+
+impl MemorySink {
+    pub fn new(info: &FrameInfo, capacity: usize) -> (Self, CaptureHandle) {
+        let shared = Arc::new(Shared {
+            frames: Mutex::new(Captured {
+                frames: VecDeque::with_capacity(capacity),
+                total: 0,
+                info: *info,
+            }),
+            added: Condvar::new(),
+        });
+        let handle = CaptureHandle { shared: shared.clone() };
+        let sink = Self {
+            shared,
+            capacity: capacity.max(1),
+        };
+        (sink, handle)
+    }
+}
+
+impl FrameSink for MemorySink {
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), SinkError> {
+        let mut captured = self.shared.frames.lock();
+        captured.info.check(data)?;
+        if captured.frames.len() == self.capacity {
+            captured.frames.pop_front();
+        }
+        let index = captured.total;
+        captured.total += 1;
+        captured.frames.push_back(CapturedFrame {
+            index,
+            data: data.to_vec(),
+            at: Instant::now(),
+        });
+        drop(captured);
+        self.shared.added.notify_all();
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        let info = self.shared.frames.lock().info;
+        format!("mémoire ({} {}x{}, {} images max)", info.format, info.width, info.height, self.capacity)
+    }
+}
+
+impl CaptureHandle {
+    // Format et résolution du sink
+    pub fn info(&self) -> FrameInfo {
+        self.shared.frames.lock().info
+    }
+
+    // Nombre total d'images écrites
+    pub fn count(&self) -> u64 {
+        self.shared.frames.lock().total
+    }
+
+    pub fn last(&self) -> Option<CapturedFrame> {
+        self.shared.frames.lock().frames.back().cloned()
+    }
+
+    pub fn take(&self) -> Vec<CapturedFrame> {
+        self.shared.frames.lock().frames.drain(..).collect()
+    }
+
+    // Attend qu'au moins `count` images aient été écrites ; false à l'échéance
+    pub fn wait_for(&self, count: u64, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut captured = self.shared.frames.lock();
+        while captured.total < count {
+            if self.shared.added.wait_until(&mut captured, deadline).timed_out() {
+                return captured.total >= count;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::convert::{Colorimetry, PixelFormat};
+
+    fn info(width: usize, height: usize) -> FrameInfo {
+        FrameInfo {
+            format: PixelFormat::Yuyv,
+            width,
+            height,
+            fps: 30,
+            colorimetry: Colorimetry::guess(height),
+        }
+    }
+
+    #[test]
+    fn keeps_the_latest_frames_and_rejects_other_sizes() {
+        let (mut sink, capture) = MemorySink::new(&info(2, 2), 2);
+        assert_eq!((capture.info().width, capture.info().height), (2, 2));
+        sink.write_frame(&[0; 8]).unwrap();
+        // Image 4x2 sur une sortie 2x2 : refusée, non comptée
+        assert!(matches!(sink.write_frame(&[0; 16]), Err(SinkError::FrameSize { expected: 8, got: 16 })));
+
+        sink.write_frame(&[1; 8]).unwrap();
+        sink.write_frame(&[2; 8]).unwrap();
+        assert_eq!(capture.last().map(|f| f.index), Some(2));
+        // Capacité de 2 : la première image est évincée, les rangs restent ceux d'écriture
+        let frames = capture.take();
+        assert_eq!(frames.iter().map(|f| f.index).collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(frames[1].data, [2; 8]);
+        assert_eq!(capture.count(), 3);
+    }
+}
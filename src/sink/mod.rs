@@ -0,0 +1,151 @@
+pub mod file;
+#[cfg(test)]
+pub mod memory;
+pub mod pipe;
+pub mod v4l2;
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::info;
+use crate::codec::convert::{Colorimetry, ConvertError, PixelFormat};
+use crate::v4l2::device::IoMode;
+use crate::v4l2::error::V4l2Error;
+use crate::v4l2::readers::ReaderMonitor;
+
+// Description des images reçues par un sink (format et résolution de sortie de la pipeline)
+#[derive(Debug, Clone, Copy)]
+pub struct FrameInfo {
+    pub format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    pub fps: u32,
+    pub colorimetry: Colorimetry,
+}
+
+impl FrameInfo {
+    pub fn frame_size(&self) -> usize {
+        self.format.frame_size(self.width, self.height)
+    }
+
+    // Refus des images tronquées ou d'une autre résolution, plutôt qu'une sortie corrompue
+    pub fn check(&self, data: &[u8]) -> Result<(), SinkError> {
+        let expected = self.frame_size();
+        if data.len() != expected {
+            return Err(SinkError::FrameSize { expected, got: data.len() });
+        }
+        Ok(())
+    }
+}
+
+// Destination des images converties et cadencées par l'étage output
+pub trait FrameSink: Send {
+    // Image compacte au format de FrameInfo
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), SinkError>;
+
+    // Pour les logs : "/dev/video10", "capture.y4m", "stdout"…
+    fn describe(&self) -> String;
+
+    // Lecteurs de la sortie, quand le sink sait les compter (V4L2) ; None : toujours consommée
+    fn readers(&self) -> Option<Arc<ReaderMonitor>> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub enum SinkError {
+    // Le lecteur ne suit pas : l'image est perdue, sans autre conséquence
+    Busy,
+    V4l2(V4l2Error),
+    Io(io::Error),
+    Convert(ConvertError),
+    UnsupportedFormat { sink: &'static str, format: PixelFormat },
+    // Taille d'image différente de celle attendue par la sortie
+    FrameSize { expected: usize, got: usize },
+}
+
+impl fmt::Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::Busy => write!(f, "sortie occupée, image ignorée"),
+            SinkError::V4l2(e) => write!(f, "V4L2 : {}", e),
+            SinkError::Io(e) => write!(f, "E/S : {}", e),
+            SinkError::Convert(e) => write!(f, "conversion : {}", e),
+            SinkError::UnsupportedFormat { sink, format } => write!(f, "format {} non géré par la sortie {}", format, sink),
+            SinkError::FrameSize { expected, got } => write!(f, "image de {} octets refusée, {} attendus", got, expected),
+        }
+    }
+}
+
+impl std::error::Error for SinkError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SinkError::V4l2(e) => Some(e),
+            SinkError::Io(e) => Some(e),
+            SinkError::Convert(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<V4l2Error> for SinkError {
+    fn from(e: V4l2Error) -> Self {
+        match e {
+            V4l2Error::Busy => SinkError::Busy,
+            V4l2Error::FrameSize { expected, got } => SinkError::FrameSize { expected, got },
+            e => SinkError::V4l2(e),
+        }
+    }
+}
+
+impl From<ConvertError> for SinkError {
+    fn from(e: ConvertError) -> Self {
+        SinkError::Convert(e)
+    }
+}
+
+impl From<io::Error> for SinkError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::WouldBlock {
+            SinkError::Busy
+        } else {
+            SinkError::Io(e)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum SinkConfig {
+    // /dev/videoN (v4l2loopback), cf. PipelineConfig::video_nr / io
+    #[default]
+    V4l2,
+    // .y4m : en-tête YUV4MPEG2 (lisible par ffplay/mpv) ; sinon images brutes (.yuv)
+    File(PathBuf),
+    // Images brutes sur stdout, pour `| ffmpeg -f rawvideo ...` ou gst fdsrc
+    Stdout,
+}
+
+impl SinkConfig {
+    // "v4l2", "stdout" (ou "-") ou "file:capture.y4m"
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "v4l2" => Some(SinkConfig::V4l2),
+            "stdout" | "-" => Some(SinkConfig::Stdout),
+            _ => value
+                .strip_prefix("file:")
+                .filter(|path| !path.is_empty())
+                .map(|path| SinkConfig::File(PathBuf::from(path))),
+        }
+    }
+
+    pub fn open(&self, info: &FrameInfo, video_nr: u16, io: IoMode) -> Result<Box<dyn FrameSink>, SinkError> {
+        let sink: Box<dyn FrameSink> = match self {
+            SinkConfig::V4l2 => Box::new(v4l2::V4l2Sink::open(video_nr, io, info)?),
+            SinkConfig::File(path) => Box::new(file::FileSink::create(path, info)?),
+            SinkConfig::Stdout => Box::new(pipe::PipeSink::stdout(info)?),
+        };
+        info!(sink = %sink.describe(), "sortie ouverte");
+        Ok(sink)
+    }
+}
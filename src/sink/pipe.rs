@@ -0,0 +1,39 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+use crate::sink::{FrameInfo, FrameSink, SinkError};
+
+// Images brutes vers un tube : `phonecam --sink stdout | ffmpeg -f rawvideo -pix_fmt yuyv422 -s 1280x720 -i - ...`
+pub struct PipeSink {
+    out: File,
+    info: FrameInfo,
+}
+
+impl PipeSink {
+    // Les logs sont déjà sur stderr ; stdout y est aussi redirigé pour qu'une écriture parasite
+    // (bibliothèque, QR code) ne se mêle pas aux images
+    pub fn stdout(info: &FrameInfo) -> Result<Self, SinkError> {
+        // SAFETY : dup/dup2 sur les descripteurs standards, le nouveau fd appartient au File
+        let out = unsafe {
+            let fd = libc::dup(libc::STDOUT_FILENO);
+            if fd < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            File::from_raw_fd(fd)
+        };
+        Ok(Self { out, info: *info })
+    }
+}
+
+impl FrameSink for PipeSink {
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), SinkError> {
+        self.info.check(data)?;
+        // Bloquant : c'est le lecteur du tube qui impose son rythme
+        self.out.write_all(data)?;
+        Ok(())
+    }
+
+    fn describe(&self) -> String {
+        format!("stdout ({} {}x{} brut)", self.info.format, self.info.width, self.info.height)
+    }
+}
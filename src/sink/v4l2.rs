@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use crate::sink::{FrameInfo, FrameSink, SinkError};
+use crate::v4l2::device::{Device, DeviceConfig, IoMode};
+use crate::v4l2::readers::ReaderMonitor;
+
+pub struct V4l2Sink {
+    device: Device,
+    readers: Arc<ReaderMonitor>,
+}
+
+impl V4l2Sink {
+    pub fn open(video_nr: u16, io: IoMode, info: &FrameInfo) -> Result<Self, SinkError> {
+        let config = DeviceConfig {
+            format: info.format,
+            width: info.width,
+            height: info.height,
+            colorimetry: info.colorimetry,
+            io,
+        };
+        let device = Device::open(video_nr, &config)?;
+        let readers = ReaderMonitor::spawn(device.path());
+        Ok(Self { device, readers })
+    }
+}
+
+impl FrameSink for V4l2Sink {
+    fn write_frame(&mut self, data: &[u8]) -> Result<(), SinkError> {
+        Ok(self.device.write_frame(data)?)
+    }
+
+    fn describe(&self) -> String {
+        format!("{} ({})", self.device.path(), self.device.card())
+    }
+
+    fn readers(&self) -> Option<Arc<ReaderMonitor>> {
+        Some(self.readers.clone())
+    }
+}
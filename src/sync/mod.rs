@@ -1,2 +1,21 @@
 pub mod spsc;
 pub mod mpmc;
+mod primitives;
+mod wait;
+
+// Erreur d'envoi non bloquant : l'élément est rendu à l'appelant
+#[derive(Debug, PartialEq, Eq)]
+pub enum PushError<T> {
+    // File pleine (backpressure)
+    Full(T),
+    // Plus aucun consommateur
+    Closed(T),
+}
+
+impl<T> PushError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            PushError::Full(value) | PushError::Closed(value) => value,
+        }
+    }
+}
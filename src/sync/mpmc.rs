@@ -0,0 +1,522 @@
+use std::mem::MaybeUninit;
+use crossbeam::utils::CachePadded;
+use crate::sync::primitives::{Arc, AtomicBool, AtomicUsize, Ordering, UnsafeCell};
+use crate::sync::wait::WaitCell;
+use crate::sync::PushError;
+
+// File bornée multi-producteurs / multi-consommateurs (algorithme de D. Vyukov) :
+// chaque case porte un numéro de séquence qui indique si elle est libre ou pleine
+// pour le tour courant, un seul CAS par opération.
+struct Slot<T> {
+    seq: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Queue<T> {
+    slots: Box<[Slot<T>]>,
+    mask: usize,
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+    senders: AtomicUsize,
+    receivers: AtomicUsize,
+    // Fermeture explicite (Sender::close) : équivaut au départ de tous les producteurs
+    closed: AtomicBool,
+    not_empty: WaitCell,
+    not_full: WaitCell,
+}
+
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    fn capacity(&self) -> usize {
+        self.mask + 1
+    }
+
+    fn len(&self) -> usize {
+        let enqueue = self.enqueue_pos.load(Ordering::Relaxed);
+        let dequeue = self.dequeue_pos.load(Ordering::Relaxed);
+        enqueue.wrapping_sub(dequeue).min(self.capacity())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.senders.load(Ordering::Acquire) == 0 || self.closed.load(Ordering::Acquire)
+    }
+
+    fn try_push(&self, value: T) -> Result<(), PushError<T>> {
+        if self.receivers.load(Ordering::Acquire) == 0 || self.closed.load(Ordering::Acquire) {
+            return Err(PushError::Closed(value));
+        }
+
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos) as isize;
+
+            if diff == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        slot.value.with_mut(|cell| unsafe { (*cell).write(value) });
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        self.not_empty.wake();
+                        return Ok(());
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                return Err(PushError::Full(value));
+            } else {
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = seq.wrapping_sub(pos.wrapping_add(1)) as isize;
+
+            if diff == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        let value = slot.value.with_mut(|cell| unsafe { (*cell).assume_init_read() });
+                        slot.seq.store(pos.wrapping_add(self.capacity()), Ordering::Release);
+                        self.not_full.wake();
+                        return Some(value);
+                    }
+                    Err(current) => pos = current,
+                }
+            } else if diff < 0 {
+                return None;
+            } else {
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn has_item(&self) -> bool {
+        let pos = self.dequeue_pos.load(Ordering::Relaxed);
+        let seq = self.slots[pos & self.mask].seq.load(Ordering::Acquire);
+        seq == pos.wrapping_add(1) || self.is_closed()
+    }
+
+    fn has_space(&self) -> bool {
+        let pos = self.enqueue_pos.load(Ordering::Relaxed);
+        let seq = self.slots[pos & self.mask].seq.load(Ordering::Acquire);
+        seq == pos || self.receivers.load(Ordering::Acquire) == 0 || self.closed.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        while self.try_pop().is_some() {}
+    }
+}
+
+// La capacité est arrondie à la puissance de deux supérieure (minimum 2)
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let capacity = capacity.max(2).next_power_of_two();
+    let slots = (0..capacity)
+        .map(|i| Slot {
+            seq: AtomicUsize::new(i),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        })
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+
+    let queue = Arc::new(Queue {
+        slots,
+        mask: capacity - 1,
+        enqueue_pos: CachePadded::new(AtomicUsize::new(0)),
+        dequeue_pos: CachePadded::new(AtomicUsize::new(0)),
+        senders: AtomicUsize::new(1),
+        receivers: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        not_empty: WaitCell::new(),
+        not_full: WaitCell::new(),
+    });
+
+    (Sender { queue: queue.clone() }, Receiver { queue })
+}
+
+pub struct Sender<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Sender<T> {
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.queue.receivers.load(Ordering::Acquire) == 0 || self.queue.closed.load(Ordering::Acquire)
+    }
+
+    pub fn try_push(&self, value: T) -> Result<(), PushError<T>> {
+        self.queue.try_push(value)
+    }
+
+    // Ferme la file pour tous les producteurs, clones compris : les envois suivants échouent
+    // (Closed) et les consommateurs se terminent une fois la file vidée
+    pub fn close(&self) {
+        self.queue.closed.store(true, Ordering::Release);
+        self.queue.not_empty.wake_all();
+        self.queue.not_full.wake_all();
+    }
+
+    // Pousse depuis le début de `items` jusqu'à la première case pleine ; renvoie le nombre envoyé
+    pub fn push_batch(&self, items: &mut Vec<T>) -> usize {
+        let mut sent = 0;
+        let mut pending = std::mem::take(items).into_iter();
+        while let Some(value) = pending.next() {
+            if let Err(err) = self.queue.try_push(value) {
+                *items = std::iter::once(err.into_inner()).chain(pending).collect();
+                break;
+            }
+            sent += 1;
+        }
+        sent
+    }
+
+    pub fn push_blocking(&self, mut value: T) -> Result<(), T> {
+        loop {
+            match self.queue.try_push(value) {
+                Ok(()) => return Ok(()),
+                Err(PushError::Closed(v)) => return Err(v),
+                Err(PushError::Full(v)) => {
+                    value = v;
+                    self.queue.not_full.wait_blocking(|| self.queue.has_space());
+                }
+            }
+        }
+    }
+
+    pub async fn push_async(&self, mut value: T) -> Result<(), T> {
+        loop {
+            match self.queue.try_push(value) {
+                Ok(()) => return Ok(()),
+                Err(PushError::Closed(v)) => return Err(v),
+                Err(PushError::Full(v)) => {
+                    value = v;
+                    self.queue.not_full.wait_async(|| self.queue.has_space()).await;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.queue.senders.fetch_add(1, Ordering::Relaxed);
+        Self { queue: self.queue.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.queue.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.queue.not_empty.wake_all();
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Vrai quand tous les producteurs ont disparu ou que la file a été fermée
+    // (des éléments peuvent encore rester à lire)
+    pub fn is_closed(&self) -> bool {
+        self.queue.is_closed()
+    }
+
+    pub fn try_pop(&self) -> Option<T> {
+        self.queue.try_pop()
+    }
+
+    pub fn pop_batch(&self, out: &mut Vec<T>, max: usize) -> usize {
+        let mut count = 0;
+        while count < max {
+            match self.queue.try_pop() {
+                Some(value) => {
+                    out.push(value);
+                    count += 1;
+                }
+                None => break,
+            }
+        }
+        count
+    }
+
+    // None uniquement quand tous les producteurs sont partis et que la file est vide
+    pub fn pop_blocking(&self) -> Option<T> {
+        loop {
+            if let Some(value) = self.queue.try_pop() {
+                return Some(value);
+            }
+            if self.is_closed() {
+                return self.queue.try_pop();
+            }
+            self.queue.not_empty.wait_blocking(|| self.queue.has_item());
+        }
+    }
+
+    pub async fn pop_async(&self) -> Option<T> {
+        loop {
+            if let Some(value) = self.queue.try_pop() {
+                return Some(value);
+            }
+            if self.is_closed() {
+                return self.queue.try_pop();
+            }
+            self.queue.not_empty.wait_async(|| self.queue.has_item()).await;
+        }
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.queue.receivers.fetch_add(1, Ordering::Relaxed);
+        Self { queue: self.queue.clone() }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        if self.queue.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.queue.not_full.wake_all();
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use loom::thread;
+    use super::*;
+    use crate::sync::primitives::model;
+
+    // Deux producteurs se disputent enqueue_pos : chaque valeur est publiée une seule fois
+    #[test]
+    fn concurrent_producers() {
+        model(|| {
+            let (tx, rx) = bounded(2);
+            let other = tx.clone();
+            let producer = thread::spawn(move || other.push_blocking(1).unwrap());
+            tx.push_blocking(2).unwrap();
+            let mut got = [rx.pop_blocking().unwrap(), rx.pop_blocking().unwrap()];
+            got.sort();
+            assert_eq!(got, [1, 2]);
+            producer.join().unwrap();
+        });
+    }
+
+    // Deux consommateurs se disputent dequeue_pos : aucun élément lu deux fois
+    #[test]
+    fn concurrent_consumers() {
+        model(|| {
+            let (tx, rx) = bounded(2);
+            tx.try_push(1).unwrap();
+            tx.try_push(2).unwrap();
+            let other = rx.clone();
+            let consumer = thread::spawn(move || other.try_pop());
+            let mine = rx.try_pop();
+            let theirs = consumer.join().unwrap();
+            let mut got = [mine.unwrap(), theirs.unwrap()];
+            got.sort();
+            assert_eq!(got, [1, 2]);
+        });
+    }
+
+    // Trois éléments dans deux cases : le numéro de séquence doit distinguer les tours
+    #[test]
+    fn sequence_wraps_around() {
+        model(|| {
+            let (tx, rx) = bounded(2);
+            let producer = thread::spawn(move || {
+                for i in 0..3 {
+                    tx.push_blocking(i).unwrap();
+                }
+            });
+            for i in 0..3 {
+                assert_eq!(rx.pop_blocking(), Some(i));
+            }
+            assert_eq!(rx.pop_blocking(), None);
+            producer.join().unwrap();
+        });
+    }
+
+    // Sender::close réveille le consommateur endormi, qui vide la file avant de voir la fermeture
+    #[test]
+    fn close_drains_then_ends() {
+        model(|| {
+            let (tx, rx) = bounded(2);
+            tx.try_push(0).unwrap();
+            let closer = thread::spawn(move || tx.close());
+            assert_eq!(rx.pop_blocking(), Some(0));
+            assert_eq!(rx.pop_blocking(), None);
+            closer.join().unwrap();
+        });
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn capacity_rounds_up_to_power_of_two() {
+        for (requested, expected) in [(0, 2), (1, 2), (3, 4), (8, 8), (9, 16)] {
+            let (tx, rx) = bounded::<u8>(requested);
+            assert_eq!((tx.capacity(), rx.capacity()), (expected, expected), "{}", requested);
+        }
+    }
+
+    #[test]
+    fn full_then_closed() {
+        let (tx, rx) = bounded(2);
+        tx.try_push(0).unwrap();
+        tx.try_push(1).unwrap();
+        assert_eq!(tx.try_push(2), Err(PushError::Full(2)));
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.try_pop(), Some(0));
+        tx.try_push(2).unwrap();
+
+        // Un récepteur cloné garde la file ouverte
+        let other = rx.clone();
+        drop(rx);
+        assert!(!tx.is_closed());
+        assert_eq!(other.try_pop(), Some(1));
+        drop(other);
+        assert!(tx.is_closed());
+        assert_eq!(tx.try_push(3), Err(PushError::Closed(3)));
+    }
+
+    #[test]
+    fn explicit_close_drains_then_ends() {
+        let (tx, rx) = bounded(4);
+        let other = tx.clone();
+        tx.try_push(0).unwrap();
+        tx.close();
+        // Fermeture partagée par tous les clones
+        assert_eq!(other.try_push(1), Err(PushError::Closed(1)));
+        assert!(rx.is_closed());
+        assert_eq!(rx.pop_blocking(), Some(0));
+        assert_eq!(rx.pop_blocking(), None);
+    }
+
+    #[test]
+    fn batches_stop_at_the_first_full_slot() {
+        let (tx, rx) = bounded(4);
+        let mut items = (0..6).collect::<Vec<_>>();
+        assert_eq!(tx.push_batch(&mut items), 4);
+        assert_eq!(items, [4, 5]);
+
+        let mut out = Vec::new();
+        assert_eq!(rx.pop_batch(&mut out, 3), 3);
+        assert_eq!(tx.push_batch(&mut items), 2);
+        assert!(items.is_empty());
+        assert_eq!(rx.pop_batch(&mut out, 10), 3);
+        assert_eq!(rx.pop_batch(&mut out, 10), 0);
+        assert_eq!(out, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn every_item_delivered_once_across_threads() {
+        let (tx, rx) = bounded(8);
+        let producers = (0..4)
+            .map(|p| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..1000 {
+                        tx.push_blocking(p * 1000 + i).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(tx);
+        let consumers = (0..3)
+            .map(|_| {
+                let rx = rx.clone();
+                thread::spawn(move || std::iter::from_fn(|| rx.pop_blocking()).collect::<Vec<_>>())
+            })
+            .collect::<Vec<_>>();
+        drop(rx);
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        let mut received = consumers.into_iter().flat_map(|c| c.join().unwrap()).collect::<Vec<_>>();
+        received.sort_unstable();
+        assert_eq!(received, (0..4000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn close_wakes_blocked_sides() {
+        let (tx, rx) = bounded::<u32>(2);
+        let consumer = thread::spawn(move || rx.pop_blocking());
+        thread::sleep(Duration::from_millis(20));
+        drop(tx);
+        assert_eq!(consumer.join().unwrap(), None);
+
+        let (tx, rx) = bounded(2);
+        tx.try_push(0).unwrap();
+        tx.try_push(1).unwrap();
+        let producer = thread::spawn(move || tx.push_blocking(2));
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(producer.join().unwrap(), Err(2));
+    }
+
+    #[tokio::test]
+    async fn async_sides_are_woken() {
+        let (tx, rx) = bounded(2);
+        let consumer = tokio::spawn(async move {
+            let mut out = Vec::new();
+            while let Some(value) = rx.pop_async().await {
+                out.push(value);
+            }
+            out
+        });
+        for i in 0..100 {
+            tx.push_async(i).await.unwrap();
+        }
+        drop(tx);
+        assert_eq!(consumer.await.unwrap(), (0..100).collect::<Vec<_>>());
+    }
+}
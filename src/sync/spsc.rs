@@ -0,0 +1,492 @@
+use std::mem::MaybeUninit;
+use std::time::Instant;
+use crossbeam::utils::CachePadded;
+use crate::sync::primitives::{Arc, AtomicBool, AtomicUsize, Ordering, UnsafeCell};
+use crate::sync::wait::WaitCell;
+use crate::sync::PushError;
+
+// Ring buffer à un producteur et un consommateur, sans verrou sur le chemin rapide : try_push et
+// try_pop ne prennent un verrou que pour réveiller un thread endormi de l'autre côté.
+// Chaque côté garde une copie locale de l'index adverse et ne relit l'atomique
+// que lorsque la file lui semble pleine (ou vide) : pas de ping-pong de cache line.
+struct Ring<T> {
+    buffer: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    mask: usize,
+    // Index de lecture, écrit uniquement par le consommateur
+    head: CachePadded<AtomicUsize>,
+    // Index d'écriture, écrit uniquement par le producteur
+    tail: CachePadded<AtomicUsize>,
+    closed: AtomicBool,
+    not_empty: WaitCell,
+    not_full: WaitCell,
+}
+
+unsafe impl<T: Send> Send for Ring<T> {}
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+impl<T> Ring<T> {
+    fn capacity(&self) -> usize {
+        self.mask + 1
+    }
+
+    fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.not_empty.wake_all();
+        self.not_full.wake_all();
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Relaxed);
+        let mut i = head;
+        while i != tail {
+            self.buffer[i & self.mask].with_mut(|cell| unsafe { (*cell).assume_init_drop() });
+            i = i.wrapping_add(1);
+        }
+    }
+}
+
+// La capacité est arrondie à la puissance de deux supérieure
+pub fn channel<T>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let capacity = capacity.max(1).next_power_of_two();
+    let buffer = (0..capacity)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+
+    let ring = Arc::new(Ring {
+        buffer,
+        mask: capacity - 1,
+        head: CachePadded::new(AtomicUsize::new(0)),
+        tail: CachePadded::new(AtomicUsize::new(0)),
+        closed: AtomicBool::new(false),
+        not_empty: WaitCell::new(),
+        not_full: WaitCell::new(),
+    });
+
+    (
+        Producer { ring: ring.clone(), tail: 0, cached_head: 0 },
+        Consumer { ring, head: 0, cached_tail: 0 },
+    )
+}
+
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+    tail: usize,
+    cached_head: usize,
+}
+
+impl<T> Producer<T> {
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    // Nombre approximatif d'éléments en attente
+    pub fn len(&self) -> usize {
+        self.tail.wrapping_sub(self.ring.head.load(Ordering::Relaxed))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ring.closed.load(Ordering::Acquire)
+    }
+
+    fn free_slots(&mut self) -> usize {
+        let capacity = self.ring.capacity();
+        if self.tail.wrapping_sub(self.cached_head) == capacity {
+            self.cached_head = self.ring.head.load(Ordering::Acquire);
+        }
+        capacity - self.tail.wrapping_sub(self.cached_head)
+    }
+
+    fn publish(&mut self, count: usize) {
+        self.tail = self.tail.wrapping_add(count);
+        self.ring.tail.store(self.tail, Ordering::Release);
+        self.ring.not_empty.wake();
+    }
+
+    pub fn try_push(&mut self, value: T) -> Result<(), PushError<T>> {
+        if self.is_closed() {
+            return Err(PushError::Closed(value));
+        }
+        if self.free_slots() == 0 {
+            return Err(PushError::Full(value));
+        }
+
+        self.ring.buffer[self.tail & self.ring.mask].with_mut(|cell| unsafe { (*cell).write(value) });
+        self.publish(1);
+        Ok(())
+    }
+
+    // Pousse autant d'éléments que possible depuis le début de `items`, publiés en une seule fois
+    pub fn push_batch(&mut self, items: &mut Vec<T>) -> usize {
+        if self.is_closed() {
+            return 0;
+        }
+        let count = self.free_slots().min(items.len());
+        if count == 0 {
+            return 0;
+        }
+
+        for (i, value) in items.drain(..count).enumerate() {
+            let idx = self.tail.wrapping_add(i) & self.ring.mask;
+            self.ring.buffer[idx].with_mut(|cell| unsafe { (*cell).write(value) });
+        }
+        self.publish(count);
+        count
+    }
+
+    fn has_space(&self) -> bool {
+        let ring = &self.ring;
+        self.tail.wrapping_sub(ring.head.load(Ordering::Acquire)) < ring.capacity()
+            || ring.closed.load(Ordering::Acquire)
+    }
+
+    pub fn push_blocking(&mut self, mut value: T) -> Result<(), T> {
+        loop {
+            match self.try_push(value) {
+                Ok(()) => return Ok(()),
+                Err(PushError::Closed(v)) => return Err(v),
+                Err(PushError::Full(v)) => {
+                    value = v;
+                    self.ring.not_full.wait_blocking(|| self.has_space());
+                }
+            }
+        }
+    }
+
+    pub async fn push_async(&mut self, mut value: T) -> Result<(), T> {
+        loop {
+            match self.try_push(value) {
+                Ok(()) => return Ok(()),
+                Err(PushError::Closed(v)) => return Err(v),
+                Err(PushError::Full(v)) => {
+                    value = v;
+                    self.ring.not_full.wait_async(|| self.has_space()).await;
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for Producer<T> {
+    fn drop(&mut self) {
+        self.ring.close();
+    }
+}
+
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+    head: usize,
+    cached_tail: usize,
+}
+
+impl<T> Consumer<T> {
+    pub fn capacity(&self) -> usize {
+        self.ring.capacity()
+    }
+
+    pub fn len(&self) -> usize {
+        self.ring.tail.load(Ordering::Relaxed).wrapping_sub(self.head)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Vrai quand le producteur a disparu (des éléments peuvent encore rester à lire)
+    pub fn is_closed(&self) -> bool {
+        self.ring.closed.load(Ordering::Acquire)
+    }
+
+    fn available(&mut self) -> usize {
+        if self.cached_tail == self.head {
+            self.cached_tail = self.ring.tail.load(Ordering::Acquire);
+        }
+        self.cached_tail.wrapping_sub(self.head)
+    }
+
+    fn release(&mut self, count: usize) {
+        self.head = self.head.wrapping_add(count);
+        self.ring.head.store(self.head, Ordering::Release);
+        self.ring.not_full.wake();
+    }
+
+    pub fn try_pop(&mut self) -> Option<T> {
+        if self.available() == 0 {
+            return None;
+        }
+
+        let value = self.ring.buffer[self.head & self.ring.mask].with_mut(|cell| unsafe { (*cell).assume_init_read() });
+        self.release(1);
+        Some(value)
+    }
+
+    // Retire jusqu'à `max` éléments dans `out`, libérés en une seule fois ; renvoie le nombre lu
+    pub fn pop_batch(&mut self, out: &mut Vec<T>, max: usize) -> usize {
+        let count = self.available().min(max);
+        if count == 0 {
+            return 0;
+        }
+
+        out.reserve(count);
+        for i in 0..count {
+            let idx = self.head.wrapping_add(i) & self.ring.mask;
+            out.push(self.ring.buffer[idx].with_mut(|cell| unsafe { (*cell).assume_init_read() }));
+        }
+        self.release(count);
+        count
+    }
+
+    fn has_item(&self) -> bool {
+        self.ring.tail.load(Ordering::Acquire) != self.head || self.ring.closed.load(Ordering::Acquire)
+    }
+
+    // None uniquement quand le producteur est parti et que la file est vide
+    pub fn pop_blocking(&mut self) -> Option<T> {
+        loop {
+            if let Some(value) = self.try_pop() {
+                return Some(value);
+            }
+            if self.is_closed() {
+                return self.try_pop();
+            }
+            self.ring.not_empty.wait_blocking(|| self.has_item());
+        }
+    }
+
+    // Attend un élément jusqu'à l'échéance (None aussi si la file est fermée et vide)
+    pub fn pop_until(&mut self, deadline: Instant) -> Option<T> {
+        loop {
+            if let Some(value) = self.try_pop() {
+                return Some(value);
+            }
+            if self.is_closed() {
+                return self.try_pop();
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            self.ring.not_empty.wait_blocking_until(|| self.has_item(), deadline);
+        }
+    }
+
+    pub async fn pop_async(&mut self) -> Option<T> {
+        loop {
+            if let Some(value) = self.try_pop() {
+                return Some(value);
+            }
+            if self.is_closed() {
+                return self.try_pop();
+            }
+            self.ring.not_empty.wait_async(|| self.has_item()).await;
+        }
+    }
+}
+
+impl<T> Drop for Consumer<T> {
+    fn drop(&mut self) {
+        self.ring.close();
+    }
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use loom::thread;
+    use super::*;
+    use crate::sync::primitives::model;
+
+    // Capacité 1 : le producteur dort sur not_full, le consommateur sur not_empty
+    #[test]
+    fn push_pop_blocking_in_order() {
+        model(|| {
+            let (mut tx, mut rx) = channel(1);
+            let producer = thread::spawn(move || {
+                tx.push_blocking(0).unwrap();
+                tx.push_blocking(1).unwrap();
+            });
+            assert_eq!(rx.pop_blocking(), Some(0));
+            assert_eq!(rx.pop_blocking(), Some(1));
+            // Producteur parti : la fermeture n'est vue qu'une fois la file vidée
+            assert_eq!(rx.pop_blocking(), None);
+            producer.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn batch_publish_is_atomic() {
+        model(|| {
+            let (mut tx, mut rx) = channel(2);
+            let producer = thread::spawn(move || {
+                let mut items = vec![0, 1];
+                assert_eq!(tx.push_batch(&mut items), 2);
+            });
+            let mut out = Vec::new();
+            while out.len() < 2 {
+                if rx.pop_batch(&mut out, 2) == 0 {
+                    thread::yield_now();
+                }
+            }
+            assert_eq!(out, [0, 1]);
+            producer.join().unwrap();
+        });
+    }
+
+    // Consommateur parti pendant que le producteur attend une place
+    #[test]
+    fn consumer_drop_wakes_blocked_producer() {
+        model(|| {
+            let (mut tx, rx) = channel(1);
+            tx.try_push(0).unwrap();
+            let consumer = thread::spawn(move || drop(rx));
+            assert_eq!(tx.push_blocking(1), Err(1));
+            consumer.join().unwrap();
+        });
+    }
+
+    // Éléments encore en file à la destruction : chacun est détruit exactement une fois
+    #[test]
+    fn drop_releases_queued_items() {
+        model(|| {
+            let item = Arc::new(());
+            let (mut tx, rx) = channel(2);
+            tx.try_push(item.clone()).unwrap();
+            tx.try_push(item.clone()).unwrap();
+            let consumer = thread::spawn(move || drop(rx));
+            drop(tx);
+            consumer.join().unwrap();
+            assert_eq!(Arc::strong_count(&item), 1);
+        });
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn capacity_rounds_up_to_power_of_two() {
+        for (requested, expected) in [(0, 1), (1, 1), (3, 4), (8, 8), (9, 16)] {
+            let (tx, rx) = channel::<u8>(requested);
+            assert_eq!((tx.capacity(), rx.capacity()), (expected, expected), "{}", requested);
+        }
+    }
+
+    #[test]
+    fn full_then_closed() {
+        let (mut tx, mut rx) = channel(2);
+        tx.try_push(0).unwrap();
+        tx.try_push(1).unwrap();
+        assert_eq!(tx.try_push(2), Err(PushError::Full(2)));
+        assert_eq!(rx.len(), 2);
+
+        // Une place libérée : le producteur relit l'index du consommateur
+        assert_eq!(rx.try_pop(), Some(0));
+        tx.try_push(2).unwrap();
+        assert_eq!(rx.try_pop(), Some(1));
+        assert_eq!(rx.try_pop(), Some(2));
+        assert_eq!(rx.try_pop(), None);
+        assert!(rx.is_empty());
+
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.try_push(3), Err(PushError::Closed(3)));
+    }
+
+    #[test]
+    fn batches_keep_order_across_wraparound() {
+        let (mut tx, mut rx) = channel(4);
+        let mut items = (0..5).collect::<Vec<_>>();
+        assert_eq!(tx.push_batch(&mut items), 4);
+        assert_eq!(items, [4]);
+        assert_eq!(tx.push_batch(&mut items), 0);
+        let mut out = Vec::new();
+        assert_eq!(rx.pop_batch(&mut out, 3), 3);
+        assert_eq!(out, [0, 1, 2]);
+
+        // Plusieurs tours d'anneau en lots partiels des deux côtés
+        items.extend(5..50);
+        while out.len() < 50 {
+            let before = items.len();
+            let pushed = tx.push_batch(&mut items);
+            assert!(pushed <= 4 && items.len() == before - pushed);
+            let popped = rx.pop_batch(&mut out, 3);
+            assert!(popped <= 3);
+            assert!(pushed + popped > 0, "aucune progression");
+        }
+        assert_eq!(out, (0..50).collect::<Vec<_>>());
+
+        drop(rx);
+        assert_eq!(tx.push_batch(&mut vec![1, 2]), 0);
+    }
+
+    #[test]
+    fn pop_until_gives_up_at_the_deadline() {
+        let (mut tx, mut rx) = channel(1);
+        let start = Instant::now();
+        assert_eq!(rx.pop_until(start + Duration::from_millis(20)), None);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let producer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            tx.try_push(7).unwrap();
+            tx
+        });
+        assert_eq!(rx.pop_until(Instant::now() + Duration::from_secs(5)), Some(7));
+
+        // Producteur parti : l'élément restant est rendu, puis None sans attendre l'échéance
+        let mut tx = producer.join().unwrap();
+        tx.try_push(8).unwrap();
+        drop(tx);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        assert_eq!(rx.pop_until(deadline), Some(8));
+        assert_eq!(rx.pop_until(deadline), None);
+        assert!(Instant::now() < deadline);
+    }
+
+    #[test]
+    fn close_wakes_blocked_sides() {
+        let (tx, mut rx) = channel::<u32>(1);
+        let consumer = thread::spawn(move || rx.pop_blocking());
+        thread::sleep(Duration::from_millis(20));
+        drop(tx);
+        assert_eq!(consumer.join().unwrap(), None);
+
+        let (mut tx, rx) = channel(1);
+        tx.try_push(0).unwrap();
+        let producer = thread::spawn(move || tx.push_blocking(1));
+        thread::sleep(Duration::from_millis(20));
+        drop(rx);
+        assert_eq!(producer.join().unwrap(), Err(1));
+    }
+
+    #[tokio::test]
+    async fn async_sides_are_woken() {
+        let (mut tx, mut rx) = channel(1);
+        let producer = thread::spawn(move || {
+            for i in 0..100 {
+                tx.push_blocking(i).unwrap();
+            }
+        });
+        for i in 0..100 {
+            assert_eq!(rx.pop_async().await, Some(i));
+        }
+        assert_eq!(rx.pop_async().await, None);
+        producer.join().unwrap();
+    }
+
+    // Attente asynchrone abandonnée (timeout, select!) : plus comptée par `wake`
+    #[tokio::test]
+    async fn abandoned_async_wait_unregisters() {
+        let (_tx, mut rx) = channel::<u32>(1);
+        assert!(tokio::time::timeout(Duration::from_millis(10), rx.pop_async()).await.is_err());
+        assert_eq!(rx.ring.not_empty.registered_tasks(), 0);
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::PoisonError;
+use std::time::Instant;
+#[cfg(not(loom))]
+use crossbeam::utils::Backoff;
+use tokio::sync::Notify;
+use crate::sync::primitives::{fence, AtomicUsize, Condvar, Mutex, Ordering};
+
+// Point de rendez-vous partagé par les files : attente bloquante (threads de la pipeline)
+// ou asynchrone (tâches tokio). Le chemin rapide de `wake` ne prend aucun verrou et,
+// sans personne en attente, se réduit à la barrière et à la lecture des compteurs.
+pub(crate) struct WaitCell {
+    // Threads endormis sur le condvar
+    sleepers: AtomicUsize,
+    // Tâches inscrites sur `notify`
+    tasks: AtomicUsize,
+    lock: Mutex<()>,
+    condvar: Condvar,
+    notify: Notify,
+}
+
+impl WaitCell {
+    pub fn new() -> Self {
+        Self {
+            sleepers: AtomicUsize::new(0),
+            tasks: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            condvar: Condvar::new(),
+            notify: Notify::new(),
+        }
+    }
+
+    // À appeler après avoir publié l'état (Release) qui rend la condition vraie
+    pub fn wake(&self) {
+        // Ordonne la publication avant la lecture des compteurs (pendant des attentes) ;
+        // sans elle, un réveil pourrait se perdre
+        fence(Ordering::SeqCst);
+        // Pas de notify_one sans tâche inscrite : il laisserait un permis et un tour d'attente inutile
+        if self.tasks.load(Ordering::Relaxed) > 0 {
+            self.notify.notify_one();
+        }
+        if self.sleepers.load(Ordering::Relaxed) > 0 {
+            let _guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+            self.condvar.notify_all();
+        }
+    }
+
+    // Réveille tous les attentistes (fermeture de la file)
+    pub fn wake_all(&self) {
+        self.notify.notify_waiters();
+        self.wake();
+    }
+
+    pub fn wait_blocking(&self, ready: impl Fn() -> bool) {
+        // Quelques tours d'attente active avant de dormir : la plupart des attentes sont courtes.
+        // Sous loom, seul le protocole d'endormissement est exploré
+        #[cfg(not(loom))]
+        {
+            let backoff = Backoff::new();
+            while !backoff.is_completed() {
+                if ready() {
+                    return;
+                }
+                backoff.snooze();
+            }
+        }
+
+        let mut guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+        while !ready() {
+            guard = self.condvar.wait(guard).unwrap_or_else(PoisonError::into_inner);
+        }
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Comme `wait_blocking`, mais abandonne à l'échéance ; renvoie l'état de la condition
+    pub fn wait_blocking_until(&self, ready: impl Fn() -> bool, deadline: Instant) -> bool {
+        #[cfg(not(loom))]
+        {
+            let backoff = Backoff::new();
+            while !backoff.is_completed() {
+                if ready() {
+                    return true;
+                }
+                if Instant::now() >= deadline {
+                    return false;
+                }
+                backoff.snooze();
+            }
+        }
+
+        let mut guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.sleepers.fetch_add(1, Ordering::SeqCst);
+        fence(Ordering::SeqCst);
+        let mut is_ready = ready();
+        while !is_ready {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            guard = match self.condvar.wait_timeout(guard, remaining) {
+                Ok((guard, _)) => guard,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+            is_ready = ready();
+        }
+        self.sleepers.fetch_sub(1, Ordering::SeqCst);
+        is_ready
+    }
+
+    #[cfg(test)]
+    pub fn registered_tasks(&self) -> usize {
+        self.tasks.load(Ordering::Relaxed)
+    }
+
+    pub async fn wait_async(&self, ready: impl Fn() -> bool) {
+        // Inscription visible de `wake` avant le premier test de la condition ; retirée même
+        // si la future est abandonnée en cours d'attente
+        self.tasks.fetch_add(1, Ordering::SeqCst);
+        let _registered = TaskGuard(&self.tasks);
+        fence(Ordering::SeqCst);
+        loop {
+            // S'enregistrer avant de tester la condition pour ne rater aucun réveil
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if ready() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+struct TaskGuard<'a>(&'a AtomicUsize);
+
+impl Drop for TaskGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
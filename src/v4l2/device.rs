@@ -1,38 +1,596 @@
-use std::io;
-use crate::v4l2::dmabuf::DmaBufAllocator;
-use std::os::unix::io::RawFd;
-use std::fs::OpenOptions;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
 use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
+use tracing::{info, warn};
+use crate::codec::convert::{ColorMatrix, ColorRange, Colorimetry, PixelFormat};
+use crate::v4l2::dmabuf::{Access, DmaBuf, DmaBufAllocator};
+use crate::v4l2::error::{fourcc_str, V4l2Error};
+use crate::v4l2::sys::{self, ioctl, zeroed};
 
+// Tampons du mode streaming : assez pour absorber un lecteur lent sans ajouter de latence
+const STREAM_BUFFERS: u32 = 4;
+// Le pilote reprend l'horodatage fourni par l'application
+const V4L2_BUF_FLAG_TIMESTAMP_COPY: u32 = 0x0000_4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IoMode {
+    // write() d'une image complète : le plus simple, géré par v4l2loopback
+    #[default]
+    Write,
+    // VIDIOC_REQBUFS + mmap + QBUF/DQBUF : une copie de moins côté noyau
+    Mmap,
+    // Tampons DMA-BUF alloués par l'application (dma-heap/udmabuf) et passés par descripteur
+    Dmabuf,
+}
+
+impl IoMode {
+    pub const ALL: [IoMode; 3] = [IoMode::Write, IoMode::Mmap, IoMode::Dmabuf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IoMode::Write => "write",
+            IoMode::Mmap => "mmap",
+            IoMode::Dmabuf => "dmabuf",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DeviceConfig {
+    pub format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    pub colorimetry: Colorimetry,
+    pub io: IoMode,
+}
+
+// Format effectivement retenu par le pilote (VIDIOC_S_FMT)
+#[derive(Debug, Clone, Copy)]
+pub struct NegotiatedFormat {
+    pub format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    pub bytesperline: usize,
+    pub sizeimage: usize,
+}
+
+impl NegotiatedFormat {
+    // Image compacte attendue par write_frame, avant un éventuel changement de pas
+    fn check_frame(&self, data: &[u8]) -> Result<(), V4l2Error> {
+        let expected = self.format.frame_size(self.width, self.height);
+        if data.len() != expected {
+            return Err(V4l2Error::FrameSize { expected, got: data.len() });
+        }
+        Ok(())
+    }
+
+    // Les images produites par la pipeline sont compactes ; le pilote peut demander un pas plus large
+    fn is_packed(&self) -> bool {
+        self.bytesperline == self.format.layout(self.width, self.height)[0].stride
+    }
+
+    // Recopie une image compacte avec le pas du pilote (les plans de chroma suivent le même rapport)
+    fn restride(&self, src: &[u8], dst: &mut [u8]) {
+        let layout = self.format.layout(self.width, self.height);
+        let main_stride = layout[0].stride.max(1);
+        let mut offset = 0;
+        for plane in layout.iter().take(self.format.plane_count()) {
+            let stride = plane.stride * self.bytesperline / main_stride;
+            for row in 0..plane.rows {
+                let from = plane.offset + row * plane.stride;
+                let to = offset + row * stride;
+                dst[to..to + plane.row_bytes].copy_from_slice(&src[from..from + plane.row_bytes]);
+            }
+            offset += stride * plane.rows;
+        }
+    }
+}
+
+// Sortie V4L2 (v4l2loopback) : négociation du format puis écriture par write() ou streaming mmap/dmabuf
 pub struct Device {
-    fd: RawFd,
-    allocator: DmaBufAllocator,
+    // Déclaré avant `file` : les tampons sont libérés avant la fermeture du descripteur
+    io: Io,
+    file: File,
+    path: String,
+    card: String,
+    format: NegotiatedFormat,
+    // Mode effectif, après repli éventuel
+    mode: IoMode,
+    staging: Vec<u8>,
+}
+
+enum Io {
+    Write,
+    Stream(Stream),
 }
 
 impl Device {
-    pub fn open(nr: u16) -> io::Result<Self> {
+    pub fn open(nr: u16, config: &DeviceConfig) -> Result<Self, V4l2Error> {
         let path = format!("/dev/video{}", nr);
-        
-        // Ouverture réelle du périphérique en écriture seule
+
+        // Non bloquant : sous charge, une image est abandonnée plutôt que de retenir la pipeline
         let file = OpenOptions::new()
-            .read(false)
+            .read(true)
             .write(true)
             .custom_flags(libc::O_NONBLOCK)
-            .open(&path)?;
-        
-        use std::os::unix::io::IntoRawFd;
-        let fd = file.into_raw_fd();
-        
-        let allocator = DmaBufAllocator::new(&path, 1920 * 1080 * 2)?; 
-        
-        Ok(Self {
+            .open(&path)
+            .map_err(|source| V4l2Error::open(&path, source))?;
+        let fd = file.as_raw_fd();
+
+        let mut cap: sys::v4l2_capability = zeroed();
+        ioctl(fd, sys::VIDIOC_QUERYCAP, &mut cap).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_QUERYCAP", source })?;
+        let caps = if cap.capabilities & sys::V4L2_CAP_DEVICE_CAPS != 0 { cap.device_caps } else { cap.capabilities };
+        if caps & sys::V4L2_CAP_VIDEO_OUTPUT == 0 {
+            return Err(V4l2Error::NotOutputDevice { path });
+        }
+        let required = match config.io {
+            IoMode::Write => sys::V4L2_CAP_READWRITE,
+            IoMode::Mmap | IoMode::Dmabuf => sys::V4L2_CAP_STREAMING,
+        };
+        if caps & required == 0 {
+            return Err(V4l2Error::UnsupportedIo { path, mode: config.io.name() });
+        }
+
+        let format = negotiate(fd, config)?;
+        let (io, mode) = open_io(fd, config.io, &format)?;
+
+        let device = Self {
+            io,
+            file,
+            path,
+            card: c_str(&cap.card),
+            format,
+            mode,
+            staging: Vec::new(),
+        };
+        device.log_format();
+        Ok(device)
+    }
+
+    fn log_format(&self) {
+        info!(
+            path = %self.path,
+            card = %self.card,
+            fourcc = %fourcc_str(self.format.format.fourcc()),
+            width = self.format.width,
+            height = self.format.height,
+            bytesperline = self.format.bytesperline,
+            io = self.mode.name(),
+            "format négocié"
+        );
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn card(&self) -> &str {
+        &self.card
+    }
+
+    // Image compacte au format négocié (cf. PixelFormat::layout) ; toute autre taille est refusée
+    pub fn write_frame(&mut self, data: &[u8]) -> Result<(), V4l2Error> {
+        let format = self.format;
+        format.check_frame(data)?;
+        match &mut self.io {
+            Io::Write => {
+                let frame = if format.is_packed() {
+                    data
+                } else {
+                    self.staging.resize(format.sizeimage, 0);
+                    format.restride(data, &mut self.staging);
+                    &self.staging[..]
+                };
+                match self.file.write(frame) {
+                    Ok(n) if n == frame.len() => Ok(()),
+                    Ok(_) => Err(V4l2Error::Write(io::ErrorKind::WriteZero.into())),
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(V4l2Error::Busy),
+                    Err(e) => Err(V4l2Error::Write(e)),
+                }
+            }
+            Io::Stream(stream) => {
+                let index = stream.acquire()?;
+                stream.fill(index, |buffer| {
+                    // Capacité vérifiée à l'allocation (open_io)
+                    if format.is_packed() {
+                        buffer[..data.len()].copy_from_slice(data);
+                    } else {
+                        format.restride(data, buffer);
+                    }
+                })?;
+                stream.queue(index, format.sizeimage)
+            }
+        }
+    }
+}
+
+// Tampons du mode demandé ; Dmabuf se replie sur Mmap si dma-heap/udmabuf manquent ou si le pilote refuse
+fn open_io(fd: RawFd, mode: IoMode, format: &NegotiatedFormat) -> Result<(Io, IoMode), V4l2Error> {
+    let stream = match mode {
+        IoMode::Write => return Ok((Io::Write, IoMode::Write)),
+        IoMode::Mmap => Stream::mmap(fd, STREAM_BUFFERS)?,
+        // Pas de fournisseur dma-buf, ou pilote sans V4L2_MEMORY_DMABUF (v4l2loopback) : copie mmap
+        IoMode::Dmabuf => match Stream::dmabuf(fd, STREAM_BUFFERS, format.sizeimage) {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!(error = %e, "DMA-BUF indisponible, repli sur le mode mmap");
+                Stream::mmap(fd, STREAM_BUFFERS)?
+            }
+        },
+    };
+    // Un tampon plus petit que sizeimage tronquerait chaque image
+    if let Some(length) = stream.buffers.iter().map(Buffer::len).min().filter(|&len| len < format.sizeimage) {
+        return Err(V4l2Error::BufferTooSmall { needed: format.sizeimage, length });
+    }
+    let mode = if stream.memory == sys::V4L2_MEMORY_DMABUF { IoMode::Dmabuf } else { IoMode::Mmap };
+    Ok((Io::Stream(stream), mode))
+}
+
+fn negotiate(fd: RawFd, config: &DeviceConfig) -> Result<NegotiatedFormat, V4l2Error> {
+    let mut fmt: sys::v4l2_format = zeroed();
+    fmt.type_ = sys::V4L2_BUF_TYPE_VIDEO_OUTPUT;
+    ioctl(fd, sys::VIDIOC_G_FMT, &mut fmt).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_G_FMT", source })?;
+
+    let (colorspace, ycbcr_enc) = match (config.format.is_yuv(), config.colorimetry.matrix) {
+        (false, _) => (sys::V4L2_COLORSPACE_SRGB, 0),
+        (true, ColorMatrix::Bt601) => (sys::V4L2_COLORSPACE_SMPTE170M, sys::V4L2_YCBCR_ENC_601),
+        (true, ColorMatrix::Bt709) => (sys::V4L2_COLORSPACE_REC709, sys::V4L2_YCBCR_ENC_709),
+    };
+    let quantization = match config.colorimetry.range {
+        ColorRange::Limited => sys::V4L2_QUANTIZATION_LIM_RANGE,
+        ColorRange::Full => sys::V4L2_QUANTIZATION_FULL_RANGE,
+    };
+    let layout = config.format.layout(config.width, config.height);
+    fmt.fmt.pix = sys::v4l2_pix_format {
+        width: config.width as u32,
+        height: config.height as u32,
+        pixelformat: config.format.fourcc(),
+        field: sys::V4L2_FIELD_NONE,
+        bytesperline: layout[0].stride as u32,
+        sizeimage: config.format.frame_size(config.width, config.height) as u32,
+        colorspace,
+        ycbcr_enc,
+        quantization,
+        ..Default::default()
+    };
+    ioctl(fd, sys::VIDIOC_S_FMT, &mut fmt).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_S_FMT", source })?;
+
+    // SAFETY : union renseignée par le pilote pour un type VIDEO_OUTPUT
+    accept_format(config, unsafe { &fmt.fmt.pix })
+}
+
+// Réponse du pilote à S_FMT : fourcc et taille imposés, pas et taille d'image au moins compacts
+fn accept_format(config: &DeviceConfig, pix: &sys::v4l2_pix_format) -> Result<NegotiatedFormat, V4l2Error> {
+    let layout = config.format.layout(config.width, config.height);
+    let (width, height) = (pix.width as usize, pix.height as usize);
+    if pix.pixelformat != config.format.fourcc() || (width, height) != (config.width, config.height) {
+        return Err(V4l2Error::FormatRejected {
+            requested: (config.format, config.width, config.height),
+            got: (pix.pixelformat, width, height),
+        });
+    }
+
+    let packed_stride = layout[0].stride;
+    let bytesperline = (pix.bytesperline as usize).max(packed_stride);
+    let sizeimage = (pix.sizeimage as usize).max(config.format.frame_size(width, height) * bytesperline / packed_stride.max(1));
+    Ok(NegotiatedFormat {
+        format: config.format,
+        width,
+        height,
+        bytesperline,
+        sizeimage,
+    })
+}
+
+fn c_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+struct MappedBuffer {
+    addr: *mut u8,
+    length: usize,
+}
+
+enum Buffer {
+    // Tampon du pilote projeté en mémoire (V4L2_MEMORY_MMAP)
+    Mapped(MappedBuffer),
+    // Tampon de l'application, passé par descripteur (V4L2_MEMORY_DMABUF)
+    Dma(DmaBuf),
+}
+
+impl Buffer {
+    fn len(&self) -> usize {
+        match self {
+            Buffer::Mapped(buffer) => buffer.length,
+            Buffer::Dma(buffer) => buffer.size(),
+        }
+    }
+}
+
+// Tampons du mode streaming ; `free` : indices que l'application peut remplir
+struct Stream {
+    fd: RawFd,
+    memory: u32,
+    buffers: Vec<Buffer>,
+    free: Vec<u32>,
+    streaming: bool,
+}
+
+// Les tampons appartiennent au seul thread de sortie
+unsafe impl Send for Stream {}
+
+impl Stream {
+    // VIDIOC_REQBUFS ; le flux est construit avant le remplissage pour que Drop libère tout en cas d'erreur
+    fn request(fd: RawFd, memory: u32, count: u32) -> Result<Self, V4l2Error> {
+        let mut req = sys::v4l2_requestbuffers {
+            count,
+            type_: sys::V4L2_BUF_TYPE_VIDEO_OUTPUT,
+            memory,
+            ..Default::default()
+        };
+        ioctl(fd, sys::VIDIOC_REQBUFS, &mut req).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_REQBUFS", source })?;
+        let stream = Self {
             fd,
-            allocator,
-        })
+            memory,
+            buffers: Vec::with_capacity(req.count as usize),
+            free: (0..req.count).rev().collect(),
+            streaming: false,
+        };
+        if req.count == 0 {
+            return Err(V4l2Error::Mmap(io::Error::new(io::ErrorKind::OutOfMemory, "aucun tampon alloué par le pilote")));
+        }
+        Ok(stream)
+    }
+
+    fn mmap(fd: RawFd, count: u32) -> Result<Self, V4l2Error> {
+        let mut stream = Self::request(fd, sys::V4L2_MEMORY_MMAP, count)?;
+        for index in 0..stream.free.len() as u32 {
+            let mut buf = stream.buffer_desc(index);
+            ioctl(fd, sys::VIDIOC_QUERYBUF, &mut buf).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_QUERYBUF", source })?;
+
+            // SAFETY : offset et longueur fournis par le pilote pour ce tampon
+            let addr = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    buf.length as usize,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    fd,
+                    buf.m.offset as libc::off_t,
+                )
+            };
+            if addr == libc::MAP_FAILED {
+                // Les tampons déjà projetés sont libérés par Drop
+                return Err(V4l2Error::Mmap(io::Error::last_os_error()));
+            }
+            stream.buffers.push(Buffer::Mapped(MappedBuffer {
+                addr: addr as *mut u8,
+                length: buf.length as usize,
+            }));
+        }
+        Ok(stream)
+    }
+
+    // Le fournisseur est ouvert avant REQBUFS : sans lui, le pilote n'est pas touché
+    fn dmabuf(fd: RawFd, count: u32, size: usize) -> Result<Self, V4l2Error> {
+        let allocator = DmaBufAllocator::new().map_err(V4l2Error::DmaBuf)?;
+        let mut stream = Self::request(fd, sys::V4L2_MEMORY_DMABUF, count)?;
+        for _ in 0..stream.free.len() {
+            stream.buffers.push(Buffer::Dma(allocator.allocate(size).map_err(V4l2Error::DmaBuf)?));
+        }
+        info!(buffers = stream.buffers.len(), size, backend = allocator.backend().name(), "tampons DMA-BUF alloués");
+        Ok(stream)
+    }
+
+    fn buffer_desc(&self, index: u32) -> sys::v4l2_buffer {
+        let mut buf: sys::v4l2_buffer = zeroed();
+        buf.index = index;
+        buf.type_ = sys::V4L2_BUF_TYPE_VIDEO_OUTPUT;
+        buf.memory = self.memory;
+        buf
+    }
+
+    // Remplissage d'un tampon qui n'est pas en file côté pilote ; rendu à `free` en cas d'échec
+    fn fill(&mut self, index: u32, write: impl FnOnce(&mut [u8])) -> Result<(), V4l2Error> {
+        match &mut self.buffers[index as usize] {
+            Buffer::Mapped(buffer) => {
+                // SAFETY : projection valide tant que le flux existe
+                write(unsafe { std::slice::from_raw_parts_mut(buffer.addr, buffer.length) });
+                Ok(())
+            }
+            // DMA_BUF_IOCTL_SYNC start/end autour de l'écriture CPU
+            Buffer::Dma(buffer) => match buffer.cpu_access(Access::Write) {
+                Ok(mut access) => {
+                    write(&mut access);
+                    Ok(())
+                }
+                Err(e) => {
+                    self.free.push(index);
+                    Err(V4l2Error::DmaBuf(e))
+                }
+            },
+        }
+    }
+    // Tampon libre, ou le plus ancien rendu par le pilote (DQBUF)
+    fn acquire(&mut self) -> Result<u32, V4l2Error> {
+        if let Some(index) = self.free.pop() {
+            return Ok(index);
+        }
+        let mut buf = self.buffer_desc(0);
+        match ioctl(self.fd, sys::VIDIOC_DQBUF, &mut buf) {
+            Ok(()) => Ok(buf.index),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(V4l2Error::Busy),
+            Err(source) => Err(V4l2Error::Ioctl { request: "VIDIOC_DQBUF", source }),
+        }
+    }
+
+    fn queue(&mut self, index: u32, bytesused: usize) -> Result<(), V4l2Error> {
+        let buffer = &self.buffers[index as usize];
+        let mut buf = self.buffer_desc(index);
+        buf.bytesused = bytesused as u32;
+        if let Buffer::Dma(dma) = buffer {
+            buf.m.fd = dma.fd();
+            buf.length = dma.size() as u32;
+        }
+        buf.field = sys::V4L2_FIELD_NONE;
+        buf.flags = V4L2_BUF_FLAG_TIMESTAMP_COPY;
+        let mut now: libc::timespec = zeroed();
+        // SAFETY : pointeur valide vers une timespec locale
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now) };
+        buf.timestamp = libc::timeval {
+            tv_sec: now.tv_sec,
+            tv_usec: now.tv_nsec / 1000,
+        };
+
+        if let Err(source) = ioctl(self.fd, sys::VIDIOC_QBUF, &mut buf) {
+            self.free.push(index);
+            return Err(V4l2Error::Ioctl { request: "VIDIOC_QBUF", source });
+        }
+
+        // Une sortie ne démarre qu'une fois un premier tampon en file
+        if !self.streaming {
+            let mut kind = sys::V4L2_BUF_TYPE_VIDEO_OUTPUT as libc::c_int;
+            ioctl(self.fd, sys::VIDIOC_STREAMON, &mut kind).map_err(|source| V4l2Error::Ioctl { request: "VIDIOC_STREAMON", source })?;
+            self.streaming = true;
+        }
+        Ok(())
     }
-    
-    pub fn write_frame_dmabuf(&self, data: &[u8]) -> io::Result<()> {
-        // Envoi réellement les données au kernel via l'allocateur DMA-BUF
-        self.allocator.write_frame(data)
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        if self.streaming {
+            let mut kind = sys::V4L2_BUF_TYPE_VIDEO_OUTPUT as libc::c_int;
+            let _ = ioctl(self.fd, sys::VIDIOC_STREAMOFF, &mut kind);
+        }
+        for buffer in &self.buffers {
+            if let Buffer::Mapped(buffer) = buffer {
+                // SAFETY : projection créée dans Stream::mmap, plus utilisée après STREAMOFF
+                unsafe { libc::munmap(buffer.addr as *mut _, buffer.length) };
+            }
+        }
+        // Libère les tampons côté pilote
+        let mut req = sys::v4l2_requestbuffers {
+            count: 0,
+            type_: sys::V4L2_BUF_TYPE_VIDEO_OUTPUT,
+            memory: self.memory,
+            ..Default::default()
+        };
+        let _ = ioctl(self.fd, sys::VIDIOC_REQBUFS, &mut req);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(format: PixelFormat, width: usize, height: usize) -> DeviceConfig {
+        DeviceConfig { format, width, height, colorimetry: Colorimetry::default(), io: IoMode::Write }
+    }
+
+    // Réponse du pilote à S_FMT
+    fn pix(format: PixelFormat, width: u32, height: u32, bytesperline: u32, sizeimage: u32) -> sys::v4l2_pix_format {
+        sys::v4l2_pix_format { width, height, pixelformat: format.fourcc(), bytesperline, sizeimage, ..Default::default() }
+    }
+
+    #[test]
+    fn driver_answer_is_accepted_as_is_when_packed() {
+        let cfg = config(PixelFormat::Yuyv, 640, 480);
+        let format = accept_format(&cfg, &pix(PixelFormat::Yuyv, 640, 480, 1280, 640 * 480 * 2)).unwrap();
+        assert_eq!((format.bytesperline, format.sizeimage), (1280, 640 * 480 * 2));
+        assert!(format.is_packed());
+    }
+
+    #[test]
+    fn missing_stride_and_size_fall_back_to_packed() {
+        // Pilote qui laisse bytesperline / sizeimage à zéro (ou trop petits)
+        let cfg = config(PixelFormat::Nv12, 64, 48);
+        let format = accept_format(&cfg, &pix(PixelFormat::Nv12, 64, 48, 0, 100)).unwrap();
+        assert_eq!((format.bytesperline, format.sizeimage), (64, 64 * 48 * 3 / 2));
+        assert!(format.is_packed());
+    }
+
+    #[test]
+    fn wider_driver_stride_scales_the_image_size() {
+        let cfg = config(PixelFormat::Nv12, 64, 48);
+        let format = accept_format(&cfg, &pix(PixelFormat::Nv12, 64, 48, 128, 0)).unwrap();
+        assert_eq!((format.bytesperline, format.sizeimage), (128, 128 * 48 * 3 / 2));
+        assert!(!format.is_packed());
+        // sizeimage annoncé plus grand : conservé
+        let format = accept_format(&cfg, &pix(PixelFormat::Nv12, 64, 48, 128, 1 << 20)).unwrap();
+        assert_eq!(format.sizeimage, 1 << 20);
+    }
+
+    #[test]
+    fn other_fourcc_or_size_is_rejected() {
+        let cfg = config(PixelFormat::Yuyv, 640, 480);
+        let rejected = |pix| matches!(accept_format(&cfg, &pix), Err(V4l2Error::FormatRejected { .. }));
+        assert!(rejected(pix(PixelFormat::Uyvy, 640, 480, 1280, 0)));
+        assert!(rejected(pix(PixelFormat::Yuyv, 320, 240, 640, 0)));
+        match accept_format(&cfg, &pix(PixelFormat::Nv12, 640, 360, 640, 0)) {
+            Err(V4l2Error::FormatRejected { requested, got }) => {
+                assert_eq!(requested, (PixelFormat::Yuyv, 640, 480));
+                assert_eq!(got, (PixelFormat::Nv12.fourcc(), 640, 360));
+            }
+            other => panic!("{:?}", other.map(|f| f.bytesperline)),
+        }
+    }
+
+    #[test]
+    fn restride_pads_every_plane() {
+        // NV12 2×2, pas du pilote 4 : plans Y et UV recopiés ligne à ligne, remplissage intact
+        let format = NegotiatedFormat { format: PixelFormat::Nv12, width: 2, height: 2, bytesperline: 4, sizeimage: 12 };
+        let mut dst = [0xEE; 12];
+        format.restride(&[1, 2, 3, 4, 5, 6], &mut dst);
+        assert_eq!(dst, [1, 2, 0xEE, 0xEE, 3, 4, 0xEE, 0xEE, 5, 6, 0xEE, 0xEE]);
+
+        // I420 4×2 avec un pas doublé : la chroma (pas 2) passe à 4
+        let format = NegotiatedFormat { format: PixelFormat::I420, width: 4, height: 2, bytesperline: 8, sizeimage: 24 };
+        let src = [1, 2, 3, 4, 5, 6, 7, 8, 10, 11, 20, 21];
+        let mut dst = [0; 24];
+        format.restride(&src, &mut dst);
+        assert_eq!(&dst[..16], [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0]);
+        assert_eq!(&dst[16..], [10, 11, 0, 0, 20, 21, 0, 0]);
+
+        // YUYV : un seul plan
+        let format = NegotiatedFormat { format: PixelFormat::Yuyv, width: 2, height: 2, bytesperline: 6, sizeimage: 12 };
+        let mut dst = [0; 12];
+        format.restride(&[1, 2, 3, 4, 5, 6, 7, 8], &mut dst);
+        assert_eq!(dst, [1, 2, 3, 4, 0, 0, 5, 6, 7, 8, 0, 0]);
+    }
+
+    #[test]
+    fn frames_of_another_size_are_rejected() {
+        // Pas du pilote élargi : l'image attendue reste compacte
+        let cfg = config(PixelFormat::Nv12, 64, 48);
+        let format = accept_format(&cfg, &pix(PixelFormat::Nv12, 64, 48, 128, 0)).unwrap();
+        assert!(format.check_frame(&vec![0; 64 * 48 * 3 / 2]).is_ok());
+        assert!(matches!(
+            format.check_frame(&vec![0; format.sizeimage]),
+            Err(V4l2Error::FrameSize { expected: 4608, got: 9216 })
+        ));
+
+        // Résolution ou format changés en amont : jamais tronqué ni complété
+        let format = accept_format(&config(PixelFormat::Yuyv, 640, 480), &pix(PixelFormat::Yuyv, 640, 480, 0, 0)).unwrap();
+        for len in [0, 640 * 480 * 2 - 1, 640 * 480 * 2 + 1, 1280 * 720 * 2, 640 * 480 * 3 / 2] {
+            assert!(matches!(format.check_frame(&vec![0; len]), Err(V4l2Error::FrameSize { expected: 614400, got }) if got == len));
+        }
+    }
+
+    #[test]
+    fn io_mode_names() {
+        for mode in IoMode::ALL {
+            assert_eq!(IoMode::from_name(mode.name()), Some(mode));
+        }
+        assert_eq!(IoMode::from_name("MMAP"), Some(IoMode::Mmap));
+        assert_eq!(IoMode::from_name("userptr"), None);
     }
 }
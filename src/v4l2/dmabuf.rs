@@ -1,62 +1,250 @@
-use std::os::unix::io::RawFd;
-use std::{io, ptr};
-use std::ffi::CString;
+// Tampons DMA-BUF alloués par le noyau (dma-heap, ou udmabuf à partir d'un memfd), projetés
+// en mémoire pour y écrire les images puis passés au pilote V4L2 par descripteur
+// (V4L2_MEMORY_DMABUF). Chaque accès CPU est encadré par DMA_BUF_IOCTL_SYNC start/end.
+#![allow(non_camel_case_types)]
 
-pub struct DmaBufAllocator {
-    fd: RawFd,
-    size: usize,
-    addr: *mut u8,
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::Path;
+use std::ptr;
+use crate::v4l2::sys::{self, ioctl, ioctl_ret};
+
+const DMA_HEAP_SYSTEM: &str = "/dev/dma_heap/system";
+const UDMABUF: &str = "/dev/udmabuf";
+
+// <linux/dma-heap.h>
+#[repr(C)]
+#[derive(Default)]
+struct dma_heap_allocation_data {
+    len: u64,
+    fd: u32,
+    fd_flags: u32,
+    heap_flags: u64,
+}
+
+// <linux/udmabuf.h>
+#[repr(C)]
+#[derive(Default)]
+struct udmabuf_create {
+    memfd: u32,
+    flags: u32,
+    offset: u64,
+    size: u64,
+}
+
+// <linux/dma-buf.h>
+#[repr(C)]
+struct dma_buf_sync {
+    flags: u64,
 }
 
-unsafe impl Send for DmaBufAllocator {}
-unsafe impl Sync for DmaBufAllocator {}
+const DMA_HEAP_IOCTL_ALLOC: libc::c_ulong = sys::iowr_kind::<dma_heap_allocation_data>(b'H', 0);
+const UDMABUF_CREATE: libc::c_ulong = sys::iow_kind::<udmabuf_create>(b'u', 0x42);
+const UDMABUF_FLAGS_CLOEXEC: u32 = 0x01;
+const DMA_BUF_IOCTL_SYNC: libc::c_ulong = sys::iow_kind::<dma_buf_sync>(b'b', 0);
+const DMA_BUF_SYNC_WRITE: u64 = 2;
+const DMA_BUF_SYNC_START: u64 = 0;
+const DMA_BUF_SYNC_END: u64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // /dev/dma_heap/system (noyau ≥ 5.6)
+    Heap,
+    // memfd scellé converti en dmabuf par /dev/udmabuf
+    Udmabuf,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Heap => "dma-heap",
+            Backend::Udmabuf => "udmabuf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Access {
+    Write,
+}
+
+impl Access {
+    fn flags(self) -> u64 {
+        match self {
+            Access::Write => DMA_BUF_SYNC_WRITE,
+        }
+    }
+}
+
+pub struct DmaBufAllocator {
+    backend: Backend,
+    device: File,
+}
 
 impl DmaBufAllocator {
-    pub fn new(device: &str, size: usize) -> io::Result<Self> {
-        let path = CString::new(device).unwrap();
-        
-        let fd = unsafe {
-            libc::open(path.as_ptr(), libc::O_RDWR)
-        };
-        
-        if fd < 0 {
-            return Err(io::Error::last_os_error());
+    // Premier fournisseur disponible ; NotFound si aucun (le pilote reprend alors la copie mmap/write)
+    pub fn new() -> io::Result<Self> {
+        let mut last_error = io::Error::new(io::ErrorKind::NotFound, "ni /dev/dma_heap/system ni /dev/udmabuf");
+        for (backend, path) in [(Backend::Heap, DMA_HEAP_SYSTEM), (Backend::Udmabuf, UDMABUF)] {
+            if !Path::new(path).exists() {
+                continue;
+            }
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Ok(device) => return Ok(Self { backend, device }),
+                Err(e) => last_error = io::Error::new(e.kind(), format!("{} : {}", path, e)),
+            }
         }
-        
-        // Memory map (zero-copy bypass)
+        Err(last_error)
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    // Taille arrondie à la page
+    pub fn allocate(&self, size: usize) -> io::Result<DmaBuf> {
+        let size = size.next_multiple_of(page_size());
+        let fd = match self.backend {
+            Backend::Heap => {
+                let mut data = dma_heap_allocation_data {
+                    len: size as u64,
+                    fd_flags: (libc::O_RDWR | libc::O_CLOEXEC) as u32,
+                    ..Default::default()
+                };
+                ioctl(self.device.as_raw_fd(), DMA_HEAP_IOCTL_ALLOC, &mut data)?;
+                data.fd as RawFd
+            }
+            Backend::Udmabuf => {
+                let memfd = sealed_memfd(size)?;
+                let mut create = udmabuf_create {
+                    memfd: memfd.as_raw_fd() as u32,
+                    flags: UDMABUF_FLAGS_CLOEXEC,
+                    offset: 0,
+                    size: size as u64,
+                };
+                // Le dmabuf garde sa propre référence sur les pages : le memfd peut être fermé
+                ioctl_ret(self.device.as_raw_fd(), UDMABUF_CREATE, &mut create)?
+            }
+        };
+        // SAFETY : descripteur tout juste créé par le noyau, dont on prend possession
+        let file = unsafe { File::from_raw_fd(fd) };
+        DmaBuf::map(file, size)
+    }
+}
+
+// memfd de `size` octets scellé contre la réduction, comme l'exige udmabuf
+fn sealed_memfd(size: usize) -> io::Result<File> {
+    // SAFETY : nom C statique, descripteur vérifié avant usage
+    let fd = unsafe { libc::memfd_create(c"phonecam-dmabuf".as_ptr(), libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY : descripteur valide dont on prend possession
+    let file = unsafe { File::from_raw_fd(fd) };
+    file.set_len(size as u64)?;
+    // SAFETY : fcntl sur un descripteur valide
+    if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, libc::F_SEAL_SHRINK) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(file)
+}
+
+fn page_size() -> usize {
+    // SAFETY : sysconf sans effet de bord
+    (unsafe { libc::sysconf(libc::_SC_PAGESIZE) }).max(4096) as usize
+}
+
+pub struct DmaBuf {
+    file: File,
+    addr: *mut u8,
+    size: usize,
+}
+
+// Un tampon n'est manipulé que par le thread qui le possède
+unsafe impl Send for DmaBuf {}
+
+impl DmaBuf {
+    fn map(file: File, size: usize) -> io::Result<Self> {
+        // SAFETY : projection partagée d'un dmabuf de `size` octets
         let addr = unsafe {
             libc::mmap(
                 ptr::null_mut(),
                 size,
                 libc::PROT_READ | libc::PROT_WRITE,
                 libc::MAP_SHARED,
-                fd,
+                file.as_raw_fd(),
                 0,
             )
         };
-        
         if addr == libc::MAP_FAILED {
-            unsafe { libc::close(fd); }
             return Err(io::Error::last_os_error());
         }
-        
-        Ok(Self { fd, size, addr: addr as *mut u8 })
-    }
-    
-    pub fn write_frame(&self, data: &[u8]) -> io::Result<()> {
-        unsafe {
-            // Copie directe dans la mémoire mappée (accessible par le DMA du kernel)
-            ptr::copy_nonoverlapping(data.as_ptr(), self.addr, data.len().min(self.size));
-        }
-        Ok(())
+        Ok(Self {
+            file,
+            addr: addr as *mut u8,
+            size,
+        })
+    }
+
+    pub fn fd(&self) -> RawFd {
+        self.file.as_raw_fd()
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Accès CPU cohérent : SYNC_START maintenant, SYNC_END quand le garde est relâché
+    pub fn cpu_access(&mut self, access: Access) -> io::Result<CpuAccess<'_>> {
+        self.sync(DMA_BUF_SYNC_START | access.flags())?;
+        Ok(CpuAccess { buf: self, access })
+    }
+
+    fn sync(&self, flags: u64) -> io::Result<()> {
+        ioctl(self.fd(), DMA_BUF_IOCTL_SYNC, &mut dma_buf_sync { flags })
     }
 }
 
-impl Drop for DmaBufAllocator {
+impl Drop for DmaBuf {
     fn drop(&mut self) {
-        unsafe {
-            libc::munmap(self.addr as *mut _, self.size);
-            libc::close(self.fd);
-        }
+        // SAFETY : projection créée dans DmaBuf::map ; le descripteur est fermé par File
+        unsafe { libc::munmap(self.addr as *mut _, self.size) };
+    }
+}
+
+pub struct CpuAccess<'a> {
+    buf: &'a mut DmaBuf,
+    access: Access,
+}
+
+impl std::ops::Deref for CpuAccess<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY : projection valide pendant la durée de vie du garde
+        unsafe { std::slice::from_raw_parts(self.buf.addr, self.buf.size) }
     }
 }
+
+impl std::ops::DerefMut for CpuAccess<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY : accès exclusif au tampon via &mut DmaBuf
+        unsafe { std::slice::from_raw_parts_mut(self.buf.addr, self.buf.size) }
+    }
+}
+
+impl Drop for CpuAccess<'_> {
+    fn drop(&mut self) {
+        let _ = self.buf.sync(DMA_BUF_SYNC_END | self.access.flags());
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(std::mem::size_of::<dma_heap_allocation_data>() == 24);
+    assert!(std::mem::size_of::<udmabuf_create>() == 24);
+    assert!(DMA_HEAP_IOCTL_ALLOC == 0xc018_4800);
+    assert!(UDMABUF_CREATE == 0x4018_7542);
+    assert!(DMA_BUF_IOCTL_SYNC == 0x4008_6200);
+};
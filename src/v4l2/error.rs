@@ -0,0 +1,97 @@
+use std::fmt;
+use std::io;
+use crate::codec::convert::PixelFormat;
+
+#[derive(Debug)]
+pub enum V4l2Error {
+    Open { path: String, source: io::Error },
+    // EACCES/EPERM : l'utilisateur n'a pas accès au nœud (groupe video, règle udev)
+    PermissionDenied { path: String },
+    // Ni /dev/v4l2loopback ni périphérique loopback : module absent ou non chargé
+    ModuleNotLoaded,
+    // /dev/videoN existe mais appartient à un autre pilote (webcam réelle…)
+    NotLoopback { path: String, driver: String },
+    Ioctl { request: &'static str, source: io::Error },
+    // Le périphérique n'accepte pas de sortie vidéo (mauvais /dev/videoN, ou loopback en mode capture)
+    NotOutputDevice { path: String },
+    // Le mode d'E/S demandé n'est pas proposé par le pilote
+    UnsupportedIo { path: String, mode: &'static str },
+    // Le pilote a remplacé le format ou la taille demandés
+    FormatRejected {
+        requested: (PixelFormat, usize, usize),
+        got: (u32, usize, usize),
+    },
+    // Image dont la taille ne correspond pas au format négocié (résolution ou format changés)
+    FrameSize { expected: usize, got: usize },
+    // Tampon du pilote plus petit que sizeimage
+    BufferTooSmall { needed: usize, length: usize },
+    Mmap(io::Error),
+    // Allocation ou synchronisation d'un tampon DMA-BUF
+    DmaBuf(io::Error),
+    Write(io::Error),
+    // Aucun tampon libre / le pilote n'accepte pas de données pour l'instant
+    Busy,
+}
+
+impl fmt::Display for V4l2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            V4l2Error::Open { path, source } => write!(f, "ouverture de {} : {}", path, source),
+            V4l2Error::PermissionDenied { path } => write!(
+                f,
+                "accès refusé à {} (ajouter l'utilisateur au groupe video : sudo usermod -aG video $USER, puis se reconnecter ; \
+                 /dev/v4l2loopback demande en général une règle udev ou root)",
+                path
+            ),
+            V4l2Error::ModuleNotLoaded => write!(
+                f,
+                "module v4l2loopback non chargé (sudo modprobe v4l2loopback exclusive_caps=1 card_label=\"PhoneCam Ultimate\")"
+            ),
+            V4l2Error::NotLoopback { path, driver } => write!(f, "{} est géré par le pilote {}, pas par v4l2loopback", path, driver),
+            V4l2Error::Ioctl { request, source } => write!(f, "{} : {}", request, source),
+            V4l2Error::NotOutputDevice { path } => write!(f, "{} n'est pas un périphérique de sortie vidéo", path),
+            V4l2Error::UnsupportedIo { path, mode } => write!(f, "{} ne gère pas le mode {}", path, mode),
+            V4l2Error::FormatRejected { requested, got } => write!(
+                f,
+                "format {} {}x{} refusé, le pilote propose {} {}x{}",
+                requested.0,
+                requested.1,
+                requested.2,
+                fourcc_str(got.0),
+                got.1,
+                got.2
+            ),
+            V4l2Error::FrameSize { expected, got } => write!(f, "image de {} octets, {} attendus pour le format négocié", got, expected),
+            V4l2Error::BufferTooSmall { needed, length } => write!(f, "tampon de {} octets, {} nécessaires", length, needed),
+            V4l2Error::Mmap(e) => write!(f, "mmap : {}", e),
+            V4l2Error::DmaBuf(e) => write!(f, "dma-buf : {}", e),
+            V4l2Error::Write(e) => write!(f, "écriture : {}", e),
+            V4l2Error::Busy => write!(f, "périphérique occupé, image ignorée"),
+        }
+    }
+}
+
+impl V4l2Error {
+    // Erreur d'ouverture, avec un diagnostic dédié aux problèmes de droits
+    pub fn open(path: &str, source: io::Error) -> Self {
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => V4l2Error::PermissionDenied { path: path.to_string() },
+            _ => V4l2Error::Open { path: path.to_string(), source },
+        }
+    }
+}
+
+impl std::error::Error for V4l2Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            V4l2Error::Open { source, .. } | V4l2Error::Ioctl { source, .. } => Some(source),
+            V4l2Error::Mmap(e) | V4l2Error::DmaBuf(e) | V4l2Error::Write(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// "YUYV" à partir du code V4L2 (octets de poids faible d'abord)
+pub fn fourcc_str(code: u32) -> String {
+    code.to_le_bytes().iter().map(|&b| if b.is_ascii_graphic() { b as char } else { '?' }).collect()
+}
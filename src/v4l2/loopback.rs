@@ -0,0 +1,383 @@
+// Gestion des périphériques v4l2loopback : découverte via sysfs, création et suppression
+// dynamiques via /dev/v4l2loopback (ioctls V4L2LOOPBACK_CTL_*, cf. <linux/v4l2loopback.h>).
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use tracing::info;
+use crate::v4l2::error::V4l2Error;
+
+const SYSFS_VIDEO: &str = "/sys/class/video4linux";
+const SYSFS_MODULE_VERSION: &str = "/sys/module/v4l2loopback/version";
+const CONTROL_DEVICE: &str = "/dev/v4l2loopback";
+const DRIVER: &str = "v4l2loopback";
+
+// Numéros "nus" (pas de _IOW) : l'argument est un pointeur vers la config, ou le numéro du périphérique
+const V4L2LOOPBACK_CTL_ADD: libc::c_ulong = 0x4C80;
+const V4L2LOOPBACK_CTL_REMOVE: libc::c_ulong = 0x4C81;
+
+// Disposition depuis v4l2loopback 0.13 (min/max largeur et hauteur)
+#[repr(C)]
+struct LoopbackConfigV13 {
+    output_nr: i32,
+    capture_nr: i32,
+    card_label: [u8; 32],
+    min_width: u32,
+    max_width: u32,
+    min_height: u32,
+    max_height: u32,
+    max_buffers: i32,
+    max_openers: i32,
+    debug: i32,
+    announce_all_caps: i32,
+}
+
+// Disposition 0.12.x
+#[repr(C)]
+struct LoopbackConfigV12 {
+    output_nr: i32,
+    capture_nr: i32,
+    card_label: [u8; 32],
+    max_width: u32,
+    max_height: u32,
+    max_buffers: i32,
+    max_openers: i32,
+    debug: i32,
+    announce_all_caps: i32,
+}
+
+// Nœud /dev/videoN vu par sysfs
+#[derive(Debug, Clone)]
+pub struct VideoNode {
+    pub nr: u16,
+    pub path: PathBuf,
+    // Nom de carte (card_label pour v4l2loopback)
+    pub name: String,
+    pub driver: Option<String>,
+}
+
+impl VideoNode {
+    pub fn is_loopback(&self) -> bool {
+        self.driver.as_deref().is_some_and(|d| d.starts_with(DRIVER))
+    }
+}
+
+// Tous les nœuds vidéo, triés par numéro
+pub fn discover() -> io::Result<Vec<VideoNode>> {
+    discover_in(Path::new(SYSFS_VIDEO))
+}
+
+fn discover_in(sysfs: &Path) -> io::Result<Vec<VideoNode>> {
+    let mut nodes = Vec::new();
+    for entry in fs::read_dir(sysfs)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let Some(nr) = file_name.to_str().and_then(|n| n.strip_prefix("video")).and_then(|n| n.parse().ok()) else {
+            continue;
+        };
+        nodes.push(read_node(&entry.path(), nr));
+    }
+    nodes.sort_by_key(|n| n.nr);
+    Ok(nodes)
+}
+
+fn read_node(sysfs: &Path, nr: u16) -> VideoNode {
+    let name = fs::read_to_string(sysfs.join("name")).map(|n| n.trim().to_string()).unwrap_or_default();
+    // Pilote lié (uvcvideo…) ; à défaut, périphérique plateforme "v4l2loopback"
+    let link_name = |link: &str| {
+        fs::read_link(sysfs.join(link))
+            .ok()
+            .and_then(|target| target.file_name().map(|n| n.to_string_lossy().into_owned()))
+    };
+    VideoNode {
+        nr,
+        path: PathBuf::from(format!("/dev/video{}", nr)),
+        name,
+        driver: link_name("device/driver").or_else(|| link_name("device")),
+    }
+}
+
+pub fn find(nr: u16) -> Option<VideoNode> {
+    let sysfs = Path::new(SYSFS_VIDEO).join(format!("video{}", nr));
+    sysfs.exists().then(|| read_node(&sysfs, nr))
+}
+
+// Périphérique de sortie retenu pour la pipeline
+#[derive(Debug, Clone)]
+pub struct Loopback {
+    pub node: VideoNode,
+    // Créé par ce processus : à supprimer à l'arrêt
+    pub created: bool,
+}
+
+pub struct LoopbackOptions<'a> {
+    pub label: &'a str,
+    pub max_width: usize,
+    pub max_height: usize,
+    // Autoriser la création via /dev/v4l2loopback si /dev/videoN n'existe pas
+    pub create: bool,
+}
+
+// /dev/videoN existant (s'il s'agit bien d'une loopback), sinon création dynamique
+pub fn setup_loopback(nr: u16, options: &LoopbackOptions) -> Result<Loopback, V4l2Error> {
+    if let Some(node) = find(nr) {
+        if !node.is_loopback() {
+            return Err(V4l2Error::NotLoopback {
+                path: node.path.display().to_string(),
+                driver: node.driver.unwrap_or_else(|| "inconnu".into()),
+            });
+        }
+        info!(path = %node.path.display(), name = %node.name, "loopback existante");
+        return Ok(Loopback { node, created: false });
+    }
+
+    if !options.create || !Path::new(CONTROL_DEVICE).exists() {
+        return Err(if module_loaded() {
+            V4l2Error::Open {
+                path: format!("/dev/video{}", nr),
+                source: io::Error::from(io::ErrorKind::NotFound),
+            }
+        } else {
+            V4l2Error::ModuleNotLoaded
+        });
+    }
+
+    let created = Control::open()?.add(nr, options)?;
+    // Le nœud /dev/videoN est créé par udev juste après l'ioctl
+    let node = (0..50)
+        .find_map(|_| {
+            let node = find(created).filter(|n| n.path.exists());
+            if node.is_none() {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+            }
+            node
+        })
+        .unwrap_or_else(|| VideoNode {
+            nr: created,
+            path: PathBuf::from(format!("/dev/video{}", created)),
+            name: options.label.to_string(),
+            driver: Some(DRIVER.to_string()),
+        });
+    info!(path = %node.path.display(), name = options.label, "loopback créée");
+    Ok(Loopback { node, created: true })
+}
+
+impl Loopback {
+    pub fn remove(self) -> Result<(), V4l2Error> {
+        remove_loopback(self.node.nr)
+    }
+}
+
+pub fn remove_loopback(nr: u16) -> Result<(), V4l2Error> {
+    Control::open()?.remove(nr)?;
+    info!(nr, "loopback /dev/video{} supprimée", nr);
+    Ok(())
+}
+
+fn module_loaded() -> bool {
+    Path::new("/sys/module/v4l2loopback").exists()
+}
+
+// (majeur, mineur) du module chargé
+fn module_version() -> Option<(u32, u32)> {
+    parse_version(&fs::read_to_string(SYSFS_MODULE_VERSION).ok()?)
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().split('.').map(|p| p.parse::<u32>().ok());
+    Some((parts.next()??, parts.next()??))
+}
+
+// Périphérique de contrôle /dev/v4l2loopback
+struct Control {
+    file: fs::File,
+}
+
+impl Control {
+    fn open() -> Result<Self, V4l2Error> {
+        if !Path::new(CONTROL_DEVICE).exists() {
+            return Err(V4l2Error::ModuleNotLoaded);
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(CONTROL_DEVICE)
+            .map_err(|source| V4l2Error::open(CONTROL_DEVICE, source))?;
+        Ok(Self { file })
+    }
+
+    fn add(&self, nr: u16, options: &LoopbackOptions) -> Result<u16, V4l2Error> {
+        let mut card_label = [0u8; 32];
+        let label = options.label.as_bytes();
+        let len = label.len().min(card_label.len() - 1);
+        card_label[..len].copy_from_slice(&label[..len]);
+
+        // announce_all_caps = 0 : exclusive_caps, indispensable pour que Chrome voie une caméra
+        let fd = self.file.as_raw_fd();
+        let ret = if module_version().is_some_and(|v| v < (0, 13)) {
+            let mut config = LoopbackConfigV12 {
+                output_nr: nr as i32,
+                capture_nr: nr as i32,
+                card_label,
+                max_width: options.max_width as u32,
+                max_height: options.max_height as u32,
+                max_buffers: -1,
+                max_openers: -1,
+                debug: 0,
+                announce_all_caps: 0,
+            };
+            ctl(fd, V4L2LOOPBACK_CTL_ADD, &mut config as *mut _ as libc::c_ulong)
+        } else {
+            let mut config = LoopbackConfigV13 {
+                output_nr: nr as i32,
+                capture_nr: nr as i32,
+                card_label,
+                min_width: 0,
+                max_width: options.max_width as u32,
+                min_height: 0,
+                max_height: options.max_height as u32,
+                max_buffers: -1,
+                max_openers: -1,
+                debug: 0,
+                announce_all_caps: 0,
+            };
+            ctl(fd, V4L2LOOPBACK_CTL_ADD, &mut config as *mut _ as libc::c_ulong)
+        };
+        ret.map(|created| created as u16).map_err(|source| control_error("V4L2LOOPBACK_CTL_ADD", source))
+    }
+
+    fn remove(&self, nr: u16) -> Result<(), V4l2Error> {
+        ctl(self.file.as_raw_fd(), V4L2LOOPBACK_CTL_REMOVE, nr as libc::c_ulong)
+            .map(|_| ())
+            .map_err(|source| control_error("V4L2LOOPBACK_CTL_REMOVE", source))
+    }
+}
+
+fn control_error(request: &'static str, source: io::Error) -> V4l2Error {
+    match source.kind() {
+        io::ErrorKind::PermissionDenied => V4l2Error::PermissionDenied { path: CONTROL_DEVICE.to_string() },
+        _ => V4l2Error::Ioctl { request, source },
+    }
+}
+
+// ioctl renvoyant sa valeur de retour (numéro du périphérique créé), relancé sur EINTR
+fn ctl(fd: RawFd, request: libc::c_ulong, arg: libc::c_ulong) -> io::Result<i32> {
+    loop {
+        // SAFETY : `arg` est un entier ou un pointeur vers une config valide pendant l'appel
+        let ret = unsafe { libc::ioctl(fd, request as _, arg) };
+        if ret >= 0 {
+            return Ok(ret);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(std::mem::size_of::<LoopbackConfigV13>() == 72);
+    assert!(std::mem::size_of::<LoopbackConfigV12>() == 64);
+};
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+    use super::*;
+
+    // Arborescence /sys/class/video4linux factice : liens device/driver comme le noyau les crée
+    struct FakeSysfs(PathBuf);
+
+    impl FakeSysfs {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("phonecam-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(root.join("devices/platform/v4l2loopback")).unwrap();
+            fs::create_dir_all(root.join("bus/usb/drivers/uvcvideo")).unwrap();
+            fs::create_dir_all(root.join("bus/usb/1-1")).unwrap();
+            fs::create_dir_all(root.join("class")).unwrap();
+            Self(root)
+        }
+
+        fn class(&self) -> PathBuf {
+            self.0.join("class")
+        }
+
+        fn node(&self, entry: &str, name: Option<&str>, device: Option<&str>, driver: Option<&str>) {
+            let dir = self.class().join(entry);
+            fs::create_dir_all(&dir).unwrap();
+            if let Some(name) = name {
+                fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+            }
+            if let Some(device) = device {
+                let device = self.0.join(device);
+                if let Some(driver) = driver {
+                    let _ = symlink(self.0.join(driver), device.join("driver"));
+                }
+                symlink(device, dir.join("device")).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeSysfs {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn discover_reads_names_and_drivers() {
+        let sysfs = FakeSysfs::new("sysfs-discover");
+        sysfs.node("video10", Some("PhoneCam Ultimate"), Some("devices/platform/v4l2loopback"), None);
+        sysfs.node("video0", Some("Integrated Camera: Integrated C"), Some("bus/usb/1-1"), Some("bus/usb/drivers/uvcvideo"));
+        sysfs.node("video2", None, None, None);
+        // Pas des nœuds vidéo
+        sysfs.node("vbi0", Some("vbi"), None, None);
+        sysfs.node("videoX", Some("?"), None, None);
+
+        let nodes = discover_in(&sysfs.class()).unwrap();
+        assert_eq!(nodes.iter().map(|n| n.nr).collect::<Vec<_>>(), [0, 2, 10]);
+
+        let camera = &nodes[0];
+        assert_eq!(camera.name, "Integrated Camera: Integrated C");
+        assert_eq!(camera.driver.as_deref(), Some("uvcvideo"));
+        assert!(!camera.is_loopback());
+        assert_eq!(camera.path, Path::new("/dev/video0"));
+
+        // Sans nom ni pilote lisible
+        assert_eq!((nodes[1].name.as_str(), nodes[1].driver.as_deref()), ("", None));
+        assert!(!nodes[1].is_loopback());
+
+        // v4l2loopback : pas de lien driver, le périphérique plateforme porte le nom du module
+        let loopback = &nodes[2];
+        assert_eq!(loopback.name, "PhoneCam Ultimate");
+        assert_eq!(loopback.driver.as_deref(), Some("v4l2loopback"));
+        assert!(loopback.is_loopback());
+        assert_eq!(loopback.path, Path::new("/dev/video10"));
+    }
+
+    #[test]
+    fn discover_without_sysfs_is_an_error() {
+        let sysfs = FakeSysfs::new("sysfs-missing");
+        assert!(discover_in(&sysfs.0.join("absent")).is_err());
+        assert!(discover_in(&sysfs.class()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn module_version_keeps_major_minor() {
+        assert_eq!(parse_version("0.12.7\n"), Some((0, 12)));
+        assert_eq!(parse_version("0.13.2"), Some((0, 13)));
+        assert!(parse_version("0.13.2").unwrap() >= (0, 13));
+        assert_eq!(parse_version("1"), None);
+        assert_eq!(parse_version("git"), None);
+    }
+
+    #[test]
+    fn permission_errors_name_the_control_device() {
+        let denied = control_error("V4L2LOOPBACK_CTL_ADD", io::Error::from_raw_os_error(libc::EACCES));
+        assert!(matches!(&denied, V4l2Error::PermissionDenied { path } if path == CONTROL_DEVICE));
+        let busy = control_error("V4L2LOOPBACK_CTL_REMOVE", io::Error::from_raw_os_error(libc::EBUSY));
+        assert!(matches!(busy, V4l2Error::Ioctl { request: "V4L2LOOPBACK_CTL_REMOVE", .. }));
+    }
+}
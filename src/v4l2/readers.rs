@@ -0,0 +1,166 @@
+// Présence de lecteurs sur /dev/videoN : processus (autres que nous) ayant le nœud ouvert,
+// trouvés en parcourant /proc/*/fd. v4l2loopback ne l'expose ni dans sysfs ni par un
+// événement standard ; le coût du parcours reste négligeable à une seconde d'intervalle.
+// Limite : sans droits root, les processus d'un autre utilisateur ne sont pas visibles.
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct ReaderMonitor {
+    path: String,
+    readers: AtomicUsize,
+}
+
+impl ReaderMonitor {
+    // Premier comptage synchrone, puis thread de surveillance (arrêté avec le dernier Arc)
+    pub fn spawn(path: &str) -> Arc<Self> {
+        let monitor = Arc::new(Self {
+            path: path.to_string(),
+            readers: AtomicUsize::new(count_readers(path)),
+        });
+
+        let weak = Arc::downgrade(&monitor);
+        let spawned = std::thread::Builder::new()
+            .name("pc-readers".into())
+            .spawn(move || watch(weak));
+        if let Err(e) = spawned {
+            // Sans surveillance, on considère la sortie comme toujours regardée
+            warn!(error = %e, "détection des lecteurs indisponible");
+            monitor.readers.store(usize::MAX, Ordering::Relaxed);
+        }
+        monitor
+    }
+
+    pub fn readers(&self) -> usize {
+        self.readers.load(Ordering::Relaxed)
+    }
+
+    pub fn is_watched(&self) -> bool {
+        self.readers() > 0
+    }
+}
+
+fn watch(monitor: Weak<ReaderMonitor>) {
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let Some(monitor) = monitor.upgrade() else {
+            break;
+        };
+        let count = count_readers(&monitor.path);
+        let previous = monitor.readers.swap(count, Ordering::Relaxed);
+        match (previous, count) {
+            (p, c) if p == c => {}
+            (_, 0) => info!(path = %monitor.path, "plus aucun lecteur : décodage réduit aux keyframes"),
+            (0, c) => info!(path = %monitor.path, readers = c, "lecteur présent : reprise du décodage complet"),
+            (_, c) => info!(path = %monitor.path, readers = c, "lecteurs"),
+        }
+    }
+}
+
+// Nombre de processus, hors le nôtre, ayant `path` ouvert
+fn count_readers(path: &str) -> usize {
+    count_readers_in(Path::new("/proc"), path, &std::process::id().to_string())
+}
+
+fn count_readers_in(proc: &Path, path: &str, own_pid: &str) -> usize {
+    let Ok(procs) = fs::read_dir(proc) else {
+        return 0;
+    };
+
+    procs
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.bytes().all(|b| b.is_ascii_digit()) && name != own_pid
+        })
+        .filter(|entry| {
+            // EACCES pour les processus d'autres utilisateurs : ignorés
+            let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+                return false;
+            };
+            fds.filter_map(Result::ok)
+                .any(|fd| fs::read_link(fd.path()).is_ok_and(|target| target.as_os_str() == path))
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+    use super::*;
+
+    struct FakeProc(PathBuf);
+
+    impl FakeProc {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("phonecam-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        // Processus `pid` avec ses descripteurs ouverts sur `targets`
+        fn process(&self, pid: &str, targets: &[&str]) {
+            let fd = self.0.join(pid).join("fd");
+            fs::create_dir_all(&fd).unwrap();
+            for (n, target) in targets.iter().enumerate() {
+                symlink(target, fd.join(n.to_string())).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeProc {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn counts_processes_not_descriptors() {
+        let proc = FakeProc::new("proc-count");
+        proc.process("100", &["/dev/null", "/dev/video10"]);
+        // Deux descripteurs sur le nœud : un seul lecteur
+        proc.process("101", &["/dev/video10", "/dev/video10"]);
+        proc.process("102", &["/dev/video2", "/dev/video100"]);
+        // Nous-mêmes, et les entrées qui ne sont pas des processus
+        proc.process("42", &["/dev/video10"]);
+        proc.process("self", &["/dev/video10"]);
+        // Processus sans fd lisible (autre utilisateur)
+        fs::create_dir_all(proc.0.join("103")).unwrap();
+
+        assert_eq!(count_readers_in(&proc.0, "/dev/video10", "42"), 2);
+        assert_eq!(count_readers_in(&proc.0, "/dev/video2", "42"), 1);
+        assert_eq!(count_readers_in(&proc.0, "/dev/video3", "42"), 0);
+        assert_eq!(count_readers_in(&proc.0.join("absent"), "/dev/video10", "42"), 0);
+    }
+
+    #[test]
+    fn sees_another_process_in_proc() {
+        let proc = FakeProc::new("proc-real");
+        let node = proc.0.join("video-node");
+        fs::write(&node, b"").unwrap();
+        let path = node.to_str().unwrap();
+        // Ouvert par nous seuls : pas un lecteur
+        let _own = fs::File::open(&node).unwrap();
+        assert_eq!(count_readers(path), 0);
+
+        let mut child = Command::new("sleep")
+            .arg("5")
+            .stdin(fs::File::open(&node).unwrap())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let readers = count_readers(path);
+        let _ = child.kill();
+        let _ = child.wait();
+        assert_eq!(readers, 1);
+    }
+}
@@ -0,0 +1,207 @@
+// Bindings écrits à la main du sous-ensemble de <linux/videodev2.h> utilisé pour la sortie.
+// Tailles vérifiées pour x86_64 / aarch64 (voir les assertions en bas de fichier).
+#![allow(non_camel_case_types)]
+
+use std::io;
+use std::os::unix::io::RawFd;
+
+pub const V4L2_BUF_TYPE_VIDEO_OUTPUT: u32 = 2;
+
+pub const V4L2_MEMORY_MMAP: u32 = 1;
+pub const V4L2_MEMORY_DMABUF: u32 = 4;
+
+pub const V4L2_FIELD_NONE: u32 = 1;
+
+pub const V4L2_CAP_VIDEO_OUTPUT: u32 = 0x0000_0002;
+pub const V4L2_CAP_READWRITE: u32 = 0x0100_0000;
+pub const V4L2_CAP_STREAMING: u32 = 0x0400_0000;
+pub const V4L2_CAP_DEVICE_CAPS: u32 = 0x8000_0000;
+
+pub const V4L2_COLORSPACE_SMPTE170M: u32 = 1;
+pub const V4L2_COLORSPACE_REC709: u32 = 3;
+pub const V4L2_COLORSPACE_SRGB: u32 = 8;
+
+pub const V4L2_YCBCR_ENC_601: u32 = 1;
+pub const V4L2_YCBCR_ENC_709: u32 = 2;
+
+pub const V4L2_QUANTIZATION_FULL_RANGE: u32 = 1;
+pub const V4L2_QUANTIZATION_LIM_RANGE: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct v4l2_capability {
+    pub driver: [u8; 16],
+    pub card: [u8; 32],
+    pub bus_info: [u8; 32],
+    pub version: u32,
+    pub capabilities: u32,
+    pub device_caps: u32,
+    pub reserved: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct v4l2_pix_format {
+    pub width: u32,
+    pub height: u32,
+    pub pixelformat: u32,
+    pub field: u32,
+    pub bytesperline: u32,
+    pub sizeimage: u32,
+    pub colorspace: u32,
+    pub priv_: u32,
+    pub flags: u32,
+    // union { ycbcr_enc, hsv_enc }
+    pub ycbcr_enc: u32,
+    pub quantization: u32,
+    pub xfer_func: u32,
+}
+
+// v4l2_window contient des pointeurs : l'union est alignée sur 8 octets
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union v4l2_format_fmt {
+    pub pix: v4l2_pix_format,
+    pub raw_data: [u8; 200],
+    _align: [u64; 25],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct v4l2_format {
+    pub type_: u32,
+    pub fmt: v4l2_format_fmt,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct v4l2_requestbuffers {
+    pub count: u32,
+    pub type_: u32,
+    pub memory: u32,
+    pub capabilities: u32,
+    pub flags: u8,
+    pub reserved: [u8; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct v4l2_timecode {
+    pub type_: u32,
+    pub flags: u32,
+    pub frames: u8,
+    pub seconds: u8,
+    pub minutes: u8,
+    pub hours: u8,
+    pub userbits: [u8; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union v4l2_buffer_m {
+    pub offset: u32,
+    pub userptr: libc::c_ulong,
+    pub planes: *mut libc::c_void,
+    pub fd: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct v4l2_buffer {
+    pub index: u32,
+    pub type_: u32,
+    pub bytesused: u32,
+    pub flags: u32,
+    pub field: u32,
+    pub timestamp: libc::timeval,
+    pub timecode: v4l2_timecode,
+    pub sequence: u32,
+    pub memory: u32,
+    pub m: v4l2_buffer_m,
+    pub length: u32,
+    pub reserved2: u32,
+    // union { request_fd, reserved }
+    pub request_fd: i32,
+}
+
+// Structures initialisées à zéro, comme le veut l'API V4L2 (champs réservés compris)
+pub fn zeroed<T: Copy>() -> T {
+    // SAFETY : utilisé uniquement pour les structures C ci-dessus, valides à zéro
+    unsafe { std::mem::zeroed() }
+}
+
+// Équivalents des macros _IOR / _IOW / _IOWR
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+// `kind` : lettre du sous-système ('V' pour V4L2, 'b' pour dma-buf…)
+pub const fn ioc_kind(dir: u32, kind: u8, nr: u32, size: usize) -> libc::c_ulong {
+    ((dir << 30) | ((size as u32) << 16) | ((kind as u32) << 8) | nr) as libc::c_ulong
+}
+
+pub const fn iow_kind<T>(kind: u8, nr: u32) -> libc::c_ulong {
+    ioc_kind(IOC_WRITE, kind, nr, std::mem::size_of::<T>())
+}
+
+pub const fn iowr_kind<T>(kind: u8, nr: u32) -> libc::c_ulong {
+    ioc_kind(IOC_READ | IOC_WRITE, kind, nr, std::mem::size_of::<T>())
+}
+
+const fn ioc(dir: u32, nr: u32, size: usize) -> libc::c_ulong {
+    ioc_kind(dir, b'V', nr, size)
+}
+
+const fn ior<T>(nr: u32) -> libc::c_ulong {
+    ioc(IOC_READ, nr, std::mem::size_of::<T>())
+}
+
+const fn iow<T>(nr: u32) -> libc::c_ulong {
+    ioc(IOC_WRITE, nr, std::mem::size_of::<T>())
+}
+
+const fn iowr<T>(nr: u32) -> libc::c_ulong {
+    ioc(IOC_READ | IOC_WRITE, nr, std::mem::size_of::<T>())
+}
+
+pub const VIDIOC_QUERYCAP: libc::c_ulong = ior::<v4l2_capability>(0);
+pub const VIDIOC_G_FMT: libc::c_ulong = iowr::<v4l2_format>(4);
+pub const VIDIOC_S_FMT: libc::c_ulong = iowr::<v4l2_format>(5);
+pub const VIDIOC_REQBUFS: libc::c_ulong = iowr::<v4l2_requestbuffers>(8);
+pub const VIDIOC_QUERYBUF: libc::c_ulong = iowr::<v4l2_buffer>(9);
+pub const VIDIOC_QBUF: libc::c_ulong = iowr::<v4l2_buffer>(15);
+pub const VIDIOC_DQBUF: libc::c_ulong = iowr::<v4l2_buffer>(17);
+pub const VIDIOC_STREAMON: libc::c_ulong = iow::<libc::c_int>(18);
+pub const VIDIOC_STREAMOFF: libc::c_ulong = iow::<libc::c_int>(19);
+
+// ioctl relancé sur EINTR
+pub fn ioctl<T>(fd: RawFd, request: libc::c_ulong, arg: &mut T) -> io::Result<()> {
+    ioctl_ret(fd, request, arg).map(|_| ())
+}
+
+// Variante qui renvoie la valeur de retour (ex. descripteur créé par l'ioctl)
+pub fn ioctl_ret<T>(fd: RawFd, request: libc::c_ulong, arg: &mut T) -> io::Result<i32> {
+    loop {
+        // SAFETY : `arg` est la structure attendue par `request` (cf. constantes des appelants)
+        let ret = unsafe { libc::ioctl(fd, request as _, arg as *mut T) };
+        if ret >= 0 {
+            return Ok(ret);
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    assert!(std::mem::size_of::<v4l2_capability>() == 104);
+    assert!(std::mem::size_of::<v4l2_pix_format>() == 48);
+    assert!(std::mem::size_of::<v4l2_format>() == 208);
+    assert!(std::mem::size_of::<v4l2_requestbuffers>() == 20);
+    assert!(std::mem::size_of::<v4l2_buffer>() == 88);
+    assert!(VIDIOC_QUERYCAP == 0x8068_5600);
+    assert!(VIDIOC_S_FMT == 0xc0d0_5605);
+    assert!(VIDIOC_QBUF == 0xc058_560f);
+    assert!(VIDIOC_STREAMON == 0x4004_5612);
+};
@@ -1,23 +1,29 @@
 use axum::{
     routing::get,
     Router,
-    extract::ws::{WebSocketUpgrade, WebSocket, Message},
+    extract::{ConnectInfo, ws::{close_code, CloseFrame, WebSocketUpgrade, WebSocket, Message}},
     response::IntoResponse,
 };
-use tower_http::services::ServeDir;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::net::UdpSocket;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
+use tracing::{info, info_span, warn, Instrument};
+use crate::config::Config;
+use crate::shutdown::{self, Shutdown};
 
-pub async fn start_server_without_pipeline(http_port: u16, udp_port: u16, metrics: Arc<crate::metrics::ServerMetrics>) {
-    let (tx, _rx) = broadcast::channel::<Vec<u8>>(16);
-    let video_tx = Arc::new(tx);
-    
-    let metrics_filter = metrics.clone();
-    let video_tx_filter = video_tx.clone();
-    
-    let app = Router::new()
+// Configuration courante, republiée à chaque rechargement du fichier
+pub type ConfigWatch = watch::Receiver<Arc<Config>>;
+
+// Routes communes aux deux modes : pages, codecs, statistiques et aperçu du dashboard
+fn base_router(
+    config: ConfigWatch,
+    metrics: Arc<crate::metrics::ServerMetrics>,
+    video_tx: Arc<broadcast::Sender<Vec<u8>>>,
+    shutdown: Shutdown,
+) -> Router {
+    let stats_shutdown = shutdown.clone();
+    Router::new()
         .route("/", get(|| async {
             axum::response::Html(include_str!("../../web/index.html"))
         }))
@@ -30,126 +36,326 @@ pub async fn start_server_without_pipeline(http_port: u16, udp_port: u16, metric
         .route("/dashboard", get(|| async {
             axum::response::Html(include_str!("../../web/dashboard.html"))
         }))
+        .route("/codecs", get(|| async {
+            axum::Json(crate::pipeline::codec::VideoCodec::supported())
+        }))
+        .route("/metrics", {
+            let m = metrics.clone();
+            get(move |headers: axum::http::HeaderMap| async move { render_metrics(&m, &headers) })
+        })
         .route("/stats", get(move |ws: WebSocketUpgrade| {
-            let m = metrics_filter.clone();
+            let (m, c, s) = (metrics.clone(), config.clone(), stats_shutdown.clone());
             async move {
-                ws.on_upgrade(move |socket| handle_stats_ws(socket, m))
+                ws.on_upgrade(move |socket| s.clone().track(handle_stats_ws(socket, m, c, s)))
             }
         }))
         .route("/video", get(move |ws: WebSocketUpgrade| {
-            let tx = video_tx_filter.clone();
+            let (tx, s) = (video_tx.clone(), shutdown.clone());
             async move {
-                ws.on_upgrade(move |socket| handle_video_ws(socket, tx))
+                ws.on_upgrade(move |socket| s.clone().track(handle_video_ws(socket, tx, s)))
             }
         }))
-        .route("/raw", get(move |ws: WebSocketUpgrade| {
-            let tx = video_tx.clone();
-            let m = metrics.clone();
-            async move {
-                ws.on_upgrade(move |socket| handle_ws_simple(socket, udp_port, tx, m))
-            }
-        }));
+}
 
+// Rend la main à la demande d'arrêt, une fois les requêtes HTTP en cours terminées ; les
+// WebSockets (détachés par axum) sont suivis par Shutdown et se ferment d'eux-mêmes
+async fn serve(http_port: u16, app: Router, shutdown: Shutdown) -> std::io::Result<()> {
     let addr = SocketAddr::from(([0, 0, 0, 0], http_port));
     
-    println!("⚠️  Mode HTTP - L'accès caméra nécessite HTTPS sur mobile !");
-    println!("🌐 Serveur Web : http://{}", addr);
+    warn!("mode HTTP : l'accès caméra nécessite HTTPS sur mobile");
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    // Adresse effective : le port peut avoir été choisi par le système (http_port = 0)
+    info!(addr = %listener.local_addr()?, "serveur web à l'écoute");
     
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    // Axum 0.7+ gère automatiquement TCP_NODELAY
-    axum::serve(listener, app).await.unwrap();
+    // Axum 0.7+ gère automatiquement TCP_NODELAY ; l'adresse du client étiquette les métriques de session
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(async move { shutdown.requested().await })
+        .await
 }
 
-pub async fn start_server(http_port: u16, udp_port: u16, metrics: Arc<crate::metrics::ServerMetrics>, pipeline: Arc<crate::pipeline::Pipeline>) {
-    let (tx, _rx) = broadcast::channel::<Vec<u8>>(16);
+pub async fn start_server_without_pipeline(config: ConfigWatch, metrics: Arc<crate::metrics::ServerMetrics>, shutdown: Shutdown) -> std::io::Result<()> {
+    let http_port = config.borrow().server.http_port;
+    let (tx, _rx) = broadcast::channel::<Vec<u8>>(config.borrow().server.broadcast_capacity);
     let video_tx = Arc::new(tx);
     
-    let metrics_filter = metrics.clone();
-    let video_tx_filter = video_tx.clone();
-    
-    let app = Router::new()
-        // ... (routes inchangées jusqu'à /raw)
-        .route("/raw", get(move |ws: WebSocketUpgrade| {
-            let tx = video_tx.clone();
-            let m = metrics.clone();
-            let p = pipeline.clone();
-            async move {
-                ws.on_upgrade(move |socket| handle_ws(socket, udp_port, tx, m, p))
-            }
-        }));
+    let app = base_router(config.clone(), metrics.clone(), video_tx.clone(), shutdown.clone())
+        .route("/raw", {
+            let shutdown = shutdown.clone();
+            get(move |ws: WebSocketUpgrade, ConnectInfo(peer): ConnectInfo<SocketAddr>| {
+                let (tx, c, s) = (video_tx.clone(), config.clone(), shutdown.clone());
+                let session = metrics.open_session(peer.to_string());
+                let span = info_span!("session", id = session.id(), %peer);
+                async move {
+                    ws.on_upgrade(move |socket| s.clone().track(handle_ws_simple(socket, c, tx, session, s).instrument(span)))
+                }
+            })
+        });
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], http_port));
-    
-    println!("⚠️  Mode HTTP - L'accès caméra nécessite HTTPS sur mobile !");
-    println!("🌐 Serveur Web : http://{}", addr);
+    serve(http_port, app, shutdown).await
+}
+
+pub async fn start_server(
+    config: ConfigWatch,
+    metrics: Arc<crate::metrics::ServerMetrics>,
+    pipeline: Arc<crate::pipeline::Pipeline>,
+    shutdown: Shutdown,
+) -> std::io::Result<()> {
+    let http_port = config.borrow().server.http_port;
+    let (tx, _rx) = broadcast::channel::<Vec<u8>>(config.borrow().server.broadcast_capacity);
+    let video_tx = Arc::new(tx);
     
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    let app = base_router(config.clone(), metrics.clone(), video_tx.clone(), shutdown.clone())
+        .route("/orientation", {
+            let (get_p, post_p) = (pipeline.clone(), pipeline.clone());
+            get(move || async move { axum::Json(get_p.orientation().status()) })
+                .post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    set_orientation(&post_p, body)
+                })
+        })
+        .route("/record", {
+            let (get_p, post_p) = (pipeline.clone(), pipeline.clone());
+            get(move || async move { axum::Json(get_p.recorder().status()) })
+                .post(move |axum::Json(body): axum::Json<serde_json::Value>| async move {
+                    set_recording(&post_p, body)
+                })
+        })
+        .route("/raw", {
+            let shutdown = shutdown.clone();
+            get(move |ws: WebSocketUpgrade, ConnectInfo(peer): ConnectInfo<SocketAddr>| {
+                let (tx, c, s) = (video_tx.clone(), config.clone(), shutdown.clone());
+                let session = metrics.open_session(peer.to_string());
+                let span = info_span!("session", id = session.id(), %peer);
+                let p = pipeline.clone();
+                async move {
+                    ws.on_upgrade(move |socket| s.clone().track(handle_ws(socket, c, tx, session, p, s).instrument(span)))
+                }
+            })
+        });
+
+    serve(http_port, app, shutdown).await
+}
+
+// Forçage depuis le dashboard : {"rotation": 90, "flip_h": true, "flip_v": false}, ou {"auto": true}
+fn set_orientation(pipeline: &crate::pipeline::Pipeline, body: serde_json::Value) -> impl IntoResponse {
+    use axum::http::StatusCode;
+    let control = pipeline.orientation();
+
+    if body["auto"] == true {
+        control.set_manual(None);
+    } else {
+        match serde_json::from_value::<crate::codec::rotate::Orientation>(body) {
+            Ok(orientation) => control.set_manual(Some(orientation)),
+            Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        }
+    }
+    axum::Json(control.status()).into_response()
+}
+
+// {"action": "start"} ou {"action": "stop"}
+fn set_recording(pipeline: &crate::pipeline::Pipeline, body: serde_json::Value) -> impl IntoResponse {
+    use axum::http::StatusCode;
+    let recorder = pipeline.recorder();
+
+    match body["action"].as_str() {
+        Some("start") => match recorder.start() {
+            Ok(status) => axum::Json(status).into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        },
+        Some("stop") => axum::Json(recorder.stop()).into_response(),
+        _ => (StatusCode::BAD_REQUEST, "action attendue : start ou stop").into_response(),
+    }
 }
 
-async fn handle_stats_ws(mut socket: WebSocket, metrics: Arc<crate::metrics::ServerMetrics>) {
-    let mut interval = tokio::time::interval(std::time::Duration::from_millis(500));
+// Scrape Prometheus ; OpenMetrics si le scraper le demande dans Accept
+fn render_metrics(metrics: &crate::metrics::ServerMetrics, headers: &axum::http::HeaderMap) -> impl IntoResponse {
+    use crate::metrics::prometheus::{self, Format};
+    let accept = headers.get(axum::http::header::ACCEPT).and_then(|v| v.to_str().ok());
+    let format = Format::negotiate(accept);
+    (
+        [(axum::http::header::CONTENT_TYPE, format.content_type())],
+        prometheus::render(&metrics.snapshot(), format),
+    )
+}
+
+async fn handle_stats_ws(mut socket: WebSocket, metrics: Arc<crate::metrics::ServerMetrics>, mut config: ConfigWatch, shutdown: Shutdown) {
+    let mut interval = tokio::time::interval(config.borrow_and_update().stats_interval());
     loop {
-        interval.tick().await;
-        let snapshot = metrics.snapshot();
-        let json = serde_json::to_string(&snapshot).unwrap();
-        if socket.send(Message::Text(json.into())).await.is_err() {
-            break;
+        tokio::select! {
+            _ = shutdown.requested() => {
+                close_socket(socket).await;
+                return;
+            }
+            _ = interval.tick() => {
+                let snapshot = metrics.snapshot();
+                let json = serde_json::to_string(&snapshot).unwrap();
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+            // Période modifiée dans le fichier de configuration
+            Ok(()) = config.changed() => {
+                let period = config.borrow_and_update().stats_interval();
+                if period != interval.period() {
+                    interval = tokio::time::interval(period);
+                }
+            }
         }
     }
 }
 
-async fn handle_video_ws(mut socket: WebSocket, tx: Arc<broadcast::Sender<Vec<u8>>>) {
+// Arrêt du serveur : 1001 "going away", puis attente (bornée) de la trame Close du client
+async fn close_socket(mut socket: WebSocket) {
+    let frame = CloseFrame {
+        code: close_code::AWAY,
+        reason: "arrêt du serveur".into(),
+    };
+    if socket.send(Message::Close(Some(frame))).await.is_err() {
+        return;
+    }
+    let _ = tokio::time::timeout(shutdown::CLOSE_TIMEOUT, async {
+        while let Some(Ok(msg)) = socket.recv().await {
+            if matches!(msg, Message::Close(_)) {
+                break;
+            }
+        }
+    })
+    .await;
+}
+
+// Plafond de débit pour l'encodeur du téléphone ; null : celui de son profil
+fn bitrate_message(max_bps: Option<u64>) -> Message {
+    Message::Text(serde_json::json!({ "type": "bitrate", "max_bps": max_bps }).to_string())
+}
+
+async fn handle_video_ws(mut socket: WebSocket, tx: Arc<broadcast::Sender<Vec<u8>>>, shutdown: Shutdown) {
     let mut rx = tx.subscribe();
-    while let Ok(data) = rx.recv().await {
-        if socket.send(Message::Binary(data.into())).await.is_err() {
-            break;
+    loop {
+        tokio::select! {
+            _ = shutdown.requested() => {
+                close_socket(socket).await;
+                return;
+            }
+            received = rx.recv() => {
+                let Ok(data) = received else { break };
+                if socket.send(Message::Binary(data)).await.is_err() {
+                    break;
+                }
+            }
         }
     }
 }
 
 async fn handle_ws(
     mut socket: WebSocket, 
-    udp_port: u16, 
+    mut config: ConfigWatch,
     video_tx: Arc<broadcast::Sender<Vec<u8>>>, 
-    metrics: Arc<crate::metrics::ServerMetrics>,
-    pipeline: Arc<crate::pipeline::Pipeline>
+    session: crate::metrics::session::SessionHandle,
+    pipeline: Arc<crate::pipeline::Pipeline>,
+    shutdown: Shutdown,
 ) {
-    let udp_socket = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    info!("session ouverte");
+    let (udp_port, packet_buffer, mut max_bitrate) = {
+        let config = config.borrow_and_update();
+        (config.server.udp_port, config.server.packet_buffer, config.max_bitrate_bps())
+    };
+    let udp_socket = match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(error = %e, "socket UDP de relais indisponible, session refusée");
+            return;
+        }
+    };
     let target_addr: SocketAddr = format!("127.0.0.1:{}", udp_port).parse().unwrap();
 
-    let mut buf = vec![0u8; 65536];
+    let mut buf = vec![0u8; packet_buffer];
+    if max_bitrate.is_some() && socket.send(bitrate_message(max_bitrate)).await.is_err() {
+        return;
+    }
+    
+    // Estimation du décalage d'horloge téléphone/serveur pour la latence glass-to-glass
+    let mut clock = crate::net::clock::ClockSync::new();
+    let mut ping_interval = tokio::time::interval(std::time::Duration::from_secs(2));
+    // Lecteurs de la caméra virtuelle : sans personne, le téléphone n'envoie plus que ses keyframes
+    let mut viewers_interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut last_viewers: Option<bool> = None;
     
     loop {
         tokio::select! {
+            _ = shutdown.requested() => {
+                close_socket(socket).await;
+                info!("session fermée par l'arrêt du serveur");
+                return;
+            }
+            _ = ping_interval.tick() => {
+                if socket.send(Message::Text(clock.ping_message())).await.is_err() {
+                    break;
+                }
+            }
+            Ok(()) = config.changed() => {
+                let bitrate = config.borrow_and_update().max_bitrate_bps();
+                if bitrate != max_bitrate {
+                    max_bitrate = bitrate;
+                    info!(max_bps = bitrate, "plafond de débit envoyé au téléphone");
+                    if socket.send(bitrate_message(bitrate)).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = viewers_interval.tick() => {
+                let Some(readers) = pipeline.readers() else { continue };
+                let watched = readers > 0;
+                if last_viewers != Some(watched) {
+                    last_viewers = Some(watched);
+                    let msg = serde_json::json!({ "type": "viewers", "readers": readers, "watched": watched });
+                    if socket.send(Message::Text(msg.to_string())).await.is_err() {
+                        break;
+                    }
+                }
+            }
             msg = socket.recv() => {
                 if let Some(Ok(msg)) = msg {
                     match msg {
                         Message::Binary(bin) => {
+                            session.record_packet(bin.len() as u64);
+
                             // 1. Envoyer vers UDP (legacy)
                             let _ = udp_socket.send_to(&bin, target_addr).await;
                             
                             // 2. Diffuser vers le dashboard
                             let _ = video_tx.send(bin.to_vec());
                             
-                            // 3. Passer dans la pipeline de décodage + V4L2
-                            let p = pipeline.clone();
-                            let b = bin.clone();
-                            let m = metrics.clone();
-                            tokio::spawn(async move {
-                                let snapshot = m.snapshot();
-                                let _ = p.process_chunk(&b, snapshot.width as usize, snapshot.height as usize).await;
-                            });
+                            // 3. Passer dans la pipeline de décodage + V4L2 (file bornée, jamais bloquant)
+                            if !pipeline.push_chunk(bin, clock.estimate()) {
+                                session.record_loss();
+                            }
                         }
                         Message::Text(text) => {
                             // Métadonnées JSON
                             if let Ok(val) = serde_json::from_str::<serde_json::Value>(&text) {
                                 if val["type"] == "metadata" {
                                     if let (Some(w), Some(h)) = (val["width"].as_u64(), val["height"].as_u64()) {
-                                        metrics.update_resolution(w, h);
+                                        session.update_resolution(w, h);
+                                    }
+                                } else if val["type"] == "pong" {
+                                    if let Some(estimate) = clock.on_pong(&val) {
+                                        session.update_clock(estimate.offset_us, estimate.rtt_us);
+                                    }
+                                } else if val["type"] == "orientation" {
+                                    pipeline.orientation().on_phone_metadata(&val);
+                                } else if val["type"] == "v-config" {
+                                    // Négociation du codec pour cette session
+                                    match crate::pipeline::codec::CodecConfig::from_v_config(&val) {
+                                        Some(config) => {
+                                            info!(codec = config.codec.name(), width = config.width, height = config.height, "codec négocié");
+                                            session.set_codec(config.codec.name());
+                                            pipeline.configure(config).await
+                                        }
+                                        None => warn!(codec = %val["codec"], "codec non supporté"),
                                     }
                                 }
                             }
+                            // Relayer le message texte au dashboard (config décodeur de l'aperçu)
+                            let _ = video_tx.send(text.into_bytes());
                         }
                         _ => {}
                     }
@@ -167,32 +373,59 @@ async fn handle_ws(
             }
         }
     }
+    info!("session fermée");
 }
 
 async fn handle_ws_simple(
     mut socket: WebSocket, 
-    udp_port: u16, 
+    mut config: ConfigWatch,
     video_tx: Arc<broadcast::Sender<Vec<u8>>>, 
-    metrics: Arc<crate::metrics::ServerMetrics>
+    session: crate::metrics::session::SessionHandle,
+    shutdown: Shutdown,
 ) {
-    let udp_socket = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    info!("session ouverte");
+    let (udp_port, packet_buffer, mut max_bitrate) = {
+        let config = config.borrow_and_update();
+        (config.server.udp_port, config.server.packet_buffer, config.max_bitrate_bps())
+    };
+    let udp_socket = match UdpSocket::bind("0.0.0.0:0").await {
+        Ok(socket) => socket,
+        Err(e) => {
+            warn!(error = %e, "socket UDP de relais indisponible, session refusée");
+            return;
+        }
+    };
     let target_addr: SocketAddr = format!("127.0.0.1:{}", udp_port).parse().unwrap();
 
-    let mut buf = vec![0u8; 65536];
+    let mut buf = vec![0u8; packet_buffer];
+    if max_bitrate.is_some() && socket.send(bitrate_message(max_bitrate)).await.is_err() {
+        return;
+    }
     
     loop {
         tokio::select! {
+            _ = shutdown.requested() => {
+                close_socket(socket).await;
+                info!("session fermée par l'arrêt du serveur");
+                return;
+            }
+            Ok(()) = config.changed() => {
+                let bitrate = config.borrow_and_update().max_bitrate_bps();
+                if bitrate != max_bitrate {
+                    max_bitrate = bitrate;
+                    info!(max_bps = bitrate, "plafond de débit envoyé au téléphone");
+                    if socket.send(bitrate_message(bitrate)).await.is_err() {
+                        break;
+                    }
+                }
+            }
             msg = socket.recv() => {
                 if let Some(Ok(msg)) = msg {
                     match msg {
                         Message::Binary(bin) => {
-                            // Log toutes les 60 frames
-                            static FRAME_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
-                            let count = FRAME_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                            if count % 60 == 0 {
-                                println!("[WS] 📹 {} chunks reçus ({} bytes), broadcast vers dashboard", count, bin.len());
-                            }
-                            
+                            // Compté dans la session : résumé périodique plutôt qu'un log par paquet
+                            session.record_packet(bin.len() as u64);
+
                             let _ = udp_socket.send_to(&bin, target_addr).await;
                             let _ = video_tx.send(bin.to_vec());
                         }
@@ -200,7 +433,7 @@ async fn handle_ws_simple(
                             if let Ok(val) = serde_json::from_str::<serde_json::Value>(&text) {
                                 if val["type"] == "metadata" {
                                     if let (Some(w), Some(h)) = (val["width"].as_u64(), val["height"].as_u64()) {
-                                        metrics.update_resolution(w, h);
+                                        session.update_resolution(w, h);
                                     }
                                 }
                             }
@@ -222,4 +455,5 @@ async fn handle_ws_simple(
             }
         }
     }
+    info!("session fermée");
 }
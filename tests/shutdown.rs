@@ -0,0 +1,130 @@
+// Arrêt sur SIGTERM du binaire complet : trame Close 1001 aux WebSockets ouverts, puis sortie
+// propre (code 0) dans le délai que s'accorde main (sessions puis vidage de la pipeline).
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use futures_util::StreamExt;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+
+// shutdown::SESSIONS_TIMEOUT + shutdown::DRAIN_TIMEOUT (crate binaire : constantes non importables)
+const SHUTDOWN_BUDGET: Duration = Duration::from_secs(3 + 5);
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+struct Server {
+    child: Child,
+    dir: PathBuf,
+}
+
+impl Server {
+    // Sortie fichier (pas de loopback V4L2), configuration vide isolée de celle de l'utilisateur.
+    // Ports choisis par le système : pas de course avec un autre processus
+    fn spawn() -> Self {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("shutdown-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        fs::write(&config, "").unwrap();
+
+        let child = Command::new(env!("CARGO_BIN_EXE_phonecam-ultimate"))
+            .arg("--config")
+            .arg(&config)
+            .args(["--http-port", "0", "--udp-port", "0"])
+            .arg(format!("--sink=file:{}", dir.join("out.y4m").display()))
+            .env("XDG_CONFIG_HOME", &dir)
+            .stdout(Stdio::null())
+            .stderr(fs::File::create(dir.join("stderr.log")).unwrap())
+            .spawn()
+            .unwrap();
+        Self { child, dir }
+    }
+
+    fn terminate(&self) {
+        // SAFETY : simple envoi de signal au processus enfant
+        assert_eq!(unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGTERM) }, 0);
+    }
+
+    async fn wait_exit(&mut self, timeout: Duration) -> Option<ExitStatus> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait().unwrap() {
+                return Some(status);
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        None
+    }
+
+    // Adresse effective du serveur web, publiée dans les logs une fois le port lié
+    async fn http_addr(&mut self) -> SocketAddr {
+        let started = Instant::now();
+        loop {
+            let log = self.log();
+            let addr = log
+                .lines()
+                .filter(|line| line.contains("serveur web à l'écoute"))
+                .find_map(|line| line.split_whitespace().find_map(|field| field.strip_prefix("addr=")));
+            if let Some(addr) = addr {
+                return addr.parse().unwrap_or_else(|e| panic!("adresse {:?} illisible ({}) :\n{}", addr, e, log));
+            }
+            assert!(started.elapsed() < STARTUP_TIMEOUT, "serveur jamais à l'écoute :\n{}", log);
+            assert!(self.child.try_wait().unwrap().is_none(), "serveur arrêté au démarrage :\n{}", log);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    fn log(&self) -> String {
+        fs::read_to_string(self.dir.join("stderr.log")).unwrap_or_default()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[tokio::test]
+async fn sigterm_closes_websockets_and_exits_cleanly() {
+    let mut server = Server::spawn();
+
+    // Serveur prêt quand /stats accepte la connexion
+    let url = format!("ws://127.0.0.1:{}/stats", server.http_addr().await.port());
+    let started = Instant::now();
+    let mut socket = loop {
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((socket, _)) => break socket,
+            Err(e) => {
+                assert!(started.elapsed() < STARTUP_TIMEOUT, "serveur injoignable ({}) :\n{}", e, server.log());
+                assert!(server.child.try_wait().unwrap().is_none(), "serveur arrêté au démarrage :\n{}", server.log());
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    };
+    // Session établie côté serveur : le premier instantané est arrivé
+    let first = tokio::time::timeout(STARTUP_TIMEOUT, socket.next()).await.expect("aucun instantané /stats");
+    assert!(matches!(first, Some(Ok(Message::Text(_)))), "{:?}", first);
+
+    server.terminate();
+    let terminated = Instant::now();
+
+    let close = tokio::time::timeout(SHUTDOWN_BUDGET, async {
+        while let Some(msg) = socket.next().await {
+            if let Message::Close(frame) = msg.expect("WebSocket coupé sans trame Close") {
+                return frame;
+            }
+        }
+        None
+    })
+    .await
+    .expect("pas de trame Close après SIGTERM");
+    let close = close.expect("trame Close sans code");
+    assert_eq!(close.code, CloseCode::Away, "{:?}", close);
+    drop(socket);
+
+    let status = server.wait_exit(SHUTDOWN_BUDGET.saturating_sub(terminated.elapsed())).await;
+    let status = status.unwrap_or_else(|| panic!("pas de sortie {:?} après SIGTERM :\n{}", SHUTDOWN_BUDGET, server.log()));
+    assert_eq!(status.code(), Some(0), "{}", server.log());
+}
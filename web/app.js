@@ -1,3 +1,13 @@
+// Codecs WebCodecs par ordre de préférence (le plus efficace d'abord).
+// `id` correspond aux noms renvoyés par /codecs côté serveur.
+const CODEC_CANDIDATES = [
+    { id: 'av1', label: 'AV1', codec: 'av01.0.08M.08', bitrateFactor: 0.6 },
+    { id: 'hevc', label: 'HEVC', codec: 'hvc1.1.6.L120.B0', bitrateFactor: 0.6 },
+    { id: 'vp9', label: 'VP9', codec: 'vp09.00.40.08', bitrateFactor: 0.7 },
+    { id: 'vp8', label: 'VP8', codec: 'vp8', bitrateFactor: 1.0 },
+    { id: 'h264', label: 'H.264', codec: 'avc1.42001f', fallback: 'avc1.42E01E', bitrateFactor: 1.0 },
+];
+
 class PhoneCamUltimate {
     constructor() {
         this.socket = null;
@@ -10,6 +20,13 @@ class PhoneCamUltimate {
         this.startTime = performance.now(); // Kept from original
         this.currentWidth = 1280;
         this.currentHeight = 720;
+        // timestamp VideoFrame -> horloge murale de capture (µs), pour la latence glass-to-glass
+        this.captureTimes = new Map();
+        // Personne ne lit la caméra virtuelle côté PC : on n'encode plus que les keyframes
+        this.unwatched = false;
+        // Plafond de débit imposé par le serveur (bit/s, null : celui du profil)
+        this.maxBitrate = null;
+        this.encoderConfig = null;
         // Assuming setupDynamicControls() is meant to be called here based on the provided snippet
         // However, the original code calls it later in start().
         // For now, I will add it as per the instruction's snippet, but this might need review.
@@ -52,6 +69,7 @@ class PhoneCamUltimate {
             // Reset des compteurs pour un calcul de FPS précis
             this.frameCount = 0;
             this.startTime = performance.now();
+            this.unwatched = false;
 
             // Vérification de la disponibilité de getUserMedia
             if (!navigator.mediaDevices || !navigator.mediaDevices.getUserMedia) {
@@ -92,6 +110,68 @@ class PhoneCamUltimate {
         }
     }
 
+    // Horloge murale en µs (même référence que les pong envoyés au serveur)
+    wallClockUs() {
+        return Math.round((performance.timeOrigin + performance.now()) * 1000);
+    }
+
+    rememberCaptureTime(frame) {
+        this.captureTimes.set(frame.timestamp, this.wallClockUs());
+        // Borne la table si l'encodeur saute des frames
+        if (this.captureTimes.size > 120) {
+            const oldest = this.captureTimes.keys().next().value;
+            this.captureTimes.delete(oldest);
+        }
+    }
+
+    handleServerMessage(event) {
+        if (typeof event.data !== 'string') return;
+        try {
+            const msg = JSON.parse(event.data);
+            // Synchronisation d'horloge : on renvoie l'heure locale de réception
+            if (msg.type === 'ping') {
+                this.sendMetadata({
+                    type: 'pong',
+                    id: msg.id,
+                    server_us: msg.server_us,
+                    client_us: this.wallClockUs()
+                });
+            } else if (msg.type === 'viewers') {
+                this.unwatched = !msg.watched;
+                this.log(msg.watched
+                    ? `[VIEWERS] 👀 ${msg.readers} lecteur(s) : flux complet`
+                    : '[VIEWERS] 💤 Aucun lecteur : keyframes seules');
+            } else if (msg.type === 'bitrate') {
+                this.maxBitrate = msg.max_bps;
+                this.applyBitrateCap();
+            }
+        } catch (e) { /* Pas du JSON */ }
+    }
+
+    // Débit du profil, borné par le plafond du serveur
+    cappedConfig(config) {
+        const bitrate = this.maxBitrate ? Math.min(config.bitrate, this.maxBitrate) : config.bitrate;
+        return { ...config, bitrate };
+    }
+
+    // Reconfiguration à chaud : l'encodeur repart d'une keyframe et renvoie sa v-config
+    applyBitrateCap() {
+        if (!this.encoder || this.encoder.state !== 'configured' || !this.encoderConfig) return;
+        const config = this.cappedConfig(this.encoderConfig);
+        this.encoder.configure(config);
+        this.log(`[ENCODER] 🎚️ Débit ${Math.round(config.bitrate / 1000)} kbit/s`);
+    }
+
+    // Angle de l'écran + caméra utilisée : le serveur en déduit rotation et miroir
+    sendOrientation() {
+        const angle = (screen.orientation && screen.orientation.angle) || window.orientation || 0;
+        this.sendMetadata({
+            type: 'orientation',
+            angle: (angle + 360) % 360,
+            facing: document.getElementById('cameraSelect').value
+        });
+    }
+
     sendMetadata(data) {
         if (this.socket && this.socket.readyState === WebSocket.OPEN) {
             this.socket.send(JSON.stringify(data));
@@ -148,6 +228,7 @@ class PhoneCamUltimate {
                         height: this.currentHeight
                     });
                     this.socket.send(meta);
+                    this.sendOrientation();
                     resolve();
                 };
                 this.socket.onerror = (err) => {
@@ -158,6 +239,7 @@ class PhoneCamUltimate {
                     document.getElementById('status').innerText = '❌ Erreur de connexion WebSocket';
                     reject(err);
                 };
+                this.socket.onmessage = (event) => this.handleServerMessage(event);
                 this.socket.onclose = () => {
                     this.log('[WS] WebSocket fermé');
                     document.getElementById('status').innerText = '🔌 WebSocket déconnecté';
@@ -168,6 +250,7 @@ class PhoneCamUltimate {
             // Juste envoyer les nouvelles métadonnées
             const meta = JSON.stringify({ type: 'metadata', width, height });
             this.socket.send(meta);
+            this.sendOrientation();
         }
 
         document.getElementById('status').innerText = '🎥 Encodage vidéo...';
@@ -190,14 +273,18 @@ class PhoneCamUltimate {
 
         this.log('[ENCODER] Initialisation avec résolution réelle...');
 
+        // Codecs décodables par le serveur (ordre de préférence : AV1 > HEVC > VP9 > VP8 > H.264)
+        const serverCodecs = await this.fetchServerCodecs();
+        const codecs = CODEC_CANDIDATES.filter(c => serverCodecs.includes(c.id));
+        this.log(`[ENCODER] Codecs serveur: ${codecs.map(c => c.label).join(', ')}`);
+
         // Génération dynamique des configurations pour supporter Portrait/Paysage
         const isPortrait = realHeight > realWidth;
-        const tryConfigs = [];
+        const variants = [];
 
         // 1. Essayer la résolution native (celle capturée)
         // On réduit un peu le bitrate pour aider l'encodeur mobile
-        tryConfigs.push({
-            codec: 'avc1.42001f',
+        variants.push({
             width: realWidth,
             height: realHeight,
             bitrate: isPortrait ? 3_000_000 : 5_000_000,
@@ -205,8 +292,7 @@ class PhoneCamUltimate {
             profile: 'Natif 60fps'
         });
 
-        tryConfigs.push({
-            codec: 'avc1.42001f',
+        variants.push({
             width: realWidth,
             height: realHeight,
             bitrate: 2_500_000,
@@ -218,8 +304,7 @@ class PhoneCamUltimate {
         // Cela garde le ratio exact !
         const halfW = Math.floor(realWidth / 2) & ~1; // Pair
         const halfH = Math.floor(realHeight / 2) & ~1; // Pair
-        tryConfigs.push({
-            codec: 'avc1.42E01E',
+        variants.push({
             width: halfW,
             height: halfH,
             bitrate: 1_500_000,
@@ -230,12 +315,25 @@ class PhoneCamUltimate {
         // 3. Fallbacks standards mais orientés correctement
         if (isPortrait) {
             // Portrait Fallbacks
-            tryConfigs.push({ codec: 'avc1.42E01E', width: 720, height: 1280, bitrate: 2_000_000, framerate: 30, profile: '720p Portrait' });
-            tryConfigs.push({ codec: 'avc1.42E01E', width: 360, height: 640, bitrate: 800_000, framerate: 30, profile: '360p Portrait' });
+            variants.push({ width: 720, height: 1280, bitrate: 2_000_000, framerate: 30, profile: '720p Portrait' });
+            variants.push({ width: 360, height: 640, bitrate: 800_000, framerate: 30, profile: '360p Portrait' });
         } else {
             // Landscape Fallbacks
-            tryConfigs.push({ codec: 'avc1.42E01E', width: 1280, height: 720, bitrate: 2_000_000, framerate: 30, profile: '720p Landscape' });
-            tryConfigs.push({ codec: 'avc1.42E01E', width: 640, height: 360, bitrate: 800_000, framerate: 30, profile: '360p Landscape' });
+            variants.push({ width: 1280, height: 720, bitrate: 2_000_000, framerate: 30, profile: '720p Landscape' });
+            variants.push({ width: 640, height: 360, bitrate: 800_000, framerate: 30, profile: '360p Landscape' });
+        }
+
+        // Pour chaque palier de résolution, tenter d'abord les codecs les plus efficaces
+        const tryConfigs = [];
+        for (const variant of variants) {
+            for (const candidate of codecs) {
+                tryConfigs.push({
+                    ...variant,
+                    codec: variant.profile.startsWith('Natif') ? candidate.codec : (candidate.fallback || candidate.codec),
+                    bitrate: Math.round(variant.bitrate * candidate.bitrateFactor),
+                    profile: `${candidate.label} ${variant.profile}`
+                });
+            }
         }
 
         let success = false;
@@ -244,16 +342,20 @@ class PhoneCamUltimate {
                 this.log(`[ENCODER] 🧪 Test ${config.profile}: ${config.width}x${config.height} @ ${config.framerate}fps`);
                 this.encoder = new VideoEncoder({
                     output: (chunk, metadata) => {
-                        if (metadata && metadata.decoderConfig && metadata.decoderConfig.description) {
-                            const desc = Array.from(new Uint8Array(metadata.decoderConfig.description));
+                        // VP8/VP9 (et AV1 selon le navigateur) n'ont pas de description
+                        if (metadata && metadata.decoderConfig) {
+                            const decoderConfig = metadata.decoderConfig;
+                            const desc = decoderConfig.description
+                                ? Array.from(new Uint8Array(decoderConfig.description))
+                                : null;
                             this.sendMetadata({
                                 type: 'v-config',
-                                codec: config.codec,
+                                codec: decoderConfig.codec || config.codec,
                                 width: config.width,
                                 height: config.height,
                                 description: desc
                             });
-                            this.log('[ENCODER] 📤 Config AVC envoyée');
+                            this.log(`[ENCODER] 📤 Config ${config.profile} envoyée`);
                         }
                         this.handleEncodedChunk(chunk);
                     },
@@ -265,7 +367,8 @@ class PhoneCamUltimate {
 
                 const support = await VideoEncoder.isConfigSupported(config);
                 if (support.supported) {
-                    this.encoder.configure(config);
+                    this.encoderConfig = config;
+                    this.encoder.configure(this.cappedConfig(config));
                     this.log(`[ENCODER] ✅ SUCCÈS ${config.profile}: ${config.width}x${config.height}`);
                     this.currentWidth = config.width;
                     this.currentHeight = config.height;
@@ -305,6 +408,17 @@ class PhoneCamUltimate {
         }
     }
 
+    async fetchServerCodecs() {
+        try {
+            const res = await fetch('/codecs');
+            const codecs = await res.json();
+            if (Array.isArray(codecs) && codecs.length > 0) return codecs;
+        } catch (e) {
+            this.log(`[ENCODER] ⚠️ /codecs indisponible: ${e.message}`);
+        }
+        return ['h264'];
+    }
+
     setupDynamicControls() {
         const cameraSelect = document.getElementById('cameraSelect');
         const resSelect = document.getElementById('resSelect');
@@ -324,6 +438,13 @@ class PhoneCamUltimate {
             const cameraMode = cameraSelect.value;
             await this.initStream(cameraMode, width, height);
         };
+
+        const onRotate = () => { if (this.isStreaming) this.sendOrientation(); };
+        if (screen.orientation) {
+            screen.orientation.addEventListener('change', onRotate);
+        } else {
+            window.addEventListener('orientationchange', onRotate);
+        }
     }
 
     stop() {
@@ -364,7 +485,9 @@ class PhoneCamUltimate {
                 // Keyframe toutes les secondes (60 frames à 60fps)
                 const keyFrame = (this.frameCount % 60) === 0;
 
-                if (this.encoder && this.encoder.state === 'configured') {
+                // Sans lecteur côté PC, une image par seconde suffit (et ménage la batterie)
+                if (this.encoder && this.encoder.state === 'configured' && (keyFrame || !this.unwatched)) {
+                    this.rememberCaptureTime(frame);
                     this.encoder.encode(frame, { keyFrame });
                 }
 
@@ -397,7 +520,10 @@ class PhoneCamUltimate {
                 // Forcer la première frame à être une keyframe
                 const keyFrame = this.frameCount === 0 || (this.frameCount % 60) === 0;
 
-                if (this.encoder && this.encoder.state === 'configured') {
+                if (this.unwatched && !keyFrame) {
+                    this.frameCount++;
+                } else if (this.encoder && this.encoder.state === 'configured') {
+                    this.rememberCaptureTime(frame);
                     this.encoder.encode(frame, { keyFrame });
                     this.frameCount++;
 
@@ -436,19 +562,29 @@ class PhoneCamUltimate {
         }
 
         const length = chunk.byteLength;
-        const packet = new Uint8Array(8 + length);
+        const captureUs = this.captureTimes.get(chunk.timestamp);
+        this.captureTimes.delete(chunk.timestamp);
+        const hasTimestamp = captureUs !== undefined;
+        const headerSize = hasTimestamp ? 16 : 8;
+        const packet = new Uint8Array(headerSize + length);
+        const view = new DataView(packet.buffer);
 
         // Header (8 bytes)
         packet[0] = 0x50; // 'P'
         packet[1] = 0x43; // 'C'
         packet[2] = chunk.type === 'key' ? 1 : 0;
-        packet[3] = 0; // Flags
+        packet[3] = hasTimestamp ? 0x01 : 0; // Flags (0x01 = horodatage de capture)
 
         // Payload length (u32 BE)
-        new DataView(packet.buffer).setUint32(4, length, false);
+        view.setUint32(4, length, false);
+
+        // Horodatage de capture (u64 BE, µs horloge murale du téléphone)
+        if (hasTimestamp) {
+            view.setBigUint64(8, BigInt(captureUs), false);
+        }
 
         // Copy NAL data (ASYNC!)
-        await chunk.copyTo(packet.subarray(8));
+        await chunk.copyTo(packet.subarray(headerSize));
 
         if (this.socket && this.socket.readyState === WebSocket.OPEN) {
             this.socket.send(packet);